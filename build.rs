@@ -11,17 +11,148 @@
 // WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the License for the specific language governing permissions and
 // limitations under the License.
+use prost::Message;
+use prost_types::field_descriptor_proto::{Label, Type};
+use prost_types::{DescriptorProto, FileDescriptorSet};
+use std::path::PathBuf;
+use std::process::Command;
+
+const PROTOS: &[&str] = &[
+    "opentelemetry-proto/opentelemetry/proto/collector/logs/v1/logs_service.proto",
+    "opentelemetry-proto/opentelemetry/proto/collector/metrics/v1/metrics_service.proto",
+    "opentelemetry-proto/opentelemetry/proto/metrics/experimental/metrics_config_service.proto",
+    "opentelemetry-proto/opentelemetry/proto/collector/trace/v1/trace_service.proto",
+];
+
+const INCLUDES: &[&str] = &["opentelemetry-proto"];
+
+const TRACE_CONFIG_PROTO: &str =
+    "opentelemetry-proto/opentelemetry/proto/trace/v1/trace_config.proto";
+
+const LEGACY_PROTOS: &[&str] = &[
+    "opentelemetry-proto-legacy/opentelemetry/proto/collector/logs/v1/logs_service.proto",
+    "opentelemetry-proto-legacy/opentelemetry/proto/collector/metrics/v1/metrics_service.proto",
+    "opentelemetry-proto-legacy/opentelemetry/proto/collector/trace/v1/trace_service.proto",
+];
+
+const LEGACY_INCLUDES: &[&str] = &["opentelemetry-proto-legacy"];
+
 fn main() {
+    let mut protos = PROTOS.to_vec();
+    if std::env::var_os("CARGO_FEATURE_OTEL_TRACE_CONFIG").is_some() {
+        protos.push(TRACE_CONFIG_PROTO);
+    }
+    let mut builder = tonic_build::configure()
+        .build_client(true)
+        .build_server(true)
+        .format(false);
+    if std::env::var_os("CARGO_FEATURE_OTEL_JSON").is_some() {
+        builder = json_attributes(builder, &protos);
+    }
+    builder.compile(&protos, INCLUDES).unwrap();
+    if std::env::var_os("CARGO_FEATURE_OTEL_LEGACY").is_some() {
+        legacy();
+    }
+}
+
+// The legacy protos share package names with the current ones, so they are
+// generated into their own directory and only as messages.
+fn legacy() {
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR").unwrap()).join("legacy");
+    std::fs::create_dir_all(&out_dir).unwrap();
     tonic_build::configure()
-    .build_client(true)
-    .build_server(true)
-    .format(false)
-    .compile(&[
-        "opentelemetry-proto/opentelemetry/proto/collector/logs/v1/logs_service.proto",
-        "opentelemetry-proto/opentelemetry/proto/collector/metrics/v1/metrics_service.proto",
-        "opentelemetry-proto/opentelemetry/proto/metrics/experimental/metrics_config_service.proto",
-        "opentelemetry-proto/opentelemetry/proto/collector/trace/v1/trace_service.proto",
-    ], &[
-        "opentelemetry-proto"
-    ]).unwrap();
+        .build_client(false)
+        .build_server(false)
+        .format(false)
+        .out_dir(out_dir)
+        .compile(LEGACY_PROTOS, LEGACY_INCLUDES)
+        .unwrap();
+}
+
+// Adds serde attributes implementing the OTLP/JSON mapping. Paths are given
+// without the leading `.` so they only match the exact message, oneof or field.
+fn json_attributes(mut builder: tonic_build::Builder, protos: &[&str]) -> tonic_build::Builder {
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR").unwrap());
+    let descriptor_path = out_dir.join("otel_json_descriptor.bin");
+    let status = Command::new(prost_build::protoc())
+        .arg("--include_imports")
+        .arg(format!(
+            "--descriptor_set_out={}",
+            descriptor_path.display()
+        ))
+        .args(INCLUDES.iter().map(|include| format!("-I{}", include)))
+        .arg(format!("-I{}", prost_build::protoc_include().display()))
+        .args(protos)
+        .status()
+        .unwrap();
+    assert!(status.success(), "protoc failed to build descriptors");
+    let descriptors = FileDescriptorSet::decode(&*std::fs::read(descriptor_path).unwrap()).unwrap();
+    for file in descriptors.file {
+        for message in &file.message_type {
+            builder = json_message_attributes(builder, file.package(), message);
+        }
+    }
+    builder
+}
+
+fn json_message_attributes(
+    mut builder: tonic_build::Builder,
+    parent: &str,
+    message: &DescriptorProto,
+) -> tonic_build::Builder {
+    let path = format!("{}.{}", parent, message.name());
+    builder = builder.type_attribute(
+        &path,
+        "#[derive(serde::Serialize, serde::Deserialize)]\n#[serde(rename_all = \"camelCase\", default)]",
+    );
+    for (index, oneof) in message.oneof_decl.iter().enumerate() {
+        let synthetic = message
+            .field
+            .iter()
+            .any(|f| f.oneof_index == Some(index as i32) && f.proto3_optional());
+        if !synthetic {
+            let oneof_path = format!("{}.{}", path, oneof.name());
+            builder = builder
+                .type_attribute(
+                    &oneof_path,
+                    "#[derive(serde::Serialize, serde::Deserialize)]\n#[serde(rename_all = \"camelCase\")]",
+                )
+                .field_attribute(&oneof_path, "#[serde(flatten)]");
+        }
+    }
+    for field in &message.field {
+        let with = match field.r#type() {
+            Type::Int64 | Type::Sint64 | Type::Sfixed64 => "i64_string",
+            Type::Uint64 | Type::Fixed64 => "u64_string",
+            Type::Bytes
+                if field.name().ends_with("trace_id") || field.name().ends_with("span_id") =>
+            {
+                "hex_bytes"
+            }
+            Type::Bytes => "base64_bytes",
+            _ => continue,
+        };
+        let with = if field.label() == Label::Repeated {
+            format!("{}_seq", with)
+        } else {
+            with.to_string()
+        };
+        let field_path = match field.oneof_index {
+            Some(index) if !field.proto3_optional() => format!(
+                "{}.{}.{}",
+                path,
+                message.oneof_decl[index as usize].name(),
+                field.name()
+            ),
+            _ => format!("{}.{}", path, field.name()),
+        };
+        builder = builder.field_attribute(
+            &field_path,
+            format!("#[serde(with = \"crate::json::{}\")]", with),
+        );
+    }
+    for nested in &message.nested_type {
+        builder = json_message_attributes(builder, &path, nested);
+    }
+    builder
 }
@@ -0,0 +1,352 @@
+// Copyright 2020-2022, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A map view of repeated `KeyValue` attributes with indexed lookup, unique
+//! keys and the attribute limits of the OpenTelemetry specification.
+//!
+//! Inserting an existing key replaces its value in place, so converting a
+//! `Vec<KeyValue>` keeps the first position and the last value of duplicate
+//! keys. Attributes beyond the count limit are dropped and counted, string
+//! and byte values beyond the length limit are truncated.
+//!
+//! ```ignore
+//! let limits = AttributeLimits::new().max_count(128).max_value_length(1024);
+//! span.limit_attributes(&limits);
+//!
+//! let mut attributes = Attributes::from(span.attributes.clone());
+//! attributes.insert("http.status_code", 200);
+//! span.set_attributes(attributes);
+//! ```
+
+use crate::opentelemetry::proto::common::v1::{any_value, AnyValue, KeyValue};
+use std::collections::HashMap;
+use std::iter::FromIterator;
+
+/// Limits on the number of attributes and the length of their values
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AttributeLimits {
+    max_count: Option<usize>,
+    max_value_length: Option<usize>,
+}
+
+impl AttributeLimits {
+    /// Creates limits that allow any number of attributes of any length
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum number of attributes, further attributes are dropped
+    pub fn max_count(mut self, count: usize) -> Self {
+        self.max_count = Some(count);
+        self
+    }
+
+    /// Sets the maximum length of string values, in characters, and of byte
+    /// values, in bytes, including those in arrays
+    pub fn max_value_length(mut self, length: usize) -> Self {
+        self.max_value_length = Some(length);
+        self
+    }
+
+    fn truncate(&self, value: &mut AnyValue) {
+        let max = match self.max_value_length {
+            Some(max) => max,
+            None => return,
+        };
+        match &mut value.value {
+            Some(any_value::Value::StringValue(string)) => {
+                if let Some((index, _)) = string.char_indices().nth(max) {
+                    string.truncate(index);
+                }
+            }
+            Some(any_value::Value::BytesValue(bytes)) => bytes.truncate(max),
+            Some(any_value::Value::ArrayValue(array)) => {
+                for value in &mut array.values {
+                    self.truncate(value);
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Attributes with unique keys, in insertion order
+#[derive(Clone, Debug, Default)]
+pub struct Attributes {
+    // Removed attributes leave a gap, so indices stay valid until the gaps
+    // make up more than half of the entries and are compacted away
+    entries: Vec<Option<KeyValue>>,
+    index: HashMap<String, usize>,
+    limits: AttributeLimits,
+    dropped: u32,
+}
+
+impl Attributes {
+    /// Creates an empty, unlimited attribute map
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty attribute map with limits
+    pub fn with_limits(limits: AttributeLimits) -> Self {
+        Attributes {
+            limits,
+            ..Self::default()
+        }
+    }
+
+    /// The value of an attribute
+    pub fn get(&self, key: &str) -> Option<&AnyValue> {
+        let index = *self.index.get(key)?;
+        self.entries[index]
+            .as_ref()
+            .and_then(|attribute| attribute.value.as_ref())
+    }
+
+    /// Checks if there is an attribute with the key
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.index.contains_key(key)
+    }
+
+    /// Inserts an attribute, returning the value it replaced
+    ///
+    /// A new attribute is dropped, and counted, when the map is at its count limit.
+    pub fn insert<K: Into<String>, V: Into<AnyValue>>(
+        &mut self,
+        key: K,
+        value: V,
+    ) -> Option<AnyValue> {
+        self.insert_key_value(KeyValue {
+            key: key.into(),
+            value: Some(value.into()),
+            ..KeyValue::default()
+        })
+    }
+
+    fn insert_key_value(&mut self, mut attribute: KeyValue) -> Option<AnyValue> {
+        if let Some(value) = &mut attribute.value {
+            self.limits.truncate(value);
+        }
+        if let Some(index) = self.index.get(&attribute.key) {
+            let previous = self.entries[*index].replace(attribute);
+            return previous.and_then(|previous| previous.value);
+        }
+        if self.limits.max_count.map_or(false, |max| self.len() >= max) {
+            self.dropped = self.dropped.saturating_add(1);
+            return None;
+        }
+        self.index.insert(attribute.key.clone(), self.entries.len());
+        self.entries.push(Some(attribute));
+        None
+    }
+
+    /// Removes an attribute, returning its value
+    pub fn remove(&mut self, key: &str) -> Option<AnyValue> {
+        let index = self.index.remove(key)?;
+        let removed = self.entries[index].take();
+        if self.entries.len() - self.index.len() > self.entries.len() / 2 {
+            self.compact();
+        }
+        removed.and_then(|attribute| attribute.value)
+    }
+
+    // Removes the gaps, keeping the insertion order
+    fn compact(&mut self) {
+        self.entries.retain(Option::is_some);
+        for (position, attribute) in self.entries.iter().flatten().enumerate() {
+            if let Some(index) = self.index.get_mut(&attribute.key) {
+                *index = position;
+            }
+        }
+    }
+
+    /// The attributes in insertion order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Option<&AnyValue>)> {
+        self.entries
+            .iter()
+            .flatten()
+            .map(|attribute| (attribute.key.as_str(), attribute.value.as_ref()))
+    }
+
+    /// The number of attributes
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Checks if there are no attributes
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// The number of attributes dropped because of the count limit
+    pub fn dropped_count(&self) -> u32 {
+        self.dropped
+    }
+}
+
+impl From<Vec<KeyValue>> for Attributes {
+    fn from(attributes: Vec<KeyValue>) -> Self {
+        attributes.into_iter().collect()
+    }
+}
+
+impl From<Attributes> for Vec<KeyValue> {
+    fn from(attributes: Attributes) -> Self {
+        attributes.entries.into_iter().flatten().collect()
+    }
+}
+
+impl FromIterator<KeyValue> for Attributes {
+    fn from_iter<I: IntoIterator<Item = KeyValue>>(iter: I) -> Self {
+        let mut attributes = Attributes::new();
+        attributes.extend(iter);
+        attributes
+    }
+}
+
+impl Extend<KeyValue> for Attributes {
+    fn extend<I: IntoIterator<Item = KeyValue>>(&mut self, iter: I) {
+        for attribute in iter {
+            self.insert_key_value(attribute);
+        }
+    }
+}
+
+/// Messages with attributes and a dropped attributes count
+pub trait Attributed {
+    /// The attributes and the dropped attributes count of the message
+    fn attribute_fields(&mut self) -> (&mut Vec<KeyValue>, &mut u32);
+
+    /// Replaces the attributes, adding the attributes the map dropped to the
+    /// dropped attributes count
+    fn set_attributes(&mut self, attributes: Attributes) {
+        let dropped = attributes.dropped_count();
+        let (fields, dropped_count) = self.attribute_fields();
+        *fields = attributes.into();
+        *dropped_count = dropped_count.saturating_add(dropped);
+    }
+
+    /// Removes duplicate keys, keeping the last value, and applies the limits
+    fn limit_attributes(&mut self, limits: &AttributeLimits) {
+        let (fields, _) = self.attribute_fields();
+        let mut attributes = Attributes::with_limits(*limits);
+        attributes.extend(std::mem::take(fields));
+        self.set_attributes(attributes);
+    }
+}
+
+impl Attributed for crate::opentelemetry::proto::common::v1::InstrumentationScope {
+    fn attribute_fields(&mut self) -> (&mut Vec<KeyValue>, &mut u32) {
+        (&mut self.attributes, &mut self.dropped_attributes_count)
+    }
+}
+
+#[cfg(feature = "opentelemetry-proto-resource-v1")]
+impl Attributed for crate::opentelemetry::proto::resource::v1::Resource {
+    fn attribute_fields(&mut self) -> (&mut Vec<KeyValue>, &mut u32) {
+        (&mut self.attributes, &mut self.dropped_attributes_count)
+    }
+}
+
+#[cfg(feature = "opentelemetry-proto-trace-v1")]
+mod trace {
+    use super::Attributed;
+    use crate::opentelemetry::proto::common::v1::KeyValue;
+    use crate::opentelemetry::proto::trace::v1::span::{Event, Link};
+    use crate::opentelemetry::proto::trace::v1::Span;
+
+    impl Attributed for Span {
+        fn attribute_fields(&mut self) -> (&mut Vec<KeyValue>, &mut u32) {
+            (&mut self.attributes, &mut self.dropped_attributes_count)
+        }
+    }
+
+    impl Attributed for Event {
+        fn attribute_fields(&mut self) -> (&mut Vec<KeyValue>, &mut u32) {
+            (&mut self.attributes, &mut self.dropped_attributes_count)
+        }
+    }
+
+    impl Attributed for Link {
+        fn attribute_fields(&mut self) -> (&mut Vec<KeyValue>, &mut u32) {
+            (&mut self.attributes, &mut self.dropped_attributes_count)
+        }
+    }
+}
+
+#[cfg(feature = "opentelemetry-proto-logs-v1")]
+impl Attributed for crate::opentelemetry::proto::logs::v1::LogRecord {
+    fn attribute_fields(&mut self) -> (&mut Vec<KeyValue>, &mut u32) {
+        (&mut self.attributes, &mut self.dropped_attributes_count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(attributes: &Attributes) -> Vec<&str> {
+        attributes.iter().map(|(key, _)| key).collect()
+    }
+
+    #[test]
+    fn keeps_first_position_and_last_value_of_duplicates() {
+        let mut attributes = Attributes::new();
+        attributes.insert("a", 1);
+        attributes.insert("b", 2);
+        assert_eq!(attributes.insert("a", 3), Some(AnyValue::from(1)));
+        assert_eq!(keys(&attributes), vec!["a", "b"]);
+        assert_eq!(attributes.get("a"), Some(&AnyValue::from(3)));
+    }
+
+    #[test]
+    fn compacts_removed_attributes() {
+        let mut attributes = Attributes::new();
+        for round in 0..100 {
+            attributes.insert("kept", round);
+            attributes.insert("removed", round);
+            assert_eq!(attributes.remove("removed"), Some(AnyValue::from(round)));
+            assert!(attributes.entries.len() <= 3);
+        }
+        assert_eq!(attributes.remove("removed"), None);
+        assert_eq!(keys(&attributes), vec!["kept"]);
+
+        for key in &["a", "b", "c", "d", "e"] {
+            attributes.insert(*key, *key);
+        }
+        attributes.remove("kept");
+        attributes.remove("b");
+        attributes.remove("d");
+        assert!(attributes.entries.len() <= 2 * attributes.len());
+        assert_eq!(keys(&attributes), vec!["a", "c", "e"]);
+        assert_eq!(attributes.get("e"), Some(&AnyValue::from("e")));
+        attributes.insert("c", "C");
+        assert_eq!(attributes.get("c"), Some(&AnyValue::from("C")));
+        assert_eq!(attributes.len(), 3);
+    }
+
+    #[test]
+    fn applies_limits() {
+        let limits = AttributeLimits::new().max_count(2).max_value_length(3);
+        let mut attributes = Attributes::with_limits(limits);
+        attributes.insert("a", "abcdef");
+        attributes.insert("b", "äöüß");
+        attributes.insert("c", "dropped");
+        attributes.insert("a", "replaced");
+        assert_eq!(attributes.get("a"), Some(&AnyValue::from("rep")));
+        assert_eq!(attributes.get("b"), Some(&AnyValue::from("äöü")));
+        assert!(!attributes.contains_key("c"));
+        assert_eq!(attributes.dropped_count(), 1);
+    }
+}
@@ -0,0 +1,365 @@
+// Copyright 2020-2022, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An `Authenticator` inspects the metadata and connection of an export request
+//! and either names its `Principal` or rejects it, usually with `UNAUTHENTICATED`.
+//! The principal is stored in the request extensions, from where the `all`
+//! forwarders attach it to their events.
+//!
+//! ```ignore
+//! let tokens = BearerTokenAuthenticator::new().token("s3cr3t", "tenant-a");
+//! let svc = tremor_otelapis::auth::authenticated(logs::make_forwarder(tx), tokens);
+//!
+//! tremor_otelapis::all::CollectorBuilder::new(tx)
+//!     .authenticator(ApiKeyAuthenticator::new("x-api-key").key("k3y", "tenant-b"))
+//!     .serve(addr)
+//!     .await?;
+//! ```
+
+use std::sync::Arc;
+use tonic::service::interceptor::InterceptedService;
+use tonic::service::Interceptor;
+use tonic::{Request, Status};
+
+/// The authenticated identity of a client
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Principal(pub String);
+
+impl Principal {
+    /// The name of the principal
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Authenticates export requests
+pub trait Authenticator: Send + Sync + 'static {
+    /// Names the principal of a request, or rejects it
+    fn authenticate(&self, request: &Request<()>) -> Result<Principal, Status>;
+}
+
+// Authentication fns are authenticators
+impl<F> Authenticator for F
+where
+    F: Fn(&Request<()>) -> Result<Principal, Status> + Send + Sync + 'static,
+{
+    fn authenticate(&self, request: &Request<()>) -> Result<Principal, Status> {
+        (self)(request)
+    }
+}
+
+/// Authenticates requests with static tokens in the `authorization: Bearer` header
+///
+/// The scheme is matched case-insensitively and surrounding whitespace ignored.
+#[derive(Clone, Debug, Default)]
+pub struct BearerTokenAuthenticator {
+    tokens: Vec<(String, Principal)>,
+}
+
+impl BearerTokenAuthenticator {
+    /// Creates an authenticator without any accepted tokens
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accepts a token as the specified principal
+    pub fn token<T: Into<String>, P: Into<String>>(mut self, token: T, principal: P) -> Self {
+        self.tokens
+            .push((token.into(), Principal(principal.into())));
+        self
+    }
+}
+
+impl Authenticator for BearerTokenAuthenticator {
+    fn authenticate(&self, request: &Request<()>) -> Result<Principal, Status> {
+        let token = request
+            .metadata()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(bearer_token)
+            .ok_or_else(|| Status::unauthenticated("Missing bearer token"))?;
+        find(&self.tokens, token).ok_or_else(|| Status::unauthenticated("Invalid bearer token"))
+    }
+}
+
+/// Authenticates requests with static API keys in a metadata header
+#[derive(Clone, Debug)]
+pub struct ApiKeyAuthenticator {
+    header: String,
+    keys: Vec<(String, Principal)>,
+}
+
+impl ApiKeyAuthenticator {
+    /// Creates an authenticator reading API keys from the specified header
+    pub fn new<H: Into<String>>(header: H) -> Self {
+        ApiKeyAuthenticator {
+            header: header.into().to_lowercase(),
+            keys: Vec::new(),
+        }
+    }
+
+    /// Accepts an API key as the specified principal
+    pub fn key<K: Into<String>, P: Into<String>>(mut self, key: K, principal: P) -> Self {
+        self.keys.push((key.into(), Principal(principal.into())));
+        self
+    }
+}
+
+impl Authenticator for ApiKeyAuthenticator {
+    fn authenticate(&self, request: &Request<()>) -> Result<Principal, Status> {
+        let key = request
+            .metadata()
+            .get(self.header.as_str())
+            .and_then(|value| value.to_str().ok())
+            .ok_or_else(|| Status::unauthenticated(format!("Missing {} header", self.header)))?;
+        find(&self.keys, key).ok_or_else(|| Status::unauthenticated("Invalid API key"))
+    }
+}
+
+/// Authenticates mutual TLS clients whose certificate is on an allow-list
+#[cfg(feature = "tls")]
+#[derive(Clone, Debug, Default)]
+pub struct ClientCertAuthenticator {
+    certificates: Vec<(Vec<u8>, Principal)>,
+}
+
+#[cfg(feature = "tls")]
+impl ClientCertAuthenticator {
+    /// Creates an authenticator without any allowed certificates
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allows clients presenting the DER encoded certificate as the specified principal
+    pub fn allow<C: Into<Vec<u8>>, P: Into<String>>(mut self, der: C, principal: P) -> Self {
+        self.certificates
+            .push((der.into(), Principal(principal.into())));
+        self
+    }
+}
+
+#[cfg(feature = "tls")]
+impl Authenticator for ClientCertAuthenticator {
+    fn authenticate(&self, request: &Request<()>) -> Result<Principal, Status> {
+        let certs = request
+            .peer_certs()
+            .ok_or_else(|| Status::unauthenticated("Missing client certificate"))?;
+        let leaf = certs
+            .first()
+            .ok_or_else(|| Status::unauthenticated("Missing client certificate"))?;
+        self.certificates
+            .iter()
+            .find(|(der, _)| der.as_slice() == leaf.get_ref())
+            .map(|(_, principal)| principal.clone())
+            .ok_or_else(|| Status::unauthenticated("Client certificate is not allowed"))
+    }
+}
+
+/// A tonic interceptor running an authenticator and storing the principal
+/// in the request extensions
+#[derive(Clone)]
+pub struct AuthInterceptor {
+    authenticator: Option<Arc<dyn Authenticator>>,
+}
+
+impl AuthInterceptor {
+    /// Creates an interceptor for the specified authenticator
+    pub fn new<A: Authenticator>(authenticator: A) -> Self {
+        AuthInterceptor {
+            authenticator: Some(Arc::new(authenticator)),
+        }
+    }
+
+    // Passes requests through unauthenticated without an authenticator
+    #[cfg(feature = "otel-all")]
+    pub(crate) fn optional(authenticator: Option<Arc<dyn Authenticator>>) -> Self {
+        AuthInterceptor { authenticator }
+    }
+}
+
+impl Interceptor for AuthInterceptor {
+    fn call(&mut self, request: Request<()>) -> Result<Request<()>, Status> {
+        match &self.authenticator {
+            Some(authenticator) => authenticate(authenticator.as_ref(), request),
+            None => Ok(request),
+        }
+    }
+}
+
+/// Wraps a tonic service so only authenticated requests reach it
+pub fn authenticated<S, A: Authenticator>(
+    service: S,
+    authenticator: A,
+) -> InterceptedService<S, AuthInterceptor> {
+    InterceptedService::new(service, AuthInterceptor::new(authenticator))
+}
+
+// Runs the authenticator and stores the principal in the request extensions
+pub(crate) fn authenticate(
+    authenticator: &dyn Authenticator,
+    mut request: Request<()>,
+) -> Result<Request<()>, Status> {
+    let principal = authenticator.authenticate(&request)?;
+    request.extensions_mut().insert(principal);
+    Ok(request)
+}
+
+// The token of an `authorization` value with the `Bearer` scheme
+fn bearer_token(value: &str) -> Option<&str> {
+    let value = value.trim();
+    let (scheme, token) = value.split_at(value.find(|c: char| c == ' ' || c == '\t')?);
+    let token = token.trim();
+    if scheme.eq_ignore_ascii_case("bearer") && !token.is_empty() {
+        Some(token)
+    } else {
+        None
+    }
+}
+
+// Compares every credential in constant time, so the time taken does not
+// reveal how much of a credential matched
+fn find(credentials: &[(String, Principal)], presented: &str) -> Option<Principal> {
+    credentials
+        .iter()
+        .fold(None, |found, (credential, principal)| {
+            if constant_time_eq(credential.as_bytes(), presented.as_bytes()) {
+                Some(principal.clone())
+            } else {
+                found
+            }
+        })
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::metadata::MetadataValue;
+
+    fn request(headers: &[(&'static str, &'static str)]) -> Request<()> {
+        let mut request = Request::new(());
+        for (name, value) in headers {
+            request
+                .metadata_mut()
+                .insert(*name, MetadataValue::from_static(value));
+        }
+        request
+    }
+
+    fn principal(
+        authenticator: &dyn Authenticator,
+        headers: &[(&'static str, &'static str)],
+    ) -> Result<String, tonic::Code> {
+        authenticator
+            .authenticate(&request(headers))
+            .map(|principal| principal.0)
+            .map_err(|status| status.code())
+    }
+
+    #[test]
+    fn bearer_tokens() {
+        let tokens = BearerTokenAuthenticator::new()
+            .token("s3cr3t", "tenant-a")
+            .token("other", "tenant-b");
+        for value in &[
+            "Bearer s3cr3t",
+            "bearer s3cr3t",
+            "BEARER  s3cr3t ",
+            " Bearer\ts3cr3t",
+        ] {
+            assert_eq!(
+                principal(&tokens, &[("authorization", value)]),
+                Ok("tenant-a".to_string()),
+                "{:?}",
+                value
+            );
+        }
+        assert_eq!(
+            principal(&tokens, &[("authorization", "Bearer other")]),
+            Ok("tenant-b".to_string())
+        );
+        for value in &[
+            "Bearer wrong",
+            "Bearer s3cr3",
+            "Bearer s3cr3t2",
+            "Bearer",
+            "Bearer ",
+            "Basic s3cr3t",
+            "Bearers3cr3t",
+            "s3cr3t",
+        ] {
+            assert_eq!(
+                principal(&tokens, &[("authorization", value)]),
+                Err(tonic::Code::Unauthenticated),
+                "{:?}",
+                value
+            );
+        }
+        assert_eq!(principal(&tokens, &[]), Err(tonic::Code::Unauthenticated));
+        assert_eq!(
+            principal(
+                &BearerTokenAuthenticator::new(),
+                &[("authorization", "Bearer ")]
+            ),
+            Err(tonic::Code::Unauthenticated)
+        );
+    }
+
+    #[test]
+    fn api_keys() {
+        let keys = ApiKeyAuthenticator::new("X-Api-Key").key("k3y", "tenant-b");
+        assert_eq!(
+            principal(&keys, &[("x-api-key", "k3y")]),
+            Ok("tenant-b".to_string())
+        );
+        assert_eq!(
+            principal(&keys, &[("x-api-key", "k3y2")]),
+            Err(tonic::Code::Unauthenticated)
+        );
+        assert_eq!(
+            principal(&keys, &[("authorization", "k3y")]),
+            Err(tonic::Code::Unauthenticated)
+        );
+        assert_eq!(principal(&keys, &[]), Err(tonic::Code::Unauthenticated));
+    }
+
+    #[test]
+    fn custom_authenticators() {
+        let deny = |_: &Request<()>| Err(Status::permission_denied("No access"));
+        assert_eq!(principal(&deny, &[]), Err(tonic::Code::PermissionDenied));
+        let allow = |_: &Request<()>| Ok(Principal("anyone".to_string()));
+        assert_eq!(principal(&allow, &[]), Ok("anyone".to_string()));
+    }
+
+    #[test]
+    fn interceptor_stores_the_principal() {
+        let mut interceptor =
+            AuthInterceptor::new(BearerTokenAuthenticator::new().token("s3cr3t", "tenant-a"));
+        let accepted = interceptor.call(request(&[("authorization", "Bearer s3cr3t")]));
+        assert_eq!(
+            accepted
+                .ok()
+                .and_then(|request| request.extensions().get::<Principal>().cloned()),
+            Some(Principal("tenant-a".to_string()))
+        );
+        let rejected = interceptor.call(request(&[("authorization", "Bearer wrong")]));
+        assert_eq!(
+            rejected.err().map(|status| status.code()),
+            Some(tonic::Code::Unauthenticated)
+        );
+    }
+}
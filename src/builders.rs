@@ -0,0 +1,976 @@
+// Copyright 2020-2022, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Builders for spans, log records and metrics that fill in the fields the
+//! protocol requires, such as identifiers and timestamps, and request builders
+//! that group items per `Resource` and `InstrumentationScope` into complete
+//! export requests.
+//!
+//! ```ignore
+//! let span = SpanBuilder::new("GET /users")
+//!     .kind(SpanKind::Server)
+//!     .attribute("http.method", "GET")
+//!     .build();
+//!
+//! let request = TraceRequestBuilder::new()
+//!     .add(&resource, &scope, span)
+//!     .build();
+//! ```
+
+use crate::opentelemetry::proto::common::v1::{AnyValue, InstrumentationScope, KeyValue};
+use crate::opentelemetry::proto::resource::v1::Resource;
+use crate::value::key_value;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Nanoseconds since the Unix epoch of a point in time, zero before the epoch
+pub fn unix_nanos(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|since| since.as_nanos() as u64)
+        .unwrap_or_default()
+}
+
+// Items of one resource grouped per scope
+type ScopeGroups<T> = Vec<(InstrumentationScope, Vec<T>)>;
+
+// Items grouped per resource and scope, in the order they were first added
+#[derive(Debug)]
+struct Groups<T> {
+    resources: Vec<(Resource, ScopeGroups<T>)>,
+}
+
+impl<T> Default for Groups<T> {
+    fn default() -> Self {
+        Groups {
+            resources: Vec::new(),
+        }
+    }
+}
+
+impl<T> Groups<T> {
+    fn add(&mut self, resource: &Resource, scope: &InstrumentationScope, item: T) {
+        let index = match self
+            .resources
+            .iter()
+            .position(|(known, _)| known == resource)
+        {
+            Some(index) => index,
+            None => {
+                self.resources.push((resource.clone(), Vec::new()));
+                self.resources.len() - 1
+            }
+        };
+        let scopes = &mut self.resources[index].1;
+        match scopes.iter_mut().find(|(known, _)| known == scope) {
+            Some((_, items)) => items.push(item),
+            None => scopes.push((scope.clone(), vec![item])),
+        }
+    }
+}
+
+#[cfg(feature = "otel-trace")]
+pub use self::trace::{SpanBuilder, TraceRequestBuilder};
+
+#[cfg(feature = "otel-trace")]
+mod trace {
+    use super::{unix_nanos, Groups};
+    use crate::opentelemetry::proto::collector::trace::v1::ExportTraceServiceRequest;
+    use crate::opentelemetry::proto::common::v1::{AnyValue, InstrumentationScope, KeyValue};
+    use crate::opentelemetry::proto::resource::v1::Resource;
+    use crate::opentelemetry::proto::trace::v1::span::{Event, Link, SpanKind};
+    use crate::opentelemetry::proto::trace::v1::status::StatusCode;
+    use crate::opentelemetry::proto::trace::v1::{
+        ResourceSpans, ScopeSpans, Span, SpanFlags, Status,
+    };
+    use crate::trace_context::{SpanId, TraceId, TraceParent};
+    use crate::value::key_value;
+    use std::time::SystemTime;
+
+    /// Builder for spans
+    ///
+    /// Spans without a trace id or span id get random ones, spans without a
+    /// start time start when they are built and spans without an end time
+    /// end when they start.
+    #[derive(Clone, Debug)]
+    pub struct SpanBuilder {
+        span: Span,
+    }
+
+    impl SpanBuilder {
+        /// Creates a builder for an internal span with the specified name
+        pub fn new<N: Into<String>>(name: N) -> Self {
+            SpanBuilder {
+                span: Span {
+                    name: name.into(),
+                    kind: SpanKind::Internal as i32,
+                    ..Span::default()
+                },
+            }
+        }
+
+        /// Sets the trace id
+        pub fn trace_id<T: Into<TraceId>>(mut self, trace_id: T) -> Self {
+            self.span.set_trace_id(trace_id.into());
+            self
+        }
+
+        /// Sets the span id
+        pub fn span_id<S: Into<SpanId>>(mut self, span_id: S) -> Self {
+            self.span.set_span_id(span_id.into());
+            self
+        }
+
+        /// Sets the span id of the parent span
+        pub fn parent_span_id<S: Into<SpanId>>(mut self, parent_span_id: S) -> Self {
+            self.span.set_parent_span_id(parent_span_id.into());
+            self
+        }
+
+        /// Continues the trace of a remote parent span, propagated in a
+        /// `traceparent` header
+        pub fn remote_parent(mut self, parent: &TraceParent) -> Self {
+            self.span.set_trace_id(parent.trace_id);
+            self.span.set_parent_span_id(parent.parent_id);
+            self.span.flags = u32::from(parent.flags)
+                | SpanFlags::ContextHasIsRemoteMask as u32
+                | SpanFlags::ContextIsRemoteMask as u32;
+            self
+        }
+
+        /// Sets the W3C trace state
+        pub fn trace_state<S: Into<String>>(mut self, trace_state: S) -> Self {
+            self.span.trace_state = trace_state.into();
+            self
+        }
+
+        /// Sets the W3C trace flags
+        pub fn flags(mut self, flags: u32) -> Self {
+            self.span.flags = flags;
+            self
+        }
+
+        /// Sets the span kind
+        pub fn kind(mut self, kind: SpanKind) -> Self {
+            self.span.kind = kind as i32;
+            self
+        }
+
+        /// Sets the start time
+        pub fn start_time(mut self, time: SystemTime) -> Self {
+            self.span.start_time_unix_nano = unix_nanos(time);
+            self
+        }
+
+        /// Sets the end time
+        pub fn end_time(mut self, time: SystemTime) -> Self {
+            self.span.end_time_unix_nano = unix_nanos(time);
+            self
+        }
+
+        /// Adds an attribute
+        pub fn attribute<K: Into<String>, V: Into<AnyValue>>(mut self, key: K, value: V) -> Self {
+            self.span.attributes.push(key_value(key, value));
+            self
+        }
+
+        /// Adds attributes
+        pub fn attributes<I: IntoIterator<Item = KeyValue>>(mut self, attributes: I) -> Self {
+            self.span.attributes.extend(attributes);
+            self
+        }
+
+        /// Adds an event with attributes
+        pub fn event<N: Into<String>>(
+            mut self,
+            name: N,
+            time: SystemTime,
+            attributes: Vec<KeyValue>,
+        ) -> Self {
+            self.span.events.push(Event {
+                time_unix_nano: unix_nanos(time),
+                name: name.into(),
+                attributes,
+                ..Event::default()
+            });
+            self
+        }
+
+        /// Adds a link to another span
+        pub fn link<T: Into<TraceId>, S: Into<SpanId>>(
+            mut self,
+            trace_id: T,
+            span_id: S,
+            attributes: Vec<KeyValue>,
+        ) -> Self {
+            self.span.links.push(Link {
+                trace_id: trace_id.into().into(),
+                span_id: span_id.into().into(),
+                attributes,
+                ..Link::default()
+            });
+            self
+        }
+
+        /// Sets the status, the message is only kept for errors
+        pub fn status<M: Into<String>>(mut self, code: StatusCode, message: M) -> Self {
+            let message = if code == StatusCode::Error {
+                message.into()
+            } else {
+                String::new()
+            };
+            self.span.status = Some(Status {
+                message,
+                code: code as i32,
+            });
+            self
+        }
+
+        /// Builds the span
+        pub fn build(self) -> Span {
+            let mut span = self.span;
+            if span.trace_id().is_none() {
+                span.set_trace_id(TraceId::random());
+            }
+            if span.span_id().is_none() {
+                span.set_span_id(SpanId::random());
+            }
+            if span.start_time_unix_nano == 0 {
+                span.start_time_unix_nano = unix_nanos(SystemTime::now());
+            }
+            if span.end_time_unix_nano < span.start_time_unix_nano {
+                span.end_time_unix_nano = span.start_time_unix_nano;
+            }
+            span
+        }
+    }
+
+    /// Builder for trace export requests grouping spans per resource and scope
+    #[derive(Debug, Default)]
+    pub struct TraceRequestBuilder {
+        groups: Groups<Span>,
+    }
+
+    impl TraceRequestBuilder {
+        /// Creates a builder without spans
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Adds a span of a resource and scope
+        pub fn add(
+            mut self,
+            resource: &Resource,
+            scope: &InstrumentationScope,
+            span: Span,
+        ) -> Self {
+            self.push(resource, scope, span);
+            self
+        }
+
+        /// Adds a span of a resource and scope to a borrowed builder
+        pub fn push(&mut self, resource: &Resource, scope: &InstrumentationScope, span: Span) {
+            self.groups.add(resource, scope, span);
+        }
+
+        /// Builds the export request
+        pub fn build(self) -> ExportTraceServiceRequest {
+            let resource_spans = self
+                .groups
+                .resources
+                .into_iter()
+                .map(|(resource, scopes)| ResourceSpans {
+                    resource: Some(resource),
+                    scope_spans: scopes
+                        .into_iter()
+                        .map(|(scope, spans)| ScopeSpans {
+                            scope: Some(scope),
+                            spans,
+                            ..ScopeSpans::default()
+                        })
+                        .collect(),
+                    ..ResourceSpans::default()
+                })
+                .collect();
+            ExportTraceServiceRequest { resource_spans }
+        }
+    }
+}
+
+#[cfg(feature = "otel-logs")]
+pub use self::logs::{LogRecordBuilder, LogsRequestBuilder};
+
+#[cfg(feature = "otel-logs")]
+mod logs {
+    use super::{unix_nanos, Groups};
+    use crate::opentelemetry::proto::collector::logs::v1::ExportLogsServiceRequest;
+    use crate::opentelemetry::proto::common::v1::{AnyValue, InstrumentationScope, KeyValue};
+    use crate::opentelemetry::proto::logs::v1::{
+        LogRecord, ResourceLogs, ScopeLogs, SeverityNumber,
+    };
+    use crate::opentelemetry::proto::resource::v1::Resource;
+    use crate::trace_context::{SpanId, TraceId};
+    use crate::value::key_value;
+    use std::time::SystemTime;
+
+    /// Builder for log records
+    ///
+    /// Log records are observed when they are built, unless an observed time
+    /// is set.
+    #[derive(Clone, Debug, Default)]
+    pub struct LogRecordBuilder {
+        record: LogRecord,
+    }
+
+    impl LogRecordBuilder {
+        /// Creates a builder for an empty log record
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Sets the time of the event the record describes
+        pub fn time(mut self, time: SystemTime) -> Self {
+            self.record.time_unix_nano = unix_nanos(time);
+            self
+        }
+
+        /// Sets the time the record was observed
+        pub fn observed_time(mut self, time: SystemTime) -> Self {
+            self.record.observed_time_unix_nano = unix_nanos(time);
+            self
+        }
+
+        /// Sets the severity and its default severity text, such as `WARN2`
+        pub fn severity(mut self, severity: SeverityNumber) -> Self {
+            self.record.severity_number = severity as i32;
+            if self.record.severity_text.is_empty() {
+                self.record.severity_text = severity_text(severity).to_string();
+            }
+            self
+        }
+
+        /// Sets the severity text as reported by the source
+        pub fn severity_text<T: Into<String>>(mut self, text: T) -> Self {
+            self.record.severity_text = text.into();
+            self
+        }
+
+        /// Sets the body
+        pub fn body<V: Into<AnyValue>>(mut self, body: V) -> Self {
+            self.record.body = Some(body.into());
+            self
+        }
+
+        /// Sets the event name
+        pub fn event_name<N: Into<String>>(mut self, name: N) -> Self {
+            self.record.event_name = name.into();
+            self
+        }
+
+        /// Adds an attribute
+        pub fn attribute<K: Into<String>, V: Into<AnyValue>>(mut self, key: K, value: V) -> Self {
+            self.record.attributes.push(key_value(key, value));
+            self
+        }
+
+        /// Adds attributes
+        pub fn attributes<I: IntoIterator<Item = KeyValue>>(mut self, attributes: I) -> Self {
+            self.record.attributes.extend(attributes);
+            self
+        }
+
+        /// Correlates the record with a span
+        pub fn span<T: Into<TraceId>, S: Into<SpanId>>(
+            mut self,
+            trace_id: T,
+            span_id: S,
+            flags: u32,
+        ) -> Self {
+            self.record.set_trace_id(trace_id.into());
+            self.record.set_span_id(span_id.into());
+            self.record.flags = flags & 0xff;
+            self
+        }
+
+        /// Builds the log record
+        pub fn build(self) -> LogRecord {
+            let mut record = self.record;
+            if record.observed_time_unix_nano == 0 {
+                record.observed_time_unix_nano = unix_nanos(SystemTime::now());
+            }
+            record
+        }
+    }
+
+    fn severity_text(severity: SeverityNumber) -> &'static str {
+        match severity {
+            SeverityNumber::Unspecified => "",
+            SeverityNumber::Trace => "TRACE",
+            SeverityNumber::Trace2 => "TRACE2",
+            SeverityNumber::Trace3 => "TRACE3",
+            SeverityNumber::Trace4 => "TRACE4",
+            SeverityNumber::Debug => "DEBUG",
+            SeverityNumber::Debug2 => "DEBUG2",
+            SeverityNumber::Debug3 => "DEBUG3",
+            SeverityNumber::Debug4 => "DEBUG4",
+            SeverityNumber::Info => "INFO",
+            SeverityNumber::Info2 => "INFO2",
+            SeverityNumber::Info3 => "INFO3",
+            SeverityNumber::Info4 => "INFO4",
+            SeverityNumber::Warn => "WARN",
+            SeverityNumber::Warn2 => "WARN2",
+            SeverityNumber::Warn3 => "WARN3",
+            SeverityNumber::Warn4 => "WARN4",
+            SeverityNumber::Error => "ERROR",
+            SeverityNumber::Error2 => "ERROR2",
+            SeverityNumber::Error3 => "ERROR3",
+            SeverityNumber::Error4 => "ERROR4",
+            SeverityNumber::Fatal => "FATAL",
+            SeverityNumber::Fatal2 => "FATAL2",
+            SeverityNumber::Fatal3 => "FATAL3",
+            SeverityNumber::Fatal4 => "FATAL4",
+        }
+    }
+
+    /// Builder for logs export requests grouping log records per resource and scope
+    #[derive(Debug, Default)]
+    pub struct LogsRequestBuilder {
+        groups: Groups<LogRecord>,
+    }
+
+    impl LogsRequestBuilder {
+        /// Creates a builder without log records
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Adds a log record of a resource and scope
+        pub fn add(
+            mut self,
+            resource: &Resource,
+            scope: &InstrumentationScope,
+            record: LogRecord,
+        ) -> Self {
+            self.push(resource, scope, record);
+            self
+        }
+
+        /// Adds a log record of a resource and scope to a borrowed builder
+        pub fn push(
+            &mut self,
+            resource: &Resource,
+            scope: &InstrumentationScope,
+            record: LogRecord,
+        ) {
+            self.groups.add(resource, scope, record);
+        }
+
+        /// Builds the export request
+        pub fn build(self) -> ExportLogsServiceRequest {
+            let resource_logs = self
+                .groups
+                .resources
+                .into_iter()
+                .map(|(resource, scopes)| ResourceLogs {
+                    resource: Some(resource),
+                    scope_logs: scopes
+                        .into_iter()
+                        .map(|(scope, log_records)| ScopeLogs {
+                            scope: Some(scope),
+                            log_records,
+                            ..ScopeLogs::default()
+                        })
+                        .collect(),
+                    ..ResourceLogs::default()
+                })
+                .collect();
+            ExportLogsServiceRequest { resource_logs }
+        }
+    }
+}
+
+#[cfg(feature = "otel-metrics")]
+pub use self::metrics::{GaugeBuilder, HistogramBuilder, MetricsRequestBuilder, SumBuilder};
+
+#[cfg(feature = "otel-metrics")]
+mod metrics {
+    use super::{unix_nanos, Groups};
+    use crate::opentelemetry::proto::collector::metrics::v1::ExportMetricsServiceRequest;
+    use crate::opentelemetry::proto::common::v1::{InstrumentationScope, KeyValue};
+    use crate::opentelemetry::proto::metrics::v1::{
+        metric, number_data_point, AggregationTemporality, Gauge, Histogram, HistogramDataPoint,
+        Metric, NumberDataPoint, ResourceMetrics, ScopeMetrics, Sum,
+    };
+    use crate::opentelemetry::proto::resource::v1::Resource;
+    use std::time::SystemTime;
+
+    impl From<f64> for number_data_point::Value {
+        fn from(value: f64) -> Self {
+            number_data_point::Value::AsDouble(value)
+        }
+    }
+
+    impl From<i64> for number_data_point::Value {
+        fn from(value: i64) -> Self {
+            number_data_point::Value::AsInt(value)
+        }
+    }
+
+    fn number_point(
+        value: number_data_point::Value,
+        attributes: Vec<KeyValue>,
+        start_time_unix_nano: u64,
+        time: SystemTime,
+    ) -> NumberDataPoint {
+        NumberDataPoint {
+            attributes,
+            start_time_unix_nano,
+            time_unix_nano: unix_nanos(time),
+            value: Some(value),
+            ..NumberDataPoint::default()
+        }
+    }
+
+    /// Builder for gauge metrics
+    #[derive(Clone, Debug)]
+    pub struct GaugeBuilder {
+        metric: Metric,
+        points: Vec<NumberDataPoint>,
+    }
+
+    impl GaugeBuilder {
+        /// Creates a builder for a gauge with the specified name
+        pub fn new<N: Into<String>>(name: N) -> Self {
+            GaugeBuilder {
+                metric: Metric {
+                    name: name.into(),
+                    ..Metric::default()
+                },
+                points: Vec::new(),
+            }
+        }
+
+        /// Sets the description
+        pub fn description<D: Into<String>>(mut self, description: D) -> Self {
+            self.metric.description = description.into();
+            self
+        }
+
+        /// Sets the unit, such as `ms` or `By`
+        pub fn unit<U: Into<String>>(mut self, unit: U) -> Self {
+            self.metric.unit = unit.into();
+            self
+        }
+
+        /// Adds a point observed now
+        pub fn point<V: Into<number_data_point::Value>>(
+            self,
+            value: V,
+            attributes: Vec<KeyValue>,
+        ) -> Self {
+            self.point_at(value, attributes, SystemTime::now())
+        }
+
+        /// Adds a point observed at the specified time
+        pub fn point_at<V: Into<number_data_point::Value>>(
+            mut self,
+            value: V,
+            attributes: Vec<KeyValue>,
+            time: SystemTime,
+        ) -> Self {
+            self.points
+                .push(number_point(value.into(), attributes, 0, time));
+            self
+        }
+
+        /// Builds the metric
+        pub fn build(self) -> Metric {
+            Metric {
+                data: Some(metric::Data::Gauge(Gauge {
+                    data_points: self.points,
+                })),
+                ..self.metric
+            }
+        }
+    }
+
+    /// Builder for sum metrics, cumulative and monotonic by default
+    #[derive(Clone, Debug)]
+    pub struct SumBuilder {
+        metric: Metric,
+        sum: Sum,
+        start_time_unix_nano: u64,
+    }
+
+    impl SumBuilder {
+        /// Creates a builder for a sum with the specified name
+        pub fn new<N: Into<String>>(name: N) -> Self {
+            SumBuilder {
+                metric: Metric {
+                    name: name.into(),
+                    ..Metric::default()
+                },
+                sum: Sum {
+                    aggregation_temporality: AggregationTemporality::Cumulative as i32,
+                    is_monotonic: true,
+                    ..Sum::default()
+                },
+                start_time_unix_nano: 0,
+            }
+        }
+
+        /// Sets the description
+        pub fn description<D: Into<String>>(mut self, description: D) -> Self {
+            self.metric.description = description.into();
+            self
+        }
+
+        /// Sets the unit, such as `ms` or `By`
+        pub fn unit<U: Into<String>>(mut self, unit: U) -> Self {
+            self.metric.unit = unit.into();
+            self
+        }
+
+        /// Sets whether the sum only ever increases
+        pub fn monotonic(mut self, monotonic: bool) -> Self {
+            self.sum.is_monotonic = monotonic;
+            self
+        }
+
+        /// Sets the aggregation temporality
+        pub fn temporality(mut self, temporality: AggregationTemporality) -> Self {
+            self.sum.aggregation_temporality = temporality as i32;
+            self
+        }
+
+        /// Sets the start of the aggregation interval of the points added afterwards
+        pub fn start_time(mut self, time: SystemTime) -> Self {
+            self.start_time_unix_nano = unix_nanos(time);
+            self
+        }
+
+        /// Adds a point observed now
+        pub fn point<V: Into<number_data_point::Value>>(
+            self,
+            value: V,
+            attributes: Vec<KeyValue>,
+        ) -> Self {
+            self.point_at(value, attributes, SystemTime::now())
+        }
+
+        /// Adds a point observed at the specified time
+        pub fn point_at<V: Into<number_data_point::Value>>(
+            mut self,
+            value: V,
+            attributes: Vec<KeyValue>,
+            time: SystemTime,
+        ) -> Self {
+            let point = number_point(value.into(), attributes, self.start_time_unix_nano, time);
+            self.sum.data_points.push(point);
+            self
+        }
+
+        /// Builds the metric
+        pub fn build(self) -> Metric {
+            Metric {
+                data: Some(metric::Data::Sum(self.sum)),
+                ..self.metric
+            }
+        }
+    }
+
+    /// Builder for explicit bucket histogram metrics, cumulative by default
+    #[derive(Clone, Debug)]
+    pub struct HistogramBuilder {
+        metric: Metric,
+        histogram: Histogram,
+        bounds: Vec<f64>,
+        start_time_unix_nano: u64,
+    }
+
+    impl HistogramBuilder {
+        /// Creates a builder for a histogram with the specified name and
+        /// bucket bounds
+        pub fn new<N: Into<String>>(name: N, bounds: Vec<f64>) -> Self {
+            let mut bounds: Vec<f64> = bounds.into_iter().filter(|b| b.is_finite()).collect();
+            bounds.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+            bounds.dedup();
+            HistogramBuilder {
+                metric: Metric {
+                    name: name.into(),
+                    ..Metric::default()
+                },
+                histogram: Histogram {
+                    aggregation_temporality: AggregationTemporality::Cumulative as i32,
+                    ..Histogram::default()
+                },
+                bounds,
+                start_time_unix_nano: 0,
+            }
+        }
+
+        /// Sets the description
+        pub fn description<D: Into<String>>(mut self, description: D) -> Self {
+            self.metric.description = description.into();
+            self
+        }
+
+        /// Sets the unit, such as `ms` or `By`
+        pub fn unit<U: Into<String>>(mut self, unit: U) -> Self {
+            self.metric.unit = unit.into();
+            self
+        }
+
+        /// Sets the aggregation temporality
+        pub fn temporality(mut self, temporality: AggregationTemporality) -> Self {
+            self.histogram.aggregation_temporality = temporality as i32;
+            self
+        }
+
+        /// Sets the start of the aggregation interval of the points added afterwards
+        pub fn start_time(mut self, time: SystemTime) -> Self {
+            self.start_time_unix_nano = unix_nanos(time);
+            self
+        }
+
+        /// Adds a point aggregating the specified values, observed now
+        pub fn record(self, values: &[f64], attributes: Vec<KeyValue>) -> Self {
+            self.record_at(values, attributes, SystemTime::now())
+        }
+
+        /// Adds a point aggregating the specified values, observed at the specified time
+        ///
+        /// A value falls into the first bucket whose upper bound is greater than
+        /// or equal to it, values above all bounds fall into the last bucket.
+        pub fn record_at(
+            mut self,
+            values: &[f64],
+            attributes: Vec<KeyValue>,
+            time: SystemTime,
+        ) -> Self {
+            let values: Vec<f64> = values.iter().copied().filter(|v| !v.is_nan()).collect();
+            let mut bucket_counts = vec![0_u64; self.bounds.len() + 1];
+            for value in &values {
+                let bucket = self
+                    .bounds
+                    .iter()
+                    .take_while(|bound| *value > **bound)
+                    .count();
+                bucket_counts[bucket] += 1;
+            }
+            let point = HistogramDataPoint {
+                attributes,
+                start_time_unix_nano: self.start_time_unix_nano,
+                time_unix_nano: unix_nanos(time),
+                count: values.len() as u64,
+                sum: Some(values.iter().sum()),
+                bucket_counts,
+                explicit_bounds: self.bounds.clone(),
+                min: values.iter().copied().reduce(f64::min),
+                max: values.iter().copied().reduce(f64::max),
+                ..HistogramDataPoint::default()
+            };
+            self.histogram.data_points.push(point);
+            self
+        }
+
+        /// Builds the metric
+        pub fn build(self) -> Metric {
+            Metric {
+                data: Some(metric::Data::Histogram(self.histogram)),
+                ..self.metric
+            }
+        }
+    }
+
+    /// Builder for metrics export requests grouping metrics per resource and scope
+    #[derive(Debug, Default)]
+    pub struct MetricsRequestBuilder {
+        groups: Groups<Metric>,
+    }
+
+    impl MetricsRequestBuilder {
+        /// Creates a builder without metrics
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Adds a metric of a resource and scope
+        pub fn add(
+            mut self,
+            resource: &Resource,
+            scope: &InstrumentationScope,
+            metric: Metric,
+        ) -> Self {
+            self.push(resource, scope, metric);
+            self
+        }
+
+        /// Adds a metric of a resource and scope to a borrowed builder
+        pub fn push(&mut self, resource: &Resource, scope: &InstrumentationScope, metric: Metric) {
+            self.groups.add(resource, scope, metric);
+        }
+
+        /// Builds the export request
+        pub fn build(self) -> ExportMetricsServiceRequest {
+            let resource_metrics = self
+                .groups
+                .resources
+                .into_iter()
+                .map(|(resource, scopes)| ResourceMetrics {
+                    resource: Some(resource),
+                    scope_metrics: scopes
+                        .into_iter()
+                        .map(|(scope, metrics)| ScopeMetrics {
+                            scope: Some(scope),
+                            metrics,
+                            ..ScopeMetrics::default()
+                        })
+                        .collect(),
+                    ..ResourceMetrics::default()
+                })
+                .collect();
+            ExportMetricsServiceRequest { resource_metrics }
+        }
+    }
+}
+
+/// Creates an instrumentation scope with a name, version and attributes
+pub fn scope<N: Into<String>, V: Into<String>>(
+    name: N,
+    version: V,
+    attributes: Vec<KeyValue>,
+) -> InstrumentationScope {
+    InstrumentationScope {
+        name: name.into(),
+        version: version.into(),
+        attributes,
+        ..InstrumentationScope::default()
+    }
+}
+
+/// Creates a resource with attributes
+pub fn resource<K: Into<String>, V: Into<AnyValue>, I: IntoIterator<Item = (K, V)>>(
+    attributes: I,
+) -> Resource {
+    Resource {
+        attributes: attributes
+            .into_iter()
+            .map(|(key, value)| key_value(key, value))
+            .collect(),
+        ..Resource::default()
+    }
+}
+
+#[cfg(all(test, feature = "otel-all"))]
+mod tests {
+    use super::*;
+    use crate::opentelemetry::proto::metrics::v1::metric;
+    use std::time::Duration;
+
+    #[test]
+    fn histogram_buckets_include_their_upper_bound() {
+        let time = UNIX_EPOCH + Duration::from_secs(1);
+        let metric = HistogramBuilder::new("h", vec![10.0, 0.0, 5.0, 5.0, f64::INFINITY])
+            .start_time(UNIX_EPOCH)
+            .record_at(&[0.0, 5.0, 5.5, 10.0, 11.0, -1.0, f64::NAN], vec![], time)
+            .record_at(&[], vec![], time)
+            .build();
+        let histogram = match metric.data {
+            Some(metric::Data::Histogram(histogram)) => histogram,
+            other => panic!("expected a histogram, got {:?}", other),
+        };
+        let point = &histogram.data_points[0];
+        assert!(point.explicit_bounds.iter().eq(&[0.0, 5.0, 10.0]));
+        assert_eq!(point.bucket_counts, [2, 1, 2, 1]);
+        assert_eq!(point.count, 6);
+        assert_eq!(point.sum, Some(30.5));
+        assert_eq!(point.min, Some(-1.0));
+        assert_eq!(point.max, Some(11.0));
+        assert_eq!(point.time_unix_nano, 1_000_000_000);
+
+        let empty = &histogram.data_points[1];
+        assert_eq!(empty.bucket_counts, [0, 0, 0, 0]);
+        assert_eq!(empty.count, 0);
+        assert_eq!(empty.min, None);
+        assert_eq!(empty.max, None);
+    }
+
+    #[test]
+    fn fills_in_missing_timestamps_and_ids() {
+        let before = unix_nanos(SystemTime::now());
+        let span = SpanBuilder::new("span").build();
+        assert!(span.trace_id().is_some());
+        assert!(span.span_id().is_some());
+        assert!(span.start_time_unix_nano >= before);
+        assert_eq!(span.end_time_unix_nano, span.start_time_unix_nano);
+
+        let start = UNIX_EPOCH + Duration::from_secs(2);
+        let span = SpanBuilder::new("span")
+            .start_time(start)
+            .end_time(UNIX_EPOCH + Duration::from_secs(1))
+            .build();
+        assert_eq!(span.start_time_unix_nano, 2_000_000_000);
+        assert_eq!(span.end_time_unix_nano, 2_000_000_000);
+
+        let record = LogRecordBuilder::new().build();
+        assert_eq!(record.time_unix_nano, 0);
+        assert!(record.observed_time_unix_nano >= before);
+        let record = LogRecordBuilder::new().observed_time(start).build();
+        assert_eq!(record.observed_time_unix_nano, 2_000_000_000);
+
+        assert_eq!(unix_nanos(UNIX_EPOCH - Duration::from_secs(1)), 0);
+    }
+
+    #[test]
+    fn groups_items_per_resource_and_scope() {
+        let checkout = resource(vec![("service.name", "checkout")]);
+        let billing = resource(vec![("service.name", "billing")]);
+        let http = scope("http", "1.0", vec![]);
+        let db = scope("db", "1.0", vec![]);
+        let request = TraceRequestBuilder::new()
+            .add(&checkout, &http, SpanBuilder::new("a").build())
+            .add(&billing, &http, SpanBuilder::new("b").build())
+            .add(&checkout, &db, SpanBuilder::new("c").build())
+            .add(&checkout, &http, SpanBuilder::new("d").build())
+            .build();
+        let groups: Vec<_> = request
+            .resource_spans
+            .iter()
+            .map(|resource_spans| {
+                let scopes: Vec<_> = resource_spans
+                    .scope_spans
+                    .iter()
+                    .map(|scope_spans| {
+                        let names: Vec<_> = scope_spans
+                            .spans
+                            .iter()
+                            .map(|span| span.name.as_str())
+                            .collect();
+                        (scope_spans.scope.clone(), names)
+                    })
+                    .collect();
+                (resource_spans.resource.clone(), scopes)
+            })
+            .collect();
+        assert_eq!(
+            groups,
+            vec![
+                (
+                    Some(checkout),
+                    vec![(Some(http.clone()), vec!["a", "d"]), (Some(db), vec!["c"])]
+                ),
+                (Some(billing), vec![(Some(http), vec!["b"])]),
+            ]
+        );
+    }
+}
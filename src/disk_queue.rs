@@ -0,0 +1,739 @@
+// Copyright 2020-2022, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A `DiskQueue` buffers protocol buffer messages, such as export requests,
+//! in a directory so they survive collector outages and process restarts.
+//!
+//! Messages are appended to numbered segment files as records of a little
+//! endian `u32` length, the CRC-32 of the payload and the protobuf encoded
+//! payload. A new segment is started once the current one reaches its size
+//! cap, and the total size of all segments is capped as well. The position of
+//! the oldest unacknowledged message is kept in a `cursor` file, so a restarted
+//! process resumes where it stopped.
+//!
+//! A record whose checksum does not match is reported as `Error::Corrupt` on
+//! replay. The rest of its segment is set aside with a `.corrupt` extension and
+//! replay continues with the next segment. A record that is not a valid message
+//! is reported as `Error::Decode` and skipped by `drain`. A record cut short by
+//! a crash while it was written is discarded when the queue is opened.
+//!
+//! ```ignore
+//! let mut queue = DiskQueue::<ExportTraceServiceRequest>::open(
+//!     "/var/lib/agent/traces",
+//!     DiskQueueOptions::new().max_size(256 * 1024 * 1024),
+//! )?;
+//! queue.push(&request)?;
+//!
+//! // Once the collector is reachable again
+//! queue
+//!     .drain(|request| {
+//!         let mut client = client.clone();
+//!         async move { client.export(request).await.map(|_| ()) }
+//!     })
+//!     .await?;
+//! ```
+
+use prost::Message;
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::future::Future;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Default size cap of a segment file
+pub const DEFAULT_MAX_SEGMENT_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Default size cap of all segment files
+pub const DEFAULT_MAX_SIZE: u64 = 1024 * 1024 * 1024;
+
+// Length and checksum preceding each payload
+const RECORD_HEADER_SIZE: u64 = 8;
+
+const SEGMENT_EXTENSION: &str = "seg";
+const CORRUPT_EXTENSION: &str = "corrupt";
+const CURSOR_FILE: &str = "cursor";
+
+/// Errors of a disk queue
+#[derive(Debug)]
+pub enum Error {
+    /// Reading or writing the queue directory failed
+    Io(io::Error),
+    /// The message does not fit into the queue, or is 4 GiB or larger
+    Full,
+    /// A record failed its checksum during replay, the rest of its segment was set aside
+    Corrupt {
+        /// The segment file holding the record
+        segment: PathBuf,
+        /// The offset of the record in the segment file
+        offset: u64,
+    },
+    /// A record passed its checksum but is not a valid message, `drain` removes it
+    Decode(prost::DecodeError),
+    /// Sending a drained message failed, it remains queued
+    Export(tonic::Status),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "Disk queue IO error: {}", e),
+            Error::Full => write!(f, "Disk queue is full"),
+            Error::Corrupt { segment, offset } => write!(
+                f,
+                "Corrupt record in disk queue segment {} at offset {}",
+                segment.display(),
+                offset
+            ),
+            Error::Decode(e) => write!(f, "Failed to decode disk queue record: {}", e),
+            Error::Export(status) => write!(f, "Failed to export queued message: {}", status),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Decode(e) => Some(e),
+            Error::Export(status) => Some(status),
+            Error::Full | Error::Corrupt { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+/// When written records are flushed to stable storage
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FsyncPolicy {
+    /// After every pushed message and cursor update
+    Always,
+    /// On the first push once the interval elapsed since the last fsync
+    Interval(Duration),
+    /// Never, the operating system decides when data is written
+    Never,
+}
+
+/// How a full queue reacts to pushed messages
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FullPolicy {
+    /// Rejects the message with `Error::Full`
+    Reject,
+    /// Deletes the oldest segments until the message fits
+    DropOldest,
+}
+
+/// Options of a disk queue
+#[derive(Clone, Debug)]
+pub struct DiskQueueOptions {
+    max_segment_size: u64,
+    max_size: u64,
+    fsync: FsyncPolicy,
+    when_full: FullPolicy,
+}
+
+impl Default for DiskQueueOptions {
+    fn default() -> Self {
+        DiskQueueOptions {
+            max_segment_size: DEFAULT_MAX_SEGMENT_SIZE,
+            max_size: DEFAULT_MAX_SIZE,
+            fsync: FsyncPolicy::Interval(Duration::from_secs(1)),
+            when_full: FullPolicy::Reject,
+        }
+    }
+}
+
+impl DiskQueueOptions {
+    /// Creates options with the default size caps, an fsync every second and
+    /// rejecting messages when full
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the size at which a new segment file is started
+    pub fn max_segment_size(mut self, size: u64) -> Self {
+        self.max_segment_size = size.max(RECORD_HEADER_SIZE);
+        self
+    }
+
+    /// Sets the maximum total size of all segment files
+    pub fn max_size(mut self, size: u64) -> Self {
+        self.max_size = size;
+        self
+    }
+
+    /// Sets when written records are flushed to stable storage
+    pub fn fsync(mut self, fsync: FsyncPolicy) -> Self {
+        self.fsync = fsync;
+        self
+    }
+
+    /// Sets how a full queue reacts to pushed messages
+    pub fn when_full(mut self, when_full: FullPolicy) -> Self {
+        self.when_full = when_full;
+        self
+    }
+}
+
+#[derive(Debug)]
+struct Segment {
+    id: u64,
+    size: u64,
+}
+
+/// A persistent FIFO queue of protocol buffer messages
+#[derive(Debug)]
+pub struct DiskQueue<M> {
+    dir: PathBuf,
+    options: DiskQueueOptions,
+    segments: VecDeque<Segment>,
+    writer: Option<File>,
+    reader: Option<(u64, File)>,
+    read_segment: u64,
+    read_offset: u64,
+    front_size: Option<u64>,
+    last_sync: Instant,
+    unsynced: bool,
+    message: PhantomData<M>,
+}
+
+impl<M: Message + Default> DiskQueue<M> {
+    /// Opens the queue in a directory, creating it if necessary, and resumes
+    /// after the last acknowledged message
+    pub fn open<P: AsRef<Path>>(dir: P, options: DiskQueueOptions) -> Result<Self, Error> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        let mut ids = Vec::new();
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(SEGMENT_EXTENSION) {
+                continue;
+            }
+            if let Some(id) = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.parse::<u64>().ok())
+            {
+                ids.push(id);
+            }
+        }
+        ids.sort_unstable();
+        let mut segments = VecDeque::with_capacity(ids.len());
+        for id in ids {
+            let size = fs::metadata(segment_path(&dir, id))?.len();
+            segments.push_back(Segment { id, size });
+        }
+        let (mut read_segment, mut read_offset) = read_cursor(&dir)?;
+        match segments.front() {
+            Some(first) if read_segment < first.id => {
+                read_segment = first.id;
+                read_offset = 0;
+            }
+            None => read_offset = 0,
+            _ => (),
+        }
+        let mut queue = DiskQueue {
+            dir,
+            options,
+            segments,
+            writer: None,
+            reader: None,
+            read_segment,
+            read_offset,
+            front_size: None,
+            last_sync: Instant::now(),
+            unsynced: false,
+            message: PhantomData,
+        };
+        queue.truncate_torn_record()?;
+        Ok(queue)
+    }
+
+    /// The size of the unacknowledged records in bytes
+    ///
+    /// Acknowledged records at the start of the segment being read still take
+    /// up disk space until the segment is deleted, but do not count here.
+    pub fn size(&self) -> u64 {
+        self.segments
+            .iter()
+            .map(|segment| match segment.id.cmp(&self.read_segment) {
+                Ordering::Less => 0,
+                Ordering::Equal => segment.size.saturating_sub(self.read_offset),
+                Ordering::Greater => segment.size,
+            })
+            .sum()
+    }
+
+    /// Checks if all queued messages have been acknowledged
+    pub fn is_empty(&self) -> bool {
+        match self.segments.back() {
+            None => true,
+            Some(last) => self.read_segment >= last.id && self.read_offset >= last.size,
+        }
+    }
+
+    /// Appends a message to the queue
+    pub fn push(&mut self, message: &M) -> Result<(), Error> {
+        let payload = message.encode_to_vec();
+        let length = u32::try_from(payload.len()).map_err(|_| Error::Full)?;
+        let record_size = RECORD_HEADER_SIZE + u64::from(length);
+        if record_size > self.options.max_size {
+            return Err(Error::Full);
+        }
+        while self.size() + record_size > self.options.max_size {
+            match self.options.when_full {
+                FullPolicy::Reject => return Err(Error::Full),
+                FullPolicy::DropOldest => self.drop_oldest_segment()?,
+            }
+        }
+        let roll = match self.segments.back() {
+            None => true,
+            Some(last) => {
+                last.id < self.read_segment
+                    || (last.size > 0 && last.size + record_size > self.options.max_segment_size)
+            }
+        };
+        if roll {
+            let id = self
+                .segments
+                .back()
+                .map_or(self.read_segment, |last| self.read_segment.max(last.id + 1));
+            self.writer = None;
+            self.segments.push_back(Segment { id, size: 0 });
+        }
+        let writer = match &mut self.writer {
+            Some(writer) => writer,
+            None => {
+                let id = self.segments.back().map_or(0, |last| last.id);
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(segment_path(&self.dir, id))?;
+                self.writer.get_or_insert(file)
+            }
+        };
+        let mut record = Vec::with_capacity(record_size as usize);
+        record.extend_from_slice(&length.to_le_bytes());
+        record.extend_from_slice(&crc32fast::hash(&payload).to_le_bytes());
+        record.extend_from_slice(&payload);
+        writer.write_all(&record)?;
+        if let Some(last) = self.segments.back_mut() {
+            last.size += record_size;
+        }
+        self.unsynced = true;
+        let sync = match self.options.fsync {
+            FsyncPolicy::Always => true,
+            FsyncPolicy::Interval(interval) => self.last_sync.elapsed() >= interval,
+            FsyncPolicy::Never => false,
+        };
+        if sync {
+            self.sync()?;
+        }
+        Ok(())
+    }
+
+    /// Flushes written records to stable storage
+    pub fn sync(&mut self) -> Result<(), Error> {
+        if let (true, Some(writer)) = (self.unsynced, &self.writer) {
+            writer.sync_data()?;
+        }
+        self.unsynced = false;
+        self.last_sync = Instant::now();
+        Ok(())
+    }
+
+    /// The oldest unacknowledged message, without removing it
+    pub fn peek(&mut self) -> Result<Option<M>, Error> {
+        loop {
+            let segment = match self.segments.iter().find(|s| s.id >= self.read_segment) {
+                Some(segment) => Segment {
+                    id: segment.id,
+                    size: segment.size,
+                },
+                None => return Ok(None),
+            };
+            if segment.id > self.read_segment {
+                self.read_segment = segment.id;
+                self.read_offset = 0;
+            }
+            if self.read_offset >= segment.size {
+                if self.segments.back().map(|last| last.id) == Some(segment.id) {
+                    return Ok(None);
+                }
+                self.read_segment += 1;
+                self.read_offset = 0;
+                continue;
+            }
+            return match self.read_record(segment.id, segment.size)? {
+                Some(payload) => {
+                    self.front_size = Some(RECORD_HEADER_SIZE + payload.len() as u64);
+                    M::decode(payload.as_slice())
+                        .map(Some)
+                        .map_err(Error::Decode)
+                }
+                None => Err(self.set_aside(segment.id)?),
+            };
+        }
+    }
+
+    /// Acknowledges the oldest message, removing it from the queue
+    pub fn pop(&mut self) -> Result<(), Error> {
+        let size = match self.front_size.take() {
+            Some(size) => size,
+            None => match self.peek() {
+                Ok(Some(_)) | Err(Error::Decode(_)) => self.front_size.take().unwrap_or(0),
+                Ok(None) => return Ok(()),
+                Err(e) => return Err(e),
+            },
+        };
+        self.read_offset += size;
+        // Fully read segments are deleted, except the one written to
+        while let Some(first) = self.segments.front() {
+            let consumed = first.id < self.read_segment
+                || (first.id == self.read_segment && self.read_offset >= first.size);
+            if !consumed || self.segments.len() == 1 {
+                break;
+            }
+            let id = first.id;
+            self.segments.pop_front();
+            self.remove_segment(id)?;
+            if self.read_segment == id {
+                self.read_segment = id + 1;
+                self.read_offset = 0;
+            }
+        }
+        self.write_cursor()
+    }
+
+    /// Sends queued messages in order, acknowledging each one sent, until the
+    /// queue is empty or sending fails
+    ///
+    /// Returns the number of messages sent. A message that failed to send remains
+    /// queued and is returned first by the next drain. A record that is not a
+    /// valid message is removed before its `Error::Decode` is returned, so the
+    /// next drain continues after it.
+    pub async fn drain<F, Fut>(&mut self, mut send: F) -> Result<usize, Error>
+    where
+        F: FnMut(M) -> Fut,
+        Fut: Future<Output = Result<(), tonic::Status>>,
+    {
+        let mut sent = 0;
+        loop {
+            let message = match self.peek() {
+                Ok(Some(message)) => message,
+                Ok(None) => break,
+                Err(Error::Decode(e)) => {
+                    self.pop()?;
+                    return Err(Error::Decode(e));
+                }
+                Err(e) => return Err(e),
+            };
+            send(message).await.map_err(Error::Export)?;
+            self.pop()?;
+            sent += 1;
+        }
+        Ok(sent)
+    }
+
+    // Reads the record at the read position, `None` if it is corrupt
+    fn read_record(&mut self, id: u64, size: u64) -> Result<Option<Vec<u8>>, Error> {
+        let reader = match &mut self.reader {
+            Some((reader_id, reader)) if *reader_id == id => reader,
+            _ => {
+                let file = File::open(segment_path(&self.dir, id))?;
+                &mut self.reader.insert((id, file)).1
+            }
+        };
+        if self.read_offset + RECORD_HEADER_SIZE > size {
+            return Ok(None);
+        }
+        reader.seek(SeekFrom::Start(self.read_offset))?;
+        let mut header = [0_u8; RECORD_HEADER_SIZE as usize];
+        reader.read_exact(&mut header)?;
+        let (length, crc) = split_header(header);
+        if self.read_offset + RECORD_HEADER_SIZE + length > size {
+            return Ok(None);
+        }
+        let mut payload = vec![0_u8; length as usize];
+        reader.read_exact(&mut payload)?;
+        if crc32fast::hash(&payload) == crc {
+            Ok(Some(payload))
+        } else {
+            Ok(None)
+        }
+    }
+
+    // Renames a corrupt segment and moves the read position to the next one
+    fn set_aside(&mut self, id: u64) -> Result<Error, Error> {
+        let offset = self.read_offset;
+        let path = segment_path(&self.dir, id);
+        let corrupt = path.with_extension(CORRUPT_EXTENSION);
+        self.reader = None;
+        if self.segments.back().map(|last| last.id) == Some(id) {
+            self.writer = None;
+        }
+        fs::rename(&path, &corrupt)?;
+        self.segments.retain(|segment| segment.id != id);
+        self.read_segment = id + 1;
+        self.read_offset = 0;
+        self.front_size = None;
+        self.write_cursor()?;
+        Ok(Error::Corrupt {
+            segment: corrupt,
+            offset,
+        })
+    }
+
+    fn drop_oldest_segment(&mut self) -> Result<(), Error> {
+        if self.segments.len() == 1 {
+            // The segment written to is dropped as a whole, the next push starts a new one
+            self.writer = None;
+        }
+        let id = match self.segments.pop_front() {
+            Some(segment) => segment.id,
+            None => return Err(Error::Full),
+        };
+        self.remove_segment(id)?;
+        if self.read_segment <= id {
+            self.read_segment = id + 1;
+            self.read_offset = 0;
+            self.front_size = None;
+            self.write_cursor()?;
+        }
+        Ok(())
+    }
+
+    fn remove_segment(&mut self, id: u64) -> Result<(), Error> {
+        if matches!(self.reader, Some((reader_id, _)) if reader_id == id) {
+            self.reader = None;
+        }
+        match fs::remove_file(segment_path(&self.dir, id)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    // Discards a record cut short at the end of the last segment, so new
+    // records are appended after the last complete one
+    fn truncate_torn_record(&mut self) -> Result<(), Error> {
+        let last = match self.segments.back_mut() {
+            Some(last) => last,
+            None => return Ok(()),
+        };
+        let path = segment_path(&self.dir, last.id);
+        let mut file = File::open(&path)?;
+        let mut offset = 0;
+        let mut header = [0_u8; RECORD_HEADER_SIZE as usize];
+        while offset + RECORD_HEADER_SIZE <= last.size {
+            file.seek(SeekFrom::Start(offset))?;
+            file.read_exact(&mut header)?;
+            let (length, _crc) = split_header(header);
+            if offset + RECORD_HEADER_SIZE + length > last.size {
+                break;
+            }
+            offset += RECORD_HEADER_SIZE + length;
+        }
+        if offset < last.size {
+            OpenOptions::new()
+                .write(true)
+                .open(&path)?
+                .set_len(offset)?;
+            last.size = offset;
+        }
+        Ok(())
+    }
+
+    fn write_cursor(&mut self) -> Result<(), Error> {
+        let mut cursor = [0_u8; 16];
+        cursor[..8].copy_from_slice(&self.read_segment.to_le_bytes());
+        cursor[8..].copy_from_slice(&self.read_offset.to_le_bytes());
+        let path = self.dir.join(CURSOR_FILE);
+        let tmp = path.with_extension("tmp");
+        let mut file = File::create(&tmp)?;
+        file.write_all(&cursor)?;
+        if self.options.fsync == FsyncPolicy::Always {
+            file.sync_data()?;
+        }
+        fs::rename(tmp, path)?;
+        Ok(())
+    }
+}
+
+fn segment_path(dir: &Path, id: u64) -> PathBuf {
+    dir.join(format!("{:020}.{}", id, SEGMENT_EXTENSION))
+}
+
+fn split_header(header: [u8; RECORD_HEADER_SIZE as usize]) -> (u64, u32) {
+    let mut length = [0_u8; 4];
+    let mut crc = [0_u8; 4];
+    length.copy_from_slice(&header[..4]);
+    crc.copy_from_slice(&header[4..]);
+    (
+        u64::from(u32::from_le_bytes(length)),
+        u32::from_le_bytes(crc),
+    )
+}
+
+// The read position of the last run, the start of the first segment if unknown
+fn read_cursor(dir: &Path) -> Result<(u64, u64), Error> {
+    let mut cursor = [0_u8; 16];
+    match File::open(dir.join(CURSOR_FILE)).and_then(|mut file| file.read_exact(&mut cursor)) {
+        Ok(()) => {
+            let mut segment = [0_u8; 8];
+            let mut offset = [0_u8; 8];
+            segment.copy_from_slice(&cursor[..8]);
+            offset.copy_from_slice(&cursor[8..]);
+            Ok((u64::from_le_bytes(segment), u64::from_le_bytes(offset)))
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok((0, 0)),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok((0, 0)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> DiskQueueOptions {
+        DiskQueueOptions::new().fsync(FsyncPolicy::Never)
+    }
+
+    async fn drain_all<M: Message + Default>(queue: &mut DiskQueue<M>) -> Result<Vec<M>, Error> {
+        let mut drained = Vec::new();
+        queue
+            .drain(|message| {
+                drained.push(message);
+                async { Ok(()) }
+            })
+            .await?;
+        Ok(drained)
+    }
+
+    #[tokio::test]
+    async fn accepts_pushes_after_draining_a_full_queue() -> Result<(), Error> {
+        let dir = tempfile::tempdir()?;
+        let mut queue = DiskQueue::<String>::open(dir.path(), options().max_size(64))?;
+        let mut pushed = 0;
+        while queue.push(&"message".to_string()).is_ok() {
+            pushed += 1;
+        }
+        assert!(pushed > 0);
+        assert!(matches!(
+            queue.push(&"message".to_string()),
+            Err(Error::Full)
+        ));
+        assert_eq!(drain_all(&mut queue).await?.len(), pushed);
+        assert!(queue.is_empty());
+        assert_eq!(queue.size(), 0);
+        for _ in 0..pushed {
+            queue.push(&"message".to_string())?;
+        }
+        assert_eq!(drain_all(&mut queue).await?.len(), pushed);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn replays_unacknowledged_messages_after_reopening() -> Result<(), Error> {
+        let dir = tempfile::tempdir()?;
+        let mut queue = DiskQueue::<String>::open(dir.path(), options().max_segment_size(32))?;
+        for message in &["first", "second", "third"] {
+            queue.push(&message.to_string())?;
+        }
+        assert_eq!(queue.peek()?.as_deref(), Some("first"));
+        queue.pop()?;
+        drop(queue);
+
+        let mut queue = DiskQueue::<String>::open(dir.path(), options().max_segment_size(32))?;
+        assert_eq!(drain_all(&mut queue).await?, vec!["second", "third"]);
+        drop(queue);
+
+        let mut queue = DiskQueue::<String>::open(dir.path(), options())?;
+        assert!(queue.is_empty());
+        assert_eq!(queue.peek()?, None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn discards_a_torn_record_on_open() -> Result<(), Error> {
+        let dir = tempfile::tempdir()?;
+        let mut queue = DiskQueue::<String>::open(dir.path(), options())?;
+        queue.push(&"complete".to_string())?;
+        let size = queue.size();
+        drop(queue);
+
+        let mut segment = OpenOptions::new()
+            .append(true)
+            .open(segment_path(dir.path(), 0))?;
+        segment.write_all(&[100, 0, 0, 0, 1, 2, 3, 4, 5, 6])?;
+        drop(segment);
+
+        let mut queue = DiskQueue::<String>::open(dir.path(), options())?;
+        assert_eq!(queue.size(), size);
+        queue.push(&"appended".to_string())?;
+        assert_eq!(drain_all(&mut queue).await?, vec!["complete", "appended"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sets_aside_segments_with_corrupt_records() -> Result<(), Error> {
+        let dir = tempfile::tempdir()?;
+        let mut queue = DiskQueue::<String>::open(dir.path(), options().max_segment_size(32))?;
+        for message in &["first", "second", "third"] {
+            queue.push(&message.to_string())?;
+        }
+        drop(queue);
+
+        let path = segment_path(dir.path(), 0);
+        let mut bytes = fs::read(&path)?;
+        // The first record, the second one shares its segment
+        bytes[RECORD_HEADER_SIZE as usize] ^= 0xff;
+        fs::write(&path, bytes)?;
+
+        let mut queue = DiskQueue::<String>::open(dir.path(), options().max_segment_size(32))?;
+        match queue.peek() {
+            Err(Error::Corrupt { segment, offset }) => {
+                assert_eq!(segment, path.with_extension(CORRUPT_EXTENSION));
+                assert_eq!(offset, 0);
+                assert!(segment.exists());
+            }
+            other => panic!("expected a corrupt record, got {:?}", other),
+        }
+        assert_eq!(drain_all(&mut queue).await?, vec!["third"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn drain_skips_records_that_are_not_valid_messages() -> Result<(), Error> {
+        let dir = tempfile::tempdir()?;
+        let mut queue = DiskQueue::<Vec<u8>>::open(dir.path(), options())?;
+        queue.push(&vec![0xff, 0xfe])?;
+        queue.push(&b"valid".to_vec())?;
+        drop(queue);
+
+        let mut queue = DiskQueue::<String>::open(dir.path(), options())?;
+        assert!(matches!(drain_all(&mut queue).await, Err(Error::Decode(_))));
+        assert_eq!(drain_all(&mut queue).await?, vec!["valid"]);
+        Ok(())
+    }
+}
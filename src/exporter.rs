@@ -0,0 +1,640 @@
+// Copyright 2020-2022, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Exporters accept individual spans, log records or metrics and send them to
+//! an OTLP gRPC endpoint in batches. A batch is sent once it holds
+//! `max_batch_size` items or `max_delay` after its first item was queued,
+//! whichever comes first. Items are grouped per `Resource` and
+//! `InstrumentationScope` within each export request.
+//!
+//! Each exporter runs a background task on the tokio runtime it is built on.
+//! Failed exports are retried under the `RetryPolicy` of the exporter, if any,
+//! and are then passed to the error handler.
+//!
+//! ```ignore
+//! let channel = Endpoint::from_static("http://0.0.0.0:4317").connect().await?;
+//! let exporter = ExporterBuilder::new()
+//!     .max_batch_size(256)
+//!     .resource(resource)
+//!     .trace(channel);
+//!
+//! exporter.export(span).await?;
+//! exporter.shutdown().await;
+//! ```
+
+use crate::builders::{LogsRequestBuilder, MetricsRequestBuilder, TraceRequestBuilder};
+use crate::opentelemetry::proto::collector::logs::v1::logs_service_client::LogsServiceClient;
+use crate::opentelemetry::proto::collector::logs::v1::ExportLogsServiceRequest;
+use crate::opentelemetry::proto::collector::metrics::v1::metrics_service_client::MetricsServiceClient;
+use crate::opentelemetry::proto::collector::metrics::v1::ExportMetricsServiceRequest;
+use crate::opentelemetry::proto::collector::trace::v1::trace_service_client::TraceServiceClient;
+use crate::opentelemetry::proto::collector::trace::v1::ExportTraceServiceRequest;
+use crate::opentelemetry::proto::common::v1::InstrumentationScope;
+use crate::opentelemetry::proto::logs::v1::LogRecord;
+use crate::opentelemetry::proto::metrics::v1::Metric;
+use crate::opentelemetry::proto::resource::v1::Resource;
+use crate::opentelemetry::proto::trace::v1::Span;
+use crate::retry::RetryPolicy;
+use async_channel::{Receiver, Sender};
+use std::convert::TryFrom;
+use std::fmt;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{oneshot, Semaphore};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tonic::transport::Channel;
+use tonic::Status;
+
+/// Default maximum number of items per export request
+pub const DEFAULT_MAX_BATCH_SIZE: usize = 512;
+
+/// Default maximum delay before a partial batch is sent
+pub const DEFAULT_MAX_DELAY: Duration = Duration::from_secs(5);
+
+/// Default maximum number of items waiting to be batched
+pub const DEFAULT_QUEUE_SIZE: usize = 2048;
+
+/// Boxed export future
+pub type ExportFuture = Pin<Box<dyn Future<Output = Result<(), Status>> + Send + 'static>>;
+
+/// Items queued with their resource and instrumentation scope
+pub type Batch<T> = Vec<(Resource, InstrumentationScope, T)>;
+
+/// A signal that can be exported in batches
+pub trait Signal: Send + Sync + 'static {
+    /// The individual items of the signal
+    type Item: Send + 'static;
+
+    /// The export request of the signal
+    type Request: Clone + Send + Sync + 'static;
+
+    /// Builds an export request grouping the batched items per resource and scope
+    fn request(batch: Batch<Self::Item>) -> Self::Request;
+
+    /// Sends an export request, optionally gzip compressed
+    fn export(channel: Channel, request: Self::Request, gzip: bool) -> ExportFuture;
+}
+
+/// Spans, exported with the trace service
+#[derive(Debug)]
+pub struct Traces;
+
+impl Signal for Traces {
+    type Item = Span;
+    type Request = ExportTraceServiceRequest;
+
+    fn request(batch: Batch<Span>) -> ExportTraceServiceRequest {
+        let mut builder = TraceRequestBuilder::new();
+        for (resource, scope, item) in batch {
+            builder.push(&resource, &scope, item);
+        }
+        builder.build()
+    }
+
+    fn export(channel: Channel, request: ExportTraceServiceRequest, gzip: bool) -> ExportFuture {
+        Box::pin(async move {
+            let mut client = TraceServiceClient::new(channel);
+            if gzip {
+                client = client.send_gzip();
+            }
+            client.export(request).await.map(|_| ())
+        })
+    }
+}
+
+/// Log records, exported with the logs service
+#[derive(Debug)]
+pub struct Logs;
+
+impl Signal for Logs {
+    type Item = LogRecord;
+    type Request = ExportLogsServiceRequest;
+
+    fn request(batch: Batch<LogRecord>) -> ExportLogsServiceRequest {
+        let mut builder = LogsRequestBuilder::new();
+        for (resource, scope, item) in batch {
+            builder.push(&resource, &scope, item);
+        }
+        builder.build()
+    }
+
+    fn export(channel: Channel, request: ExportLogsServiceRequest, gzip: bool) -> ExportFuture {
+        Box::pin(async move {
+            let mut client = LogsServiceClient::new(channel);
+            if gzip {
+                client = client.send_gzip();
+            }
+            client.export(request).await.map(|_| ())
+        })
+    }
+}
+
+/// Metrics, exported with the metrics service
+#[derive(Debug)]
+pub struct Metrics;
+
+impl Signal for Metrics {
+    type Item = Metric;
+    type Request = ExportMetricsServiceRequest;
+
+    fn request(batch: Batch<Metric>) -> ExportMetricsServiceRequest {
+        let mut builder = MetricsRequestBuilder::new();
+        for (resource, scope, item) in batch {
+            builder.push(&resource, &scope, item);
+        }
+        builder.build()
+    }
+
+    fn export(channel: Channel, request: ExportMetricsServiceRequest, gzip: bool) -> ExportFuture {
+        Box::pin(async move {
+            let mut client = MetricsServiceClient::new(channel);
+            if gzip {
+                client = client.send_gzip();
+            }
+            client.export(request).await.map(|_| ())
+        })
+    }
+}
+
+/// Exporter of spans
+pub type TraceExporter = Exporter<Traces>;
+
+/// Exporter of log records
+pub type LogsExporter = Exporter<Logs>;
+
+/// Exporter of metrics
+pub type MetricsExporter = Exporter<Metrics>;
+
+/// Handler of failed exports
+pub type ErrorHandler = dyn Fn(Status) + Send + Sync + 'static;
+
+/// The error returned when items are exported after the exporter stopped
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExporterClosed;
+
+impl fmt::Display for ExporterClosed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Exporter is closed")
+    }
+}
+
+impl std::error::Error for ExporterClosed {}
+
+/// Builder for batching exporters
+#[derive(Clone)]
+pub struct ExporterBuilder {
+    max_batch_size: usize,
+    max_delay: Duration,
+    queue_size: usize,
+    max_concurrency: usize,
+    gzip: bool,
+    resource: Resource,
+    scope: InstrumentationScope,
+    on_error: Option<Arc<ErrorHandler>>,
+    retry: Option<RetryPolicy>,
+}
+
+impl Default for ExporterBuilder {
+    fn default() -> Self {
+        ExporterBuilder {
+            max_batch_size: DEFAULT_MAX_BATCH_SIZE,
+            max_delay: DEFAULT_MAX_DELAY,
+            queue_size: DEFAULT_QUEUE_SIZE,
+            max_concurrency: 1,
+            gzip: true,
+            resource: Resource::default(),
+            scope: InstrumentationScope::default(),
+            on_error: None,
+            retry: None,
+        }
+    }
+}
+
+impl ExporterBuilder {
+    /// Creates a builder with the default batching settings and gzip compression
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum number of items per export request
+    pub fn max_batch_size(mut self, size: usize) -> Self {
+        self.max_batch_size = size.max(1);
+        self
+    }
+
+    /// Sets the maximum delay between queueing an item and sending its batch
+    pub fn max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = delay;
+        self
+    }
+
+    /// Sets the maximum number of items waiting to be batched, exporting
+    /// waits while the queue is full
+    pub fn queue_size(mut self, size: usize) -> Self {
+        self.queue_size = size.max(1);
+        self
+    }
+
+    /// Sets the maximum number of export requests in flight
+    pub fn max_concurrency(mut self, concurrency: usize) -> Self {
+        self.max_concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Enables or disables gzip compression of export requests
+    pub fn gzip(mut self, enabled: bool) -> Self {
+        self.gzip = enabled;
+        self
+    }
+
+    /// Sets the resource of items exported without an explicit resource
+    pub fn resource(mut self, resource: Resource) -> Self {
+        self.resource = resource;
+        self
+    }
+
+    /// Sets the instrumentation scope of items exported without an explicit scope
+    pub fn scope(mut self, scope: InstrumentationScope) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    /// Handles the status of failed export requests, which are dropped otherwise
+    pub fn on_error<F: Fn(Status) + Send + Sync + 'static>(mut self, on_error: F) -> Self {
+        self.on_error = Some(Arc::new(on_error));
+        self
+    }
+
+    /// Retries failed export requests under the policy, failed requests are
+    /// not retried by default
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Builds a span exporter sending to the channel
+    pub fn trace(self, channel: Channel) -> TraceExporter {
+        self.build(channel)
+    }
+
+    /// Builds a log record exporter sending to the channel
+    pub fn logs(self, channel: Channel) -> LogsExporter {
+        self.build(channel)
+    }
+
+    /// Builds a metrics exporter sending to the channel
+    pub fn metrics(self, channel: Channel) -> MetricsExporter {
+        self.build(channel)
+    }
+
+    /// Builds an exporter of a signal sending to the channel, spawning its
+    /// background task on the current tokio runtime
+    pub fn build<S: Signal>(self, channel: Channel) -> Exporter<S> {
+        let (commands, queued) = async_channel::bounded(self.queue_size);
+        let resource = self.resource.clone();
+        let scope = self.scope.clone();
+        let worker = tokio::spawn(run::<S>(queued, channel, self));
+        Exporter {
+            commands,
+            worker,
+            resource,
+            scope,
+            signal: PhantomData,
+        }
+    }
+}
+
+enum Command<T> {
+    Export(Resource, InstrumentationScope, T),
+    Flush(oneshot::Sender<()>),
+}
+
+/// A batching exporter of a signal
+pub struct Exporter<S: Signal> {
+    commands: Sender<Command<S::Item>>,
+    worker: JoinHandle<()>,
+    resource: Resource,
+    scope: InstrumentationScope,
+    signal: PhantomData<S>,
+}
+
+impl<S: Signal> Exporter<S> {
+    /// Queues an item with the default resource and instrumentation scope
+    pub async fn export(&self, item: S::Item) -> Result<(), ExporterClosed> {
+        self.export_with(self.resource.clone(), self.scope.clone(), item)
+            .await
+    }
+
+    /// Queues an item with the specified resource and instrumentation scope
+    pub async fn export_with(
+        &self,
+        resource: Resource,
+        scope: InstrumentationScope,
+        item: S::Item,
+    ) -> Result<(), ExporterClosed> {
+        self.commands
+            .send(Command::Export(resource, scope, item))
+            .await
+            .map_err(|_| ExporterClosed)
+    }
+
+    /// Sends the queued items and waits for all export requests to complete
+    pub async fn flush(&self) -> Result<(), ExporterClosed> {
+        let (done, flushed) = oneshot::channel();
+        self.commands
+            .send(Command::Flush(done))
+            .await
+            .map_err(|_| ExporterClosed)?;
+        flushed.await.map_err(|_| ExporterClosed)
+    }
+
+    /// Sends the queued items, waits for all export requests to complete and
+    /// stops the exporter
+    pub async fn shutdown(self) {
+        self.commands.close();
+        // A panicked worker has nothing left to send
+        let _stopped = self.worker.await;
+    }
+}
+
+async fn run<S: Signal>(
+    queued: Receiver<Command<S::Item>>,
+    channel: Channel,
+    config: ExporterBuilder,
+) {
+    let permits = Arc::new(Semaphore::new(config.max_concurrency));
+    let mut batch = Vec::with_capacity(config.max_batch_size);
+    let mut deadline = None;
+    loop {
+        let command = match deadline {
+            Some(at) => match tokio::time::timeout_at(at, queued.recv()).await {
+                Ok(command) => command.ok(),
+                Err(_) => {
+                    send::<S>(&mut batch, &channel, &config, &permits).await;
+                    deadline = None;
+                    continue;
+                }
+            },
+            None => queued.recv().await.ok(),
+        };
+        match command {
+            Some(Command::Export(resource, scope, item)) => {
+                if batch.is_empty() {
+                    deadline = Some(Instant::now() + config.max_delay);
+                }
+                batch.push((resource, scope, item));
+                if batch.len() >= config.max_batch_size {
+                    send::<S>(&mut batch, &channel, &config, &permits).await;
+                    deadline = None;
+                }
+            }
+            Some(Command::Flush(done)) => {
+                send::<S>(&mut batch, &channel, &config, &permits).await;
+                deadline = None;
+                drain(&permits, config.max_concurrency).await;
+                // The flushing caller may have given up waiting
+                let _flushed = done.send(());
+            }
+            None => {
+                send::<S>(&mut batch, &channel, &config, &permits).await;
+                drain(&permits, config.max_concurrency).await;
+                return;
+            }
+        }
+    }
+}
+
+// Sends the batch once a concurrency permit is available
+async fn send<S: Signal>(
+    batch: &mut Batch<S::Item>,
+    channel: &Channel,
+    config: &ExporterBuilder,
+    permits: &Arc<Semaphore>,
+) {
+    if batch.is_empty() {
+        return;
+    }
+    let request = S::request(std::mem::take(batch));
+    let permit = match permits.clone().acquire_owned().await {
+        Ok(permit) => permit,
+        // The semaphore is never closed
+        Err(_) => return,
+    };
+    let channel = channel.clone();
+    let gzip = config.gzip;
+    let retry = config.retry.clone();
+    let on_error = config.on_error.clone();
+    tokio::spawn(async move {
+        let exported = match retry {
+            Some(policy) => {
+                policy
+                    .call(|| S::export(channel.clone(), request.clone(), gzip))
+                    .await
+            }
+            None => S::export(channel, request, gzip).await,
+        };
+        if let (Err(status), Some(on_error)) = (exported, on_error) {
+            on_error(status);
+        }
+        drop(permit);
+    });
+}
+
+// Waits until no export request is in flight
+async fn drain(permits: &Semaphore, max_concurrency: usize) {
+    let all = u32::try_from(max_concurrency).unwrap_or(u32::MAX);
+    if let Ok(permits) = permits.acquire_many(all).await {
+        drop(permits);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opentelemetry::proto::collector::trace::v1::ExportTraceServiceResponse;
+    use crate::trace::{TraceService, TraceServiceServer};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tonic::transport::{Endpoint, Server, Uri};
+
+    type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+    // Records export requests, holding each one until the gate lets it pass
+    #[derive(Clone)]
+    struct MockTrace {
+        requests: Sender<ExportTraceServiceRequest>,
+        gate: Arc<Semaphore>,
+        in_flight: Arc<AtomicUsize>,
+        peak: Arc<AtomicUsize>,
+    }
+
+    #[tonic::async_trait]
+    impl TraceService for MockTrace {
+        async fn export(
+            &self,
+            request: tonic::Request<ExportTraceServiceRequest>,
+        ) -> Result<tonic::Response<ExportTraceServiceResponse>, Status> {
+            let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(in_flight, Ordering::SeqCst);
+            let _passed = self.gate.acquire().await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            let _recorded = self.requests.send(request.into_inner()).await;
+            Ok(tonic::Response::new(ExportTraceServiceResponse::default()))
+        }
+    }
+
+    // Serves a mock trace service over in memory connections
+    fn mock(
+        permits: usize,
+    ) -> Result<(Channel, MockTrace, Receiver<ExportTraceServiceRequest>), tonic::transport::Error>
+    {
+        let (requests, received) = async_channel::unbounded();
+        let mock = MockTrace {
+            requests,
+            gate: Arc::new(Semaphore::new(permits)),
+            in_flight: Arc::new(AtomicUsize::new(0)),
+            peak: Arc::new(AtomicUsize::new(0)),
+        };
+        let (connections, incoming) = async_channel::unbounded();
+        tokio::spawn(
+            Server::builder()
+                .add_service(TraceServiceServer::new(mock.clone()).accept_gzip())
+                .serve_with_incoming(incoming),
+        );
+        let channel = Endpoint::from_static("http://mock").connect_with_connector_lazy(
+            tower::service_fn(move |_: Uri| {
+                let connections = connections.clone();
+                async move {
+                    let (client, server) = tokio::io::duplex(1 << 16);
+                    connections
+                        .send(Ok::<_, std::io::Error>(server))
+                        .await
+                        .map_err(|_| std::io::Error::from(std::io::ErrorKind::BrokenPipe))?;
+                    Ok::<_, std::io::Error>(client)
+                }
+            }),
+        )?;
+        Ok((channel, mock, received))
+    }
+
+    fn span(name: &str) -> Span {
+        Span {
+            name: name.to_string(),
+            ..Span::default()
+        }
+    }
+
+    fn span_names(request: &ExportTraceServiceRequest) -> Vec<String> {
+        request
+            .resource_spans
+            .iter()
+            .flat_map(|resource_spans| &resource_spans.scope_spans)
+            .flat_map(|scope_spans| &scope_spans.spans)
+            .map(|span| span.name.clone())
+            .collect()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn sends_full_batches_without_waiting() -> TestResult {
+        let (channel, _mock, received) = mock(usize::MAX >> 4)?;
+        let exporter = ExporterBuilder::new()
+            .max_batch_size(2)
+            .max_delay(Duration::from_secs(3600))
+            .trace(channel);
+        let started = Instant::now();
+        for name in ["a", "b", "c"] {
+            exporter.export(span(name)).await?;
+        }
+        assert_eq!(span_names(&received.recv().await?), ["a", "b"]);
+        assert!(started.elapsed() < Duration::from_secs(3600));
+        assert!(received.is_empty());
+        exporter.shutdown().await;
+        assert_eq!(span_names(&received.recv().await?), ["c"]);
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn sends_partial_batches_after_the_delay() -> TestResult {
+        let (channel, _mock, received) = mock(usize::MAX >> 4)?;
+        let exporter = ExporterBuilder::new()
+            .max_batch_size(10)
+            .max_delay(Duration::from_secs(5))
+            .trace(channel);
+        let started = Instant::now();
+        exporter.export(span("a")).await?;
+        exporter.export(span("b")).await?;
+        assert_eq!(span_names(&received.recv().await?), ["a", "b"]);
+        assert!(started.elapsed() >= Duration::from_secs(5));
+        assert!(started.elapsed() < Duration::from_secs(6));
+        exporter.shutdown().await;
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn limits_the_exports_in_flight() -> TestResult {
+        let (channel, mock, received) = mock(0)?;
+        let exporter = ExporterBuilder::new()
+            .max_batch_size(1)
+            .max_concurrency(2)
+            .trace(channel);
+        for name in ["a", "b", "c", "d"] {
+            exporter.export(span(name)).await?;
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(mock.in_flight.load(Ordering::SeqCst), 2);
+        assert!(received.is_empty());
+
+        mock.gate.add_permits(usize::MAX >> 4);
+        exporter.flush().await?;
+        assert_eq!(received.len(), 4);
+        assert_eq!(mock.peak.load(Ordering::SeqCst), 2);
+        exporter.shutdown().await;
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn flush_and_shutdown_send_the_queued_items() -> TestResult {
+        let (channel, _mock, received) = mock(usize::MAX >> 4)?;
+        let exporter = ExporterBuilder::new()
+            .max_delay(Duration::from_secs(3600))
+            .trace(channel);
+        exporter.export(span("a")).await?;
+        exporter.export(span("b")).await?;
+        exporter.flush().await?;
+        assert_eq!(span_names(&received.try_recv()?), ["a", "b"]);
+
+        exporter.export(span("c")).await?;
+        exporter.shutdown().await;
+        assert_eq!(span_names(&received.try_recv()?), ["c"]);
+        assert!(received.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rejects_items_once_closed() -> TestResult {
+        let (channel, _mock, received) = mock(usize::MAX >> 4)?;
+        let exporter = ExporterBuilder::new().trace(channel);
+        exporter.export(span("a")).await?;
+        // Closes the queue the way shutdown does
+        exporter.commands.close();
+        assert_eq!(exporter.export(span("b")).await, Err(ExporterClosed));
+        assert_eq!(exporter.flush().await, Err(ExporterClosed));
+        exporter.shutdown().await;
+        assert_eq!(span_names(&received.try_recv()?), ["a"]);
+        Ok(())
+    }
+}
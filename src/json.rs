@@ -0,0 +1,300 @@
+// Copyright 2020-2022, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The generated messages implement `serde::Serialize` and `serde::Deserialize`
+//! following the OTLP/JSON mapping of the OpenTelemetry protocol specification:
+//!
+//! * field names are lowerCamelCase
+//! * trace and span ids are hex encoded, other bytes are base64 encoded
+//! * enumerations are integers
+//! * 64 bit integers are decimal strings, numbers are also accepted when decoding
+//! * oneof fields are inlined into the containing message
+//! * missing fields take their default values
+//!
+//! ```ignore
+//! let request: ExportTraceServiceRequest = tremor_otelapis::json::from_slice(&body)?;
+//! let body = tremor_otelapis::json::to_vec(&request)?;
+//! ```
+
+use serde::de::{DeserializeOwned, Error, SeqAccess, Visitor};
+use serde::{Deserializer, Serialize, Serializer};
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+/// Content type of JSON encoded OTLP payloads
+pub const JSON_CONTENT_TYPE: &str = "application/json";
+
+/// Encodes a message as OTLP/JSON
+pub fn to_vec<T: Serialize>(message: &T) -> serde_json::Result<Vec<u8>> {
+    serde_json::to_vec(message)
+}
+
+/// Encodes a message as an OTLP/JSON string
+pub fn to_string<T: Serialize>(message: &T) -> serde_json::Result<String> {
+    serde_json::to_string(message)
+}
+
+/// Decodes a message from OTLP/JSON
+pub fn from_slice<T: DeserializeOwned>(json: &[u8]) -> serde_json::Result<T> {
+    serde_json::from_slice(json)
+}
+
+/// Decodes a message from an OTLP/JSON string
+pub fn from_str<T: DeserializeOwned>(json: &str) -> serde_json::Result<T> {
+    serde_json::from_str(json)
+}
+
+// Accepts integers encoded as strings or as JSON numbers
+struct IntVisitor<T>(std::marker::PhantomData<T>);
+
+impl<'de, T> Visitor<'de> for IntVisitor<T>
+where
+    T: FromStr + TryFrom<u64> + TryFrom<i64>,
+{
+    type Value = T;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a 64 bit integer or a string containing one")
+    }
+
+    fn visit_u64<E: Error>(self, v: u64) -> Result<T, E> {
+        T::try_from(v).map_err(|_| E::custom(format!("integer {} out of range", v)))
+    }
+
+    fn visit_i64<E: Error>(self, v: i64) -> Result<T, E> {
+        T::try_from(v).map_err(|_| E::custom(format!("integer {} out of range", v)))
+    }
+
+    fn visit_str<E: Error>(self, v: &str) -> Result<T, E> {
+        v.parse()
+            .map_err(|_| E::custom(format!("invalid integer {:?}", v)))
+    }
+}
+
+pub(crate) mod u64_string {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(value: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        deserializer.deserialize_any(IntVisitor(std::marker::PhantomData))
+    }
+}
+
+pub(crate) mod i64_string {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(value: &i64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i64, D::Error> {
+        deserializer.deserialize_any(IntVisitor(std::marker::PhantomData))
+    }
+}
+
+pub(crate) mod u64_string_seq {
+    use super::*;
+    use serde::ser::SerializeSeq;
+
+    pub(crate) fn serialize<S: Serializer>(
+        values: &[u64],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(values.len()))?;
+        for value in values {
+            seq.serialize_element(&value.to_string())?;
+        }
+        seq.end()
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<u64>, D::Error> {
+        struct SeqVisitor;
+
+        impl<'de> Visitor<'de> for SeqVisitor {
+            type Value = Vec<u64>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a sequence of 64 bit integers")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<u64>, A::Error> {
+                let mut values = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+                while let Some(Element(value)) = seq.next_element()? {
+                    values.push(value);
+                }
+                Ok(values)
+            }
+        }
+
+        struct Element(u64);
+
+        impl<'de> serde::Deserialize<'de> for Element {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                u64_string::deserialize(deserializer).map(Element)
+            }
+        }
+
+        deserializer.deserialize_seq(SeqVisitor)
+    }
+}
+
+pub(crate) mod hex_bytes {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(value: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        let mut hex = String::with_capacity(value.len() * 2);
+        for byte in value {
+            hex.push_str(&format!("{:02x}", byte));
+        }
+        serializer.serialize_str(&hex)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<u8>, D::Error> {
+        let hex: String = serde::Deserialize::deserialize(deserializer)?;
+        if hex.len() % 2 != 0 {
+            return Err(D::Error::custom(format!("invalid hex id {:?}", hex)));
+        }
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| {
+                hex.get(i..i + 2)
+                    .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                    .ok_or_else(|| D::Error::custom(format!("invalid hex id {:?}", hex)))
+            })
+            .collect()
+    }
+}
+
+pub(crate) mod base64_bytes {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(value: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&base64::encode(value))
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<u8>, D::Error> {
+        let encoded: String = serde::Deserialize::deserialize(deserializer)?;
+        base64::decode(&encoded).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(all(test, feature = "otel-all"))]
+mod tests {
+    use super::*;
+    use crate::opentelemetry::proto::common::v1::{any_value, AnyValue, KeyValue};
+    use crate::opentelemetry::proto::metrics::v1::HistogramDataPoint;
+    use crate::opentelemetry::proto::trace::v1::{span, status, Span, Status};
+    use serde_json::{json, Value};
+
+    fn span() -> Span {
+        Span {
+            trace_id: vec![
+                0x5b, 0x8e, 0xff, 0xf7, 0x98, 0x03, 0x81, 0x03, 0xd2, 0x69, 0xb6, 0x33, 0x81, 0x3f,
+                0xc6, 0x0c,
+            ],
+            span_id: vec![0xee, 0xe1, 0x9b, 0x7e, 0xc3, 0xc1, 0xb1, 0x74],
+            name: "GET /".to_string(),
+            kind: span::SpanKind::Server as i32,
+            start_time_unix_nano: 1_544_712_660_000_000_000,
+            end_time_unix_nano: u64::MAX,
+            attributes: vec![KeyValue {
+                key: "payload".to_string(),
+                value: Some(AnyValue {
+                    value: Some(any_value::Value::BytesValue(b"otel".to_vec())),
+                }),
+                ..KeyValue::default()
+            }],
+            status: Some(Status {
+                code: status::StatusCode::Error as i32,
+                ..Status::default()
+            }),
+            ..Span::default()
+        }
+    }
+
+    fn encode<T: Serialize>(message: &T) -> Value {
+        to_vec(message)
+            .and_then(|json| from_slice(&json))
+            .unwrap_or(Value::Null)
+    }
+
+    #[test]
+    fn encodes_the_otlp_json_mapping() {
+        let json = encode(&span());
+        assert_eq!(json["traceId"], "5b8efff798038103d269b633813fc60c");
+        assert_eq!(json["spanId"], "eee19b7ec3c1b174");
+        assert_eq!(json["parentSpanId"], "");
+        assert_eq!(json["startTimeUnixNano"], "1544712660000000000");
+        assert_eq!(json["endTimeUnixNano"], "18446744073709551615");
+        assert_eq!(json["kind"], 2);
+        assert_eq!(json["status"]["code"], 2);
+        assert_eq!(
+            json["attributes"][0]["value"],
+            json!({ "bytesValue": "b3RlbA==" })
+        );
+    }
+
+    #[test]
+    fn round_trips_messages() {
+        let span = span();
+        let decoded = to_string(&span).and_then(|json| from_str::<Span>(&json));
+        assert_eq!(decoded.ok(), Some(span));
+    }
+
+    #[test]
+    fn decodes_64_bit_integers_from_strings_and_numbers() {
+        let point: Option<HistogramDataPoint> =
+            from_str(r#"{"timeUnixNano": 17, "count": "3", "bucketCounts": ["1", 2], "sum": 4.5}"#)
+                .ok();
+        assert_eq!(point.as_ref().map(|point| point.time_unix_nano), Some(17));
+        assert_eq!(point.as_ref().map(|point| point.count), Some(3));
+        assert_eq!(
+            point.as_ref().map(|point| point.bucket_counts.clone()),
+            Some(vec![1, 2])
+        );
+        assert_eq!(encode(&point)["bucketCounts"], json!(["1", "2"]));
+
+        let value: Option<AnyValue> = from_str(r#"{"intValue": "-9223372036854775808"}"#).ok();
+        assert_eq!(
+            value.and_then(|value| value.value),
+            Some(any_value::Value::IntValue(i64::MIN))
+        );
+        assert!(from_str::<HistogramDataPoint>(r#"{"count": -1}"#).is_err());
+        assert!(from_str::<HistogramDataPoint>(r#"{"count": "many"}"#).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_hex_ids() {
+        assert!(from_str::<Span>(r#"{"traceId": "5b8"}"#).is_err());
+        assert!(from_str::<Span>(r#"{"spanId": "eee19b7ec3c1b17g"}"#).is_err());
+        assert!(from_str::<Span>(r#"{"spanId": "EEE19B7EC3C1B174"}"#).is_ok());
+    }
+
+    #[test]
+    fn encodes_enumerations_as_integers() {
+        let kind: Option<Span> = from_str(r#"{"kind": 3}"#).ok();
+        assert_eq!(kind.map(|span| span.kind()), Some(span::SpanKind::Client));
+        assert!(from_str::<Span>(r#"{"kind": "SPAN_KIND_CLIENT"}"#).is_err());
+    }
+}
@@ -0,0 +1,770 @@
+// Copyright 2020-2022, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Agents built against opentelemetry-proto 0.9.0 emit `InstrumentationLibrary*`
+//! containers and the deprecated `IntGauge`, `IntSum` and `IntHistogram` metrics,
+//! whose fields are reserved and therefore dropped when decoded with the current
+//! bindings. Decode such payloads with the legacy bindings in this module and
+//! convert them with `From` to normalize them onto the current protocol:
+//!
+//! * `InstrumentationLibrary*` become `Scope*` and `InstrumentationLibrary` becomes
+//!   an `InstrumentationScope`
+//! * `IntGauge` and `IntSum` become `Gauge` and `Sum` with `as_int` data points
+//! * `IntHistogram` becomes a `Histogram` with the integer sum as a double
+//! * deprecated `labels` are appended to the `attributes` as string values
+//! * a span status with only a non-ok `deprecated_code` becomes `STATUS_CODE_ERROR`
+//! * the log record `name` becomes the `event_name`
+//!
+//! ```ignore
+//! use tremor_otelapis::legacy::opentelemetry::proto::collector::metrics::v1 as legacy;
+//! use tremor_otelapis::opentelemetry::proto::collector::metrics::v1 as current;
+//!
+//! let request = legacy::ExportMetricsServiceRequest::decode(body)?;
+//! let request = current::ExportMetricsServiceRequest::from(request);
+//! ```
+
+pub use crate::otelapis::legacy::opentelemetry;
+
+use self::opentelemetry::proto as old;
+use crate::opentelemetry::proto as new;
+
+fn convert<A: Into<B>, B>(values: Vec<A>) -> Vec<B> {
+    values.into_iter().map(Into::into).collect()
+}
+
+fn attributes(
+    attributes: Vec<old::common::v1::KeyValue>,
+    labels: Vec<old::common::v1::StringKeyValue>,
+) -> Vec<new::common::v1::KeyValue> {
+    let mut attributes: Vec<new::common::v1::KeyValue> = convert(attributes);
+    attributes.extend(labels.into_iter().map(Into::into));
+    attributes
+}
+
+// Common
+
+impl From<old::common::v1::AnyValue> for new::common::v1::AnyValue {
+    fn from(value: old::common::v1::AnyValue) -> Self {
+        Self {
+            value: value.value.map(Into::into),
+        }
+    }
+}
+
+impl From<old::common::v1::any_value::Value> for new::common::v1::any_value::Value {
+    fn from(value: old::common::v1::any_value::Value) -> Self {
+        use new::common::v1::any_value::Value;
+        use old::common::v1::any_value::Value as Old;
+        match value {
+            Old::StringValue(v) => Value::StringValue(v),
+            Old::BoolValue(v) => Value::BoolValue(v),
+            Old::IntValue(v) => Value::IntValue(v),
+            Old::DoubleValue(v) => Value::DoubleValue(v),
+            Old::ArrayValue(v) => Value::ArrayValue(v.into()),
+            Old::KvlistValue(v) => Value::KvlistValue(v.into()),
+            Old::BytesValue(v) => Value::BytesValue(v),
+        }
+    }
+}
+
+impl From<old::common::v1::ArrayValue> for new::common::v1::ArrayValue {
+    fn from(value: old::common::v1::ArrayValue) -> Self {
+        Self {
+            values: convert(value.values),
+        }
+    }
+}
+
+impl From<old::common::v1::KeyValueList> for new::common::v1::KeyValueList {
+    fn from(value: old::common::v1::KeyValueList) -> Self {
+        Self {
+            values: convert(value.values),
+        }
+    }
+}
+
+impl From<old::common::v1::KeyValue> for new::common::v1::KeyValue {
+    fn from(kv: old::common::v1::KeyValue) -> Self {
+        Self {
+            key: kv.key,
+            value: kv.value.map(Into::into),
+            ..Self::default()
+        }
+    }
+}
+
+impl From<old::common::v1::StringKeyValue> for new::common::v1::KeyValue {
+    fn from(kv: old::common::v1::StringKeyValue) -> Self {
+        Self {
+            key: kv.key,
+            value: Some(new::common::v1::AnyValue {
+                value: Some(new::common::v1::any_value::Value::StringValue(kv.value)),
+            }),
+            ..Self::default()
+        }
+    }
+}
+
+impl From<old::common::v1::InstrumentationLibrary> for new::common::v1::InstrumentationScope {
+    fn from(library: old::common::v1::InstrumentationLibrary) -> Self {
+        Self {
+            name: library.name,
+            version: library.version,
+            ..Self::default()
+        }
+    }
+}
+
+impl From<old::resource::v1::Resource> for new::resource::v1::Resource {
+    fn from(resource: old::resource::v1::Resource) -> Self {
+        Self {
+            attributes: convert(resource.attributes),
+            dropped_attributes_count: resource.dropped_attributes_count,
+            ..Self::default()
+        }
+    }
+}
+
+// Trace
+
+impl From<old::collector::trace::v1::ExportTraceServiceRequest>
+    for new::collector::trace::v1::ExportTraceServiceRequest
+{
+    fn from(request: old::collector::trace::v1::ExportTraceServiceRequest) -> Self {
+        Self {
+            resource_spans: convert(request.resource_spans),
+        }
+    }
+}
+
+impl From<old::trace::v1::ResourceSpans> for new::trace::v1::ResourceSpans {
+    fn from(spans: old::trace::v1::ResourceSpans) -> Self {
+        Self {
+            resource: spans.resource.map(Into::into),
+            scope_spans: convert(spans.instrumentation_library_spans),
+            schema_url: spans.schema_url,
+        }
+    }
+}
+
+impl From<old::trace::v1::InstrumentationLibrarySpans> for new::trace::v1::ScopeSpans {
+    fn from(spans: old::trace::v1::InstrumentationLibrarySpans) -> Self {
+        Self {
+            scope: spans.instrumentation_library.map(Into::into),
+            spans: convert(spans.spans),
+            schema_url: spans.schema_url,
+        }
+    }
+}
+
+impl From<old::trace::v1::Span> for new::trace::v1::Span {
+    fn from(span: old::trace::v1::Span) -> Self {
+        Self {
+            trace_id: span.trace_id,
+            span_id: span.span_id,
+            trace_state: span.trace_state,
+            parent_span_id: span.parent_span_id,
+            flags: 0,
+            name: span.name,
+            kind: span.kind,
+            start_time_unix_nano: span.start_time_unix_nano,
+            end_time_unix_nano: span.end_time_unix_nano,
+            attributes: convert(span.attributes),
+            dropped_attributes_count: span.dropped_attributes_count,
+            events: convert(span.events),
+            dropped_events_count: span.dropped_events_count,
+            links: convert(span.links),
+            dropped_links_count: span.dropped_links_count,
+            status: span.status.map(Into::into),
+        }
+    }
+}
+
+impl From<old::trace::v1::span::Event> for new::trace::v1::span::Event {
+    fn from(event: old::trace::v1::span::Event) -> Self {
+        Self {
+            time_unix_nano: event.time_unix_nano,
+            name: event.name,
+            attributes: convert(event.attributes),
+            dropped_attributes_count: event.dropped_attributes_count,
+        }
+    }
+}
+
+impl From<old::trace::v1::span::Link> for new::trace::v1::span::Link {
+    fn from(link: old::trace::v1::span::Link) -> Self {
+        Self {
+            trace_id: link.trace_id,
+            span_id: link.span_id,
+            trace_state: link.trace_state,
+            attributes: convert(link.attributes),
+            dropped_attributes_count: link.dropped_attributes_count,
+            flags: 0,
+        }
+    }
+}
+
+impl From<old::trace::v1::Status> for new::trace::v1::Status {
+    fn from(status: old::trace::v1::Status) -> Self {
+        use new::trace::v1::status::StatusCode;
+        use old::trace::v1::status::DeprecatedStatusCode;
+        // Senders predating `code` only set `deprecated_code`
+        #[allow(deprecated)]
+        let code = if status.code == StatusCode::Unset as i32
+            && status.deprecated_code != DeprecatedStatusCode::Ok as i32
+        {
+            StatusCode::Error as i32
+        } else {
+            status.code
+        };
+        Self {
+            message: status.message,
+            code,
+        }
+    }
+}
+
+// Logs
+
+impl From<old::collector::logs::v1::ExportLogsServiceRequest>
+    for new::collector::logs::v1::ExportLogsServiceRequest
+{
+    fn from(request: old::collector::logs::v1::ExportLogsServiceRequest) -> Self {
+        Self {
+            resource_logs: convert(request.resource_logs),
+        }
+    }
+}
+
+impl From<old::logs::v1::ResourceLogs> for new::logs::v1::ResourceLogs {
+    fn from(logs: old::logs::v1::ResourceLogs) -> Self {
+        Self {
+            resource: logs.resource.map(Into::into),
+            scope_logs: convert(logs.instrumentation_library_logs),
+            schema_url: logs.schema_url,
+        }
+    }
+}
+
+impl From<old::logs::v1::InstrumentationLibraryLogs> for new::logs::v1::ScopeLogs {
+    fn from(logs: old::logs::v1::InstrumentationLibraryLogs) -> Self {
+        Self {
+            scope: logs.instrumentation_library.map(Into::into),
+            log_records: convert(logs.logs),
+            schema_url: logs.schema_url,
+        }
+    }
+}
+
+impl From<old::logs::v1::LogRecord> for new::logs::v1::LogRecord {
+    fn from(record: old::logs::v1::LogRecord) -> Self {
+        Self {
+            time_unix_nano: record.time_unix_nano,
+            observed_time_unix_nano: 0,
+            severity_number: record.severity_number,
+            severity_text: record.severity_text,
+            body: record.body.map(Into::into),
+            attributes: convert(record.attributes),
+            dropped_attributes_count: record.dropped_attributes_count,
+            flags: record.flags,
+            trace_id: record.trace_id,
+            span_id: record.span_id,
+            event_name: record.name,
+        }
+    }
+}
+
+// Metrics
+
+impl From<old::collector::metrics::v1::ExportMetricsServiceRequest>
+    for new::collector::metrics::v1::ExportMetricsServiceRequest
+{
+    fn from(request: old::collector::metrics::v1::ExportMetricsServiceRequest) -> Self {
+        Self {
+            resource_metrics: convert(request.resource_metrics),
+        }
+    }
+}
+
+impl From<old::metrics::v1::ResourceMetrics> for new::metrics::v1::ResourceMetrics {
+    fn from(metrics: old::metrics::v1::ResourceMetrics) -> Self {
+        Self {
+            resource: metrics.resource.map(Into::into),
+            scope_metrics: convert(metrics.instrumentation_library_metrics),
+            schema_url: metrics.schema_url,
+        }
+    }
+}
+
+impl From<old::metrics::v1::InstrumentationLibraryMetrics> for new::metrics::v1::ScopeMetrics {
+    fn from(metrics: old::metrics::v1::InstrumentationLibraryMetrics) -> Self {
+        Self {
+            scope: metrics.instrumentation_library.map(Into::into),
+            metrics: convert(metrics.metrics),
+            schema_url: metrics.schema_url,
+        }
+    }
+}
+
+impl From<old::metrics::v1::Metric> for new::metrics::v1::Metric {
+    fn from(metric: old::metrics::v1::Metric) -> Self {
+        Self {
+            name: metric.name,
+            description: metric.description,
+            unit: metric.unit,
+            metadata: Vec::new(),
+            data: metric.data.map(Into::into),
+        }
+    }
+}
+
+impl From<old::metrics::v1::metric::Data> for new::metrics::v1::metric::Data {
+    #[allow(deprecated)]
+    fn from(data: old::metrics::v1::metric::Data) -> Self {
+        use new::metrics::v1::metric::Data;
+        use old::metrics::v1::metric::Data as Old;
+        match data {
+            Old::IntGauge(v) => Data::Gauge(v.into()),
+            Old::Gauge(v) => Data::Gauge(v.into()),
+            Old::IntSum(v) => Data::Sum(v.into()),
+            Old::Sum(v) => Data::Sum(v.into()),
+            Old::IntHistogram(v) => Data::Histogram(v.into()),
+            Old::Histogram(v) => Data::Histogram(v.into()),
+            Old::Summary(v) => Data::Summary(v.into()),
+        }
+    }
+}
+
+#[allow(deprecated)]
+impl From<old::metrics::v1::IntGauge> for new::metrics::v1::Gauge {
+    fn from(gauge: old::metrics::v1::IntGauge) -> Self {
+        Self {
+            data_points: convert(gauge.data_points),
+        }
+    }
+}
+
+impl From<old::metrics::v1::Gauge> for new::metrics::v1::Gauge {
+    fn from(gauge: old::metrics::v1::Gauge) -> Self {
+        Self {
+            data_points: convert(gauge.data_points),
+        }
+    }
+}
+
+#[allow(deprecated)]
+impl From<old::metrics::v1::IntSum> for new::metrics::v1::Sum {
+    fn from(sum: old::metrics::v1::IntSum) -> Self {
+        Self {
+            data_points: convert(sum.data_points),
+            aggregation_temporality: sum.aggregation_temporality,
+            is_monotonic: sum.is_monotonic,
+        }
+    }
+}
+
+impl From<old::metrics::v1::Sum> for new::metrics::v1::Sum {
+    fn from(sum: old::metrics::v1::Sum) -> Self {
+        Self {
+            data_points: convert(sum.data_points),
+            aggregation_temporality: sum.aggregation_temporality,
+            is_monotonic: sum.is_monotonic,
+        }
+    }
+}
+
+#[allow(deprecated)]
+impl From<old::metrics::v1::IntHistogram> for new::metrics::v1::Histogram {
+    fn from(histogram: old::metrics::v1::IntHistogram) -> Self {
+        Self {
+            data_points: convert(histogram.data_points),
+            aggregation_temporality: histogram.aggregation_temporality,
+        }
+    }
+}
+
+impl From<old::metrics::v1::Histogram> for new::metrics::v1::Histogram {
+    fn from(histogram: old::metrics::v1::Histogram) -> Self {
+        Self {
+            data_points: convert(histogram.data_points),
+            aggregation_temporality: histogram.aggregation_temporality,
+        }
+    }
+}
+
+impl From<old::metrics::v1::Summary> for new::metrics::v1::Summary {
+    fn from(summary: old::metrics::v1::Summary) -> Self {
+        Self {
+            data_points: convert(summary.data_points),
+        }
+    }
+}
+
+#[allow(deprecated)]
+impl From<old::metrics::v1::IntDataPoint> for new::metrics::v1::NumberDataPoint {
+    fn from(point: old::metrics::v1::IntDataPoint) -> Self {
+        Self {
+            attributes: convert(point.labels),
+            start_time_unix_nano: point.start_time_unix_nano,
+            time_unix_nano: point.time_unix_nano,
+            exemplars: convert(point.exemplars),
+            flags: 0,
+            value: Some(new::metrics::v1::number_data_point::Value::AsInt(
+                point.value,
+            )),
+        }
+    }
+}
+
+impl From<old::metrics::v1::NumberDataPoint> for new::metrics::v1::NumberDataPoint {
+    #[allow(deprecated)]
+    fn from(point: old::metrics::v1::NumberDataPoint) -> Self {
+        use new::metrics::v1::number_data_point::Value;
+        use old::metrics::v1::number_data_point::Value as Old;
+        Self {
+            attributes: attributes(point.attributes, point.labels),
+            start_time_unix_nano: point.start_time_unix_nano,
+            time_unix_nano: point.time_unix_nano,
+            exemplars: convert(point.exemplars),
+            flags: 0,
+            value: point.value.map(|value| match value {
+                Old::AsDouble(v) => Value::AsDouble(v),
+                Old::AsInt(v) => Value::AsInt(v),
+            }),
+        }
+    }
+}
+
+#[allow(deprecated)]
+impl From<old::metrics::v1::IntHistogramDataPoint> for new::metrics::v1::HistogramDataPoint {
+    fn from(point: old::metrics::v1::IntHistogramDataPoint) -> Self {
+        Self {
+            attributes: convert(point.labels),
+            start_time_unix_nano: point.start_time_unix_nano,
+            time_unix_nano: point.time_unix_nano,
+            count: point.count,
+            sum: Some(point.sum as f64),
+            bucket_counts: point.bucket_counts,
+            explicit_bounds: point.explicit_bounds,
+            exemplars: convert(point.exemplars),
+            flags: 0,
+            min: None,
+            max: None,
+        }
+    }
+}
+
+impl From<old::metrics::v1::HistogramDataPoint> for new::metrics::v1::HistogramDataPoint {
+    #[allow(deprecated)]
+    fn from(point: old::metrics::v1::HistogramDataPoint) -> Self {
+        Self {
+            attributes: attributes(point.attributes, point.labels),
+            start_time_unix_nano: point.start_time_unix_nano,
+            time_unix_nano: point.time_unix_nano,
+            count: point.count,
+            sum: Some(point.sum),
+            bucket_counts: point.bucket_counts,
+            explicit_bounds: point.explicit_bounds,
+            exemplars: convert(point.exemplars),
+            flags: 0,
+            min: None,
+            max: None,
+        }
+    }
+}
+
+impl From<old::metrics::v1::SummaryDataPoint> for new::metrics::v1::SummaryDataPoint {
+    #[allow(deprecated)]
+    fn from(point: old::metrics::v1::SummaryDataPoint) -> Self {
+        Self {
+            attributes: attributes(point.attributes, point.labels),
+            start_time_unix_nano: point.start_time_unix_nano,
+            time_unix_nano: point.time_unix_nano,
+            count: point.count,
+            sum: point.sum,
+            quantile_values: point
+                .quantile_values
+                .into_iter()
+                .map(|q| new::metrics::v1::summary_data_point::ValueAtQuantile {
+                    quantile: q.quantile,
+                    value: q.value,
+                })
+                .collect(),
+            flags: 0,
+        }
+    }
+}
+
+#[allow(deprecated)]
+impl From<old::metrics::v1::IntExemplar> for new::metrics::v1::Exemplar {
+    fn from(exemplar: old::metrics::v1::IntExemplar) -> Self {
+        Self {
+            filtered_attributes: convert(exemplar.filtered_labels),
+            time_unix_nano: exemplar.time_unix_nano,
+            span_id: exemplar.span_id,
+            trace_id: exemplar.trace_id,
+            value: Some(new::metrics::v1::exemplar::Value::AsInt(exemplar.value)),
+        }
+    }
+}
+
+impl From<old::metrics::v1::Exemplar> for new::metrics::v1::Exemplar {
+    #[allow(deprecated)]
+    fn from(exemplar: old::metrics::v1::Exemplar) -> Self {
+        use new::metrics::v1::exemplar::Value;
+        use old::metrics::v1::exemplar::Value as Old;
+        Self {
+            filtered_attributes: attributes(exemplar.filtered_attributes, exemplar.filtered_labels),
+            time_unix_nano: exemplar.time_unix_nano,
+            span_id: exemplar.span_id,
+            trace_id: exemplar.trace_id,
+            value: exemplar.value.map(|value| match value {
+                Old::AsDouble(v) => Value::AsDouble(v),
+                Old::AsInt(v) => Value::AsInt(v),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prost::Message;
+
+    fn library() -> old::common::v1::InstrumentationLibrary {
+        old::common::v1::InstrumentationLibrary {
+            name: "lib".to_string(),
+            version: "0.9".to_string(),
+        }
+    }
+
+    fn label(key: &str, value: &str) -> old::common::v1::StringKeyValue {
+        old::common::v1::StringKeyValue {
+            key: key.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    fn string_attribute(key: &str, value: &str) -> new::common::v1::KeyValue {
+        crate::value::key_value(key, value)
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn instrumentation_library_spans_become_scope_spans() -> Result<(), prost::DecodeError> {
+        let request = old::collector::trace::v1::ExportTraceServiceRequest {
+            resource_spans: vec![old::trace::v1::ResourceSpans {
+                instrumentation_library_spans: vec![old::trace::v1::InstrumentationLibrarySpans {
+                    instrumentation_library: Some(library()),
+                    spans: vec![old::trace::v1::Span {
+                        name: "span".to_string(),
+                        status: Some(old::trace::v1::Status {
+                            deprecated_code:
+                                old::trace::v1::status::DeprecatedStatusCode::UnknownError as i32,
+                            ..old::trace::v1::Status::default()
+                        }),
+                        ..old::trace::v1::Span::default()
+                    }],
+                    schema_url: "https://opentelemetry.io/schemas/1.4.0".to_string(),
+                }],
+                ..old::trace::v1::ResourceSpans::default()
+            }],
+        };
+        let body = request.encode_to_vec();
+        let request = new::collector::trace::v1::ExportTraceServiceRequest::from(
+            old::collector::trace::v1::ExportTraceServiceRequest::decode(&body[..])?,
+        );
+        let scope_spans = &request.resource_spans[0].scope_spans[0];
+        let scope = scope_spans.scope.clone().unwrap_or_default();
+        assert_eq!(
+            (scope.name.as_str(), scope.version.as_str()),
+            ("lib", "0.9")
+        );
+        assert_eq!(
+            scope_spans.schema_url,
+            "https://opentelemetry.io/schemas/1.4.0"
+        );
+        assert_eq!(scope_spans.spans[0].name, "span");
+        assert_eq!(
+            scope_spans.spans[0]
+                .status
+                .as_ref()
+                .map(|status| status.code),
+            Some(new::trace::v1::status::StatusCode::Error as i32)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn instrumentation_library_logs_become_scope_logs() {
+        let request = new::collector::logs::v1::ExportLogsServiceRequest::from(
+            old::collector::logs::v1::ExportLogsServiceRequest {
+                resource_logs: vec![old::logs::v1::ResourceLogs {
+                    instrumentation_library_logs: vec![old::logs::v1::InstrumentationLibraryLogs {
+                        instrumentation_library: Some(library()),
+                        logs: vec![old::logs::v1::LogRecord {
+                            name: "started".to_string(),
+                            ..old::logs::v1::LogRecord::default()
+                        }],
+                        ..old::logs::v1::InstrumentationLibraryLogs::default()
+                    }],
+                    ..old::logs::v1::ResourceLogs::default()
+                }],
+            },
+        );
+        let scope_logs = &request.resource_logs[0].scope_logs[0];
+        assert_eq!(
+            scope_logs.scope.as_ref().map(|scope| scope.name.as_str()),
+            Some("lib")
+        );
+        assert_eq!(scope_logs.log_records[0].event_name, "started");
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn int_metrics_become_number_metrics() -> Result<(), prost::DecodeError> {
+        use new::metrics::v1::metric::Data;
+        use new::metrics::v1::number_data_point::Value;
+        use old::metrics::v1::metric::Data as Old;
+
+        let int_point = old::metrics::v1::IntDataPoint {
+            labels: vec![label("host", "a")],
+            time_unix_nano: 7,
+            value: 42,
+            ..old::metrics::v1::IntDataPoint::default()
+        };
+        let metric = |name: &str, data| old::metrics::v1::Metric {
+            name: name.to_string(),
+            data: Some(data),
+            ..old::metrics::v1::Metric::default()
+        };
+        let body = old::collector::metrics::v1::ExportMetricsServiceRequest {
+            resource_metrics: vec![old::metrics::v1::ResourceMetrics {
+                instrumentation_library_metrics: vec![
+                    old::metrics::v1::InstrumentationLibraryMetrics {
+                        instrumentation_library: Some(library()),
+                        metrics: vec![
+                            metric(
+                                "gauge",
+                                Old::IntGauge(old::metrics::v1::IntGauge {
+                                    data_points: vec![int_point.clone()],
+                                }),
+                            ),
+                            metric(
+                                "sum",
+                                Old::IntSum(old::metrics::v1::IntSum {
+                                    data_points: vec![int_point],
+                                    aggregation_temporality: 2,
+                                    is_monotonic: true,
+                                }),
+                            ),
+                            metric(
+                                "histogram",
+                                Old::IntHistogram(old::metrics::v1::IntHistogram {
+                                    data_points: vec![old::metrics::v1::IntHistogramDataPoint {
+                                        count: 2,
+                                        sum: 9,
+                                        bucket_counts: vec![1, 1],
+                                        explicit_bounds: vec![5.0],
+                                        ..old::metrics::v1::IntHistogramDataPoint::default()
+                                    }],
+                                    aggregation_temporality: 1,
+                                }),
+                            ),
+                            metric(
+                                "double",
+                                Old::Gauge(old::metrics::v1::Gauge {
+                                    data_points: vec![old::metrics::v1::NumberDataPoint {
+                                        labels: vec![label("host", "b")],
+                                        value: Some(
+                                            old::metrics::v1::number_data_point::Value::AsDouble(
+                                                0.5,
+                                            ),
+                                        ),
+                                        ..old::metrics::v1::NumberDataPoint::default()
+                                    }],
+                                }),
+                            ),
+                        ],
+                        ..old::metrics::v1::InstrumentationLibraryMetrics::default()
+                    },
+                ],
+                ..old::metrics::v1::ResourceMetrics::default()
+            }],
+        }
+        .encode_to_vec();
+        // The current bindings drop the reserved integer metric fields
+        let current = new::collector::metrics::v1::ExportMetricsServiceRequest::decode(&body[..])?;
+        assert_eq!(
+            current.resource_metrics[0].scope_metrics[0].metrics[0].data,
+            None
+        );
+
+        let request = new::collector::metrics::v1::ExportMetricsServiceRequest::from(
+            old::collector::metrics::v1::ExportMetricsServiceRequest::decode(&body[..])?,
+        );
+        let scope_metrics = &request.resource_metrics[0].scope_metrics[0];
+        assert_eq!(
+            scope_metrics
+                .scope
+                .as_ref()
+                .map(|scope| scope.name.as_str()),
+            Some("lib")
+        );
+        let data: Vec<_> = scope_metrics
+            .metrics
+            .iter()
+            .map(|metric| metric.data.clone())
+            .collect();
+
+        let expected_point = new::metrics::v1::NumberDataPoint {
+            attributes: vec![string_attribute("host", "a")],
+            time_unix_nano: 7,
+            value: Some(Value::AsInt(42)),
+            ..new::metrics::v1::NumberDataPoint::default()
+        };
+        match &data[0] {
+            Some(Data::Gauge(gauge)) => assert_eq!(gauge.data_points, [expected_point.clone()]),
+            other => panic!("expected a gauge, got {:?}", other),
+        }
+        match &data[1] {
+            Some(Data::Sum(sum)) => {
+                assert_eq!(sum.data_points, [expected_point]);
+                assert_eq!(sum.aggregation_temporality, 2);
+                assert!(sum.is_monotonic);
+            }
+            other => panic!("expected a sum, got {:?}", other),
+        }
+        match &data[2] {
+            Some(Data::Histogram(histogram)) => {
+                let point = &histogram.data_points[0];
+                assert_eq!(point.sum, Some(9.0));
+                assert_eq!((point.count, &point.bucket_counts[..]), (2, &[1, 1][..]));
+                assert_eq!(histogram.aggregation_temporality, 1);
+            }
+            other => panic!("expected a histogram, got {:?}", other),
+        }
+        match &data[3] {
+            Some(Data::Gauge(gauge)) => {
+                assert_eq!(
+                    gauge.data_points[0].attributes,
+                    [string_attribute("host", "b")]
+                );
+                assert_eq!(gauge.data_points[0].value, Some(Value::AsDouble(0.5)));
+            }
+            other => panic!("expected a gauge, got {:?}", other),
+        }
+        Ok(())
+    }
+}
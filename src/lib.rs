@@ -47,8 +47,8 @@
 //! ```
 //!
 //! Example OpenTelemetry Log client. Note that clients simply use the generated
-//! client stub code from `tonic-build`. This library adds no extra utility or
-//! convenience.
+//! client stub code from `tonic-build`. Clients exporting individual log records,
+//! spans or metrics can batch them with the exporters of the `otel-exporter` feature.
 //!
 //! ```ignore
 //! async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -98,6 +98,21 @@
 //!
 //! ```
 //!
+//! Handlers that need to await other work, such as a database write or a
+//! downstream call, can be asynchronous closures wrapped with `handler_fn`,
+//! or implement the `OtelLogsHandler` trait directly:
+//!
+//! ```ignore
+//! async fn on_logs(
+//!     request: tonic::Request<ExportLogsServiceRequest>,
+//! ) -> Result<tonic::Response<ExportLogsServiceResponse>, tonic::Status> {
+//!     store(request.into_inner()).await?;
+//!     Ok(tonic::Response::new(ExportLogsServiceResponse::default()))
+//! }
+//!
+//! let svc = otelapis::logs::make_service(otelapis::logs::handler_fn(on_logs));
+//! ```
+//!
 //! Example async-channel based OpenTelemetry for ease of integration with
 //! async runtimes such as [tremor](https://www.tremor.rs):
 //!
@@ -112,13 +127,14 @@
 //!
 //!     loop {
 //!         match rx.try_recv() {
-//!             Ok(OpenTelemetryEvents::Metrics(metrics)) => {
+//!             Ok(OpenTelemetryEvents::Metrics(metrics, _remote, _meta)) => {
 //!                 // Do something with metrics request
 //!             }
-//!             Ok(OpenTelemetryEvents::Logs(log)) => {
-//!                 // Do something with log request
+//!             Ok(OpenTelemetryEvents::Logs(log, _remote, meta)) => {
+//!                 // Do something with log request, for example route by tenant
+//!                 let tenant = meta.metadata.get("x-tenant-id");
 //!             }
-//!             Ok(OpenTelemetryEvents::Trace(trace)) => {
+//!             Ok(OpenTelemetryEvents::Trace(trace, _remote, _meta)) => {
 //!                 // Do something with trace request
 //!             }
 //!             _ => error!("Unsupported"),
@@ -127,6 +143,51 @@
 //!
 //!    // ...
 //! }
+//! ```
+//!
+//! The collector can also be stopped gracefully, for example on `SIGTERM`:
+//!
+//! ```ignore
+//! let (tx, rx) = bounded(128);
+//! tremor_otelapis::all::make_with_shutdown(addr, tx, async {
+//!     tokio::signal::ctrl_c().await.ok();
+//! })
+//! .await?;
+//!
+//! // all accepted exports are now queued in `rx`
+//! while let Ok(event) = rx.try_recv() {
+//!     // ...
+//! }
+//! ```
+//!
+//! Signals and transport settings are selected with a `CollectorBuilder`,
+//! whose router can also be merged with other tonic services:
+//!
+//! ```ignore
+//! tremor_otelapis::all::CollectorBuilder::new(tx)
+//!     .metrics(false)
+//!     .max_message_size(4 * 1024 * 1024)
+//!     .tcp_nodelay(true)
+//!     .into_router()
+//!     .add_service(my_service)
+//!     .serve(addr)
+//!     .await?;
+//! ```
+//!
+//! The experimental metric configuration service can serve a static set of
+//! schedules:
+//!
+//! ```ignore
+//! use tremor_otelapis::metrics_config::{make_service, StaticMetricConfig};
+//!
+//! let config = StaticMetricConfig::with_suggested_wait_time(
+//!     vec![Schedule::with_period(10)
+//!         .include(Pattern::starts_with("http."))
+//!         .exclude(Pattern::equals("http.debug"))],
+//!     60,
+//! );
+//! Server::builder().add_service(make_service(config)).serve(addr).await?;
+//! ```
 //!
 //! [`otelapis`]: https://github.com/open-telemetry/opentelemetry-specification
 //! [`tonic-build`]: https://github.com/hyperium/tonic/tree/master/tonic-build
@@ -146,6 +207,57 @@ extern crate prost;
 mod otelapis;
 pub use otelapis::opentelemetry;
 
+/// Overflow policies and retry information for throttled exports
+pub mod throttling;
+
+/// Authentication of export requests
+pub mod auth;
+
+/// Retries of failed export calls with exponential backoff
+pub mod retry;
+
+/// Trace and span identifiers and W3C trace context headers
+pub mod trace_context;
+
+/// Structural validation of export requests
+pub mod validation;
+
+/// Partial success of export requests with rejected items
+pub mod partial_success;
+
+/// Attribute names of the OpenTelemetry semantic conventions
+pub mod semconv;
+
+/// Semantic convention accessors and merging of resources
+#[cfg(feature = "opentelemetry-proto-resource-v1")]
+pub mod resource;
+
+/// Conversions of Rust values into attribute values
+#[cfg(feature = "opentelemetry-proto-common-v1")]
+pub mod value;
+
+/// Attribute maps with unique keys and attribute limits
+#[cfg(feature = "opentelemetry-proto-common-v1")]
+pub mod attributes;
+
+/// Builders for spans, log records, metrics and export requests
+#[cfg(any(
+    feature = "otel-trace",
+    feature = "otel-logs",
+    feature = "otel-metrics"
+))]
+pub mod builders;
+
+/// Legacy opentelemetry-proto 0.9.0 messages and their conversion to the current protocol
+#[cfg(feature = "otel-legacy")]
+pub mod legacy;
+
+/// OTLP/JSON encoding and decoding of the generated messages
+#[cfg(feature = "otel-json")]
+// The serde helpers are only used by the messages of the enabled signals
+#[cfg_attr(not(feature = "otel-all"), allow(dead_code))]
+pub mod json;
+
 #[cfg(feature = "otel-trace")]
 /// This module defines a skeleton implementation of the open telemetry
 /// collector tracing service
@@ -153,6 +265,10 @@ pub use otelapis::opentelemetry;
 pub mod trace {
     use crate::opentelemetry::proto::collector::trace::v1 as base;
     use crate::opentelemetry::proto::collector::trace::v1::trace_service_server as skel;
+    use crate::throttling::{dispatch, OverflowPolicy};
+    use crate::validation::{enforce, ValidationPolicy};
+    use async_channel::{Receiver, Sender};
+    use std::future::Future;
 
     /// Alias tonic TraceRequest
     pub type OtelTraceRequest = tonic::Request<base::ExportTraceServiceRequest>;
@@ -160,6 +276,9 @@ pub mod trace {
     /// Alias tonic TraceResponse
     pub type OtelTraceResponse = tonic::Response<base::ExportTraceServiceResponse>;
 
+    /// Alias service skeleton
+    pub use skel::TraceService;
+
     /// Alias the generated server skeletons
     pub use skel::TraceServiceServer;
 
@@ -169,20 +288,76 @@ pub mod trace {
         + Sync
         + 'static;
 
+    /// Asynchronous trace event handler
+    #[tonic::async_trait]
+    pub trait OtelTraceHandler: Send + Sync + 'static {
+        /// Handles a trace export request
+        async fn on_trace(
+            &self,
+            request: OtelTraceRequest,
+        ) -> Result<OtelTraceResponse, tonic::Status>;
+    }
+
+    // Synchronous trace callback fns are handlers, see `OnTraceFn`
+    #[tonic::async_trait]
+    impl<F> OtelTraceHandler for F
+    where
+        F: Fn(OtelTraceRequest) -> Result<OtelTraceResponse, tonic::Status> + Send + Sync + 'static,
+    {
+        async fn on_trace(
+            &self,
+            request: OtelTraceRequest,
+        ) -> Result<OtelTraceResponse, tonic::Status> {
+            (self)(request)
+        }
+    }
+
+    /// Adapts an asynchronous trace callback fn into a trace handler
+    pub struct OnTraceAsyncFn<F> {
+        on_trace: F,
+    }
+
+    /// Wraps an asynchronous trace callback fn as a trace handler
+    pub fn handler_fn<F, Fut>(on_trace: F) -> OnTraceAsyncFn<F>
+    where
+        F: Fn(OtelTraceRequest) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<OtelTraceResponse, tonic::Status>> + Send + 'static,
+    {
+        OnTraceAsyncFn { on_trace }
+    }
+
+    #[tonic::async_trait]
+    impl<F, Fut> OtelTraceHandler for OnTraceAsyncFn<F>
+    where
+        F: Fn(OtelTraceRequest) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<OtelTraceResponse, tonic::Status>> + Send + 'static,
+    {
+        async fn on_trace(
+            &self,
+            request: OtelTraceRequest,
+        ) -> Result<OtelTraceResponse, tonic::Status> {
+            (self.on_trace)(request).await
+        }
+    }
+
     /// GRPC trace service skeleton
     pub struct OtelTraceService {
-        on_trace: Box<OnTraceFn>,
+        on_trace: Box<dyn OtelTraceHandler>,
     }
 
     impl OtelTraceService {
-        /// Creates a trace service with the specified trace event handler function
-        pub fn with_handler(handler: Box<OnTraceFn>) -> Self {
-            OtelTraceService { on_trace: handler }
+        /// Creates a trace service with the specified trace event handler
+        pub fn with_handler<H: OtelTraceHandler>(handler: H) -> Self {
+            OtelTraceService {
+                on_trace: Box::new(handler),
+            }
         }
     }
 
     /// Creates a tonic service handler for open telemetry trace events
-    pub fn make_service(handler: Box<OnTraceFn>) -> skel::TraceServiceServer<OtelTraceService> {
+    pub fn make_service<H: OtelTraceHandler>(
+        handler: H,
+    ) -> skel::TraceServiceServer<OtelTraceService> {
         skel::TraceServiceServer::new(OtelTraceService::with_handler(handler))
     }
 
@@ -192,9 +367,69 @@ pub mod trace {
             &self,
             request: tonic::Request<base::ExportTraceServiceRequest>,
         ) -> Result<tonic::Response<base::ExportTraceServiceResponse>, tonic::Status> {
-            (self.on_trace)(request)
+            self.on_trace.on_trace(request).await
+        }
+    }
+
+    /// Asynchronous channel sender
+    pub type OtelTraceSender = Sender<base::ExportTraceServiceRequest>;
+
+    /// Asynchronous channel receiver
+    pub type OtelTraceReceiver = Receiver<base::ExportTraceServiceRequest>;
+
+    /// Trace forwarding agent
+    pub struct OtelTraceServiceForwarder {
+        channel: Sender<base::ExportTraceServiceRequest>,
+        overflow: OverflowPolicy<base::ExportTraceServiceRequest>,
+        validation: ValidationPolicy,
+    }
+
+    impl OtelTraceServiceForwarder {
+        /// Creates a trace forwarding agent with an asynchronous channel sender
+        pub fn with_sender(channel: Sender<base::ExportTraceServiceRequest>) -> Self {
+            OtelTraceServiceForwarder {
+                channel,
+                overflow: OverflowPolicy::default(),
+                validation: ValidationPolicy::default(),
+            }
+        }
+
+        /// Selects how the forwarder reacts when the channel is full, blocking by default
+        pub fn with_overflow_policy(
+            mut self,
+            overflow: OverflowPolicy<base::ExportTraceServiceRequest>,
+        ) -> Self {
+            self.overflow = overflow;
+            self
+        }
+
+        /// Selects how the forwarder treats requests with invalid items, forwarding
+        /// them unvalidated by default
+        pub fn with_validation_policy(mut self, validation: ValidationPolicy) -> Self {
+            self.validation = validation;
+            self
+        }
+    }
+
+    #[tonic::async_trait]
+    impl skel::TraceService for OtelTraceServiceForwarder {
+        async fn export(
+            &self,
+            request: tonic::Request<base::ExportTraceServiceRequest>,
+        ) -> Result<tonic::Response<base::ExportTraceServiceResponse>, tonic::Status> {
+            let mut request = request.into_inner();
+            let response = enforce(self.validation, &mut request)?;
+            dispatch(&self.channel, &self.overflow, request, "Trace").await?;
+            Ok(tonic::Response::new(response))
         }
     }
+
+    /// Creates a tonic service forwarder for open telemetry trace events
+    pub fn make_forwarder(
+        sender: OtelTraceSender,
+    ) -> skel::TraceServiceServer<OtelTraceServiceForwarder> {
+        skel::TraceServiceServer::new(OtelTraceServiceForwarder::with_sender(sender))
+    }
 }
 
 /// This module defines a skeleton implementation of the open telemetry
@@ -204,7 +439,10 @@ pub mod trace {
 pub mod logs {
     use crate::opentelemetry::proto::collector::logs::v1 as base;
     use crate::opentelemetry::proto::collector::logs::v1::logs_service_server as skel;
+    use crate::throttling::{dispatch, OverflowPolicy};
+    use crate::validation::{enforce, ValidationPolicy};
     use async_channel::{Receiver, Sender};
+    use std::future::Future;
 
     /// Alias tonic request
     pub type OtelLogsRequest = tonic::Request<base::ExportLogsServiceRequest>;
@@ -222,15 +460,69 @@ pub mod logs {
     pub type OnLogsFn =
         dyn Fn(OtelLogsRequest) -> Result<OtelLogsResponse, tonic::Status> + Send + Sync + 'static;
 
+    /// Asynchronous logs event handler
+    #[tonic::async_trait]
+    pub trait OtelLogsHandler: Send + Sync + 'static {
+        /// Handles a logs export request
+        async fn on_logs(
+            &self,
+            request: OtelLogsRequest,
+        ) -> Result<OtelLogsResponse, tonic::Status>;
+    }
+
+    // Synchronous logs callback fns are handlers, see `OnLogsFn`
+    #[tonic::async_trait]
+    impl<F> OtelLogsHandler for F
+    where
+        F: Fn(OtelLogsRequest) -> Result<OtelLogsResponse, tonic::Status> + Send + Sync + 'static,
+    {
+        async fn on_logs(
+            &self,
+            request: OtelLogsRequest,
+        ) -> Result<OtelLogsResponse, tonic::Status> {
+            (self)(request)
+        }
+    }
+
+    /// Adapts an asynchronous logs callback fn into a logs handler
+    pub struct OnLogsAsyncFn<F> {
+        on_logs: F,
+    }
+
+    /// Wraps an asynchronous logs callback fn as a logs handler
+    pub fn handler_fn<F, Fut>(on_logs: F) -> OnLogsAsyncFn<F>
+    where
+        F: Fn(OtelLogsRequest) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<OtelLogsResponse, tonic::Status>> + Send + 'static,
+    {
+        OnLogsAsyncFn { on_logs }
+    }
+
+    #[tonic::async_trait]
+    impl<F, Fut> OtelLogsHandler for OnLogsAsyncFn<F>
+    where
+        F: Fn(OtelLogsRequest) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<OtelLogsResponse, tonic::Status>> + Send + 'static,
+    {
+        async fn on_logs(
+            &self,
+            request: OtelLogsRequest,
+        ) -> Result<OtelLogsResponse, tonic::Status> {
+            (self.on_logs)(request).await
+        }
+    }
+
     /// GRPC logs service skeleton
     pub struct OtelLogsService {
-        on_logs: Box<OnLogsFn>,
+        on_logs: Box<dyn OtelLogsHandler>,
     }
 
     impl OtelLogsService {
-        /// Creates a logs service with the specified logs event handler function
-        pub fn with_handler(handler: Box<OnLogsFn>) -> Self {
-            OtelLogsService { on_logs: handler }
+        /// Creates a logs service with the specified logs event handler
+        pub fn with_handler<H: OtelLogsHandler>(handler: H) -> Self {
+            OtelLogsService {
+                on_logs: Box::new(handler),
+            }
         }
     }
 
@@ -240,12 +532,14 @@ pub mod logs {
             &self,
             request: tonic::Request<base::ExportLogsServiceRequest>,
         ) -> Result<tonic::Response<base::ExportLogsServiceResponse>, tonic::Status> {
-            (self.on_logs)(request)
+            self.on_logs.on_logs(request).await
         }
     }
 
     /// Creates a tonic service handler for open telemetry log events
-    pub fn make_service(handler: Box<OnLogsFn>) -> skel::LogsServiceServer<OtelLogsService> {
+    pub fn make_service<H: OtelLogsHandler>(
+        handler: H,
+    ) -> skel::LogsServiceServer<OtelLogsService> {
         skel::LogsServiceServer::new(OtelLogsService::with_handler(handler))
     }
 
@@ -258,13 +552,35 @@ pub mod logs {
     /// Logs forwarding agent
     pub struct OtelLogsServiceForwarder {
         channel: Sender<base::ExportLogsServiceRequest>,
+        overflow: OverflowPolicy<base::ExportLogsServiceRequest>,
+        validation: ValidationPolicy,
     }
 
     // Creates a metrics service with the specified asynchronous sender channel
     impl OtelLogsServiceForwarder {
         /// Creates a log forwarding agent with an asynchronous channel sender
         pub fn with_sender(channel: Sender<base::ExportLogsServiceRequest>) -> Self {
-            OtelLogsServiceForwarder { channel }
+            OtelLogsServiceForwarder {
+                channel,
+                overflow: OverflowPolicy::default(),
+                validation: ValidationPolicy::default(),
+            }
+        }
+
+        /// Selects how the forwarder reacts when the channel is full, blocking by default
+        pub fn with_overflow_policy(
+            mut self,
+            overflow: OverflowPolicy<base::ExportLogsServiceRequest>,
+        ) -> Self {
+            self.overflow = overflow;
+            self
+        }
+
+        /// Selects how the forwarder treats requests with invalid items, forwarding
+        /// them unvalidated by default
+        pub fn with_validation_policy(mut self, validation: ValidationPolicy) -> Self {
+            self.validation = validation;
+            self
         }
     }
 
@@ -274,13 +590,10 @@ pub mod logs {
             &self,
             request: tonic::Request<base::ExportLogsServiceRequest>,
         ) -> Result<tonic::Response<base::ExportLogsServiceResponse>, tonic::Status> {
-            match self.channel.send(request.into_inner()).await {
-                Ok(_) => Ok(tonic::Response::new(base::ExportLogsServiceResponse {})),
-                Err(e) => Err(tonic::Status::internal(&format!(
-                    "Logs gRPC forwarder channel sender failed to dispatch {}",
-                    e
-                ))),
-            }
+            let mut request = request.into_inner();
+            let response = enforce(self.validation, &mut request)?;
+            dispatch(&self.channel, &self.overflow, request, "Logs").await?;
+            Ok(tonic::Response::new(response))
         }
     }
 
@@ -299,7 +612,10 @@ pub mod logs {
 pub mod metrics {
     use crate::opentelemetry::proto::collector::metrics::v1 as base;
     use crate::opentelemetry::proto::collector::metrics::v1::metrics_service_server as skel;
+    use crate::throttling::{dispatch, OverflowPolicy};
+    use crate::validation::{enforce, ValidationPolicy};
     use async_channel::{Receiver, Sender};
+    use std::future::Future;
 
     pub use skel::MetricsService;
     pub use skel::MetricsServiceServer;
@@ -316,16 +632,71 @@ pub mod metrics {
         + Sync
         + 'static;
 
+    /// Asynchronous metrics event handler
+    #[tonic::async_trait]
+    pub trait OtelMetricsHandler: Send + Sync + 'static {
+        /// Handles a metrics export request
+        async fn on_metrics(
+            &self,
+            request: OtelMetricsRequest,
+        ) -> Result<OtelMetricsResponse, tonic::Status>;
+    }
+
+    // Synchronous metrics callback fns are handlers, see `OnMetricsFn`
+    #[tonic::async_trait]
+    impl<F> OtelMetricsHandler for F
+    where
+        F: Fn(OtelMetricsRequest) -> Result<OtelMetricsResponse, tonic::Status>
+            + Send
+            + Sync
+            + 'static,
+    {
+        async fn on_metrics(
+            &self,
+            request: OtelMetricsRequest,
+        ) -> Result<OtelMetricsResponse, tonic::Status> {
+            (self)(request)
+        }
+    }
+
+    /// Adapts an asynchronous metrics callback fn into a metrics handler
+    pub struct OnMetricsAsyncFn<F> {
+        on_metrics: F,
+    }
+
+    /// Wraps an asynchronous metrics callback fn as a metrics handler
+    pub fn handler_fn<F, Fut>(on_metrics: F) -> OnMetricsAsyncFn<F>
+    where
+        F: Fn(OtelMetricsRequest) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<OtelMetricsResponse, tonic::Status>> + Send + 'static,
+    {
+        OnMetricsAsyncFn { on_metrics }
+    }
+
+    #[tonic::async_trait]
+    impl<F, Fut> OtelMetricsHandler for OnMetricsAsyncFn<F>
+    where
+        F: Fn(OtelMetricsRequest) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<OtelMetricsResponse, tonic::Status>> + Send + 'static,
+    {
+        async fn on_metrics(
+            &self,
+            request: OtelMetricsRequest,
+        ) -> Result<OtelMetricsResponse, tonic::Status> {
+            (self.on_metrics)(request).await
+        }
+    }
+
     /// GRPC metrics service skeleton
     pub struct OtelMetricsService {
-        on_metrics: Box<OnMetricsFn>,
+        on_metrics: Box<dyn OtelMetricsHandler>,
     }
 
     impl OtelMetricsService {
-        /// Creates a metrics service with the specified metrics event handler function
-        pub fn with_handler(handler: Box<OnMetricsFn>) -> Self {
+        /// Creates a metrics service with the specified metrics event handler
+        pub fn with_handler<H: OtelMetricsHandler>(handler: H) -> Self {
             OtelMetricsService {
-                on_metrics: handler,
+                on_metrics: Box::new(handler),
             }
         }
     }
@@ -336,13 +707,13 @@ pub mod metrics {
             &self,
             request: tonic::Request<base::ExportMetricsServiceRequest>,
         ) -> Result<tonic::Response<base::ExportMetricsServiceResponse>, tonic::Status> {
-            (self.on_metrics)(request)
+            self.on_metrics.on_metrics(request).await
         }
     }
 
     /// Creates a tonic service handler for open telemetry metrics events
-    pub fn make_service(
-        handler: Box<OnMetricsFn>,
+    pub fn make_service<H: OtelMetricsHandler>(
+        handler: H,
     ) -> skel::MetricsServiceServer<OtelMetricsService> {
         skel::MetricsServiceServer::new(OtelMetricsService::with_handler(handler))
     }
@@ -356,12 +727,34 @@ pub mod metrics {
     /// Creates a metrics service with the specified asynchronous sender channel
     pub struct OtelMetricsServiceForwarder {
         channel: Sender<base::ExportMetricsServiceRequest>,
+        overflow: OverflowPolicy<base::ExportMetricsServiceRequest>,
+        validation: ValidationPolicy,
     }
 
     impl OtelMetricsServiceForwarder {
         /// Creates a metrics service forwarding agent with an asynchronous channel sender
         pub fn with_sender(channel: Sender<base::ExportMetricsServiceRequest>) -> Self {
-            OtelMetricsServiceForwarder { channel }
+            OtelMetricsServiceForwarder {
+                channel,
+                overflow: OverflowPolicy::default(),
+                validation: ValidationPolicy::default(),
+            }
+        }
+
+        /// Selects how the forwarder reacts when the channel is full, blocking by default
+        pub fn with_overflow_policy(
+            mut self,
+            overflow: OverflowPolicy<base::ExportMetricsServiceRequest>,
+        ) -> Self {
+            self.overflow = overflow;
+            self
+        }
+
+        /// Selects how the forwarder treats requests with invalid items, forwarding
+        /// them unvalidated by default
+        pub fn with_validation_policy(mut self, validation: ValidationPolicy) -> Self {
+            self.validation = validation;
+            self
         }
     }
 
@@ -371,13 +764,10 @@ pub mod metrics {
             &self,
             request: tonic::Request<base::ExportMetricsServiceRequest>,
         ) -> Result<tonic::Response<base::ExportMetricsServiceResponse>, tonic::Status> {
-            match self.channel.send(request.into_inner()).await {
-                Ok(_) => Ok(tonic::Response::new(base::ExportMetricsServiceResponse {})),
-                Err(e) => Err(tonic::Status::internal(&format!(
-                    "Metrics gRPC forwarder channel sender failed to dispatch {}",
-                    e
-                ))),
-            }
+            let mut request = request.into_inner();
+            let response = enforce(self.validation, &mut request)?;
+            dispatch(&self.channel, &self.overflow, request, "Metrics").await?;
+            Ok(tonic::Response::new(response))
         }
     }
 
@@ -389,47 +779,538 @@ pub mod metrics {
     }
 }
 
+/// This module defines a skeleton implementation of the experimental
+/// open telemetry metric configuration service
+///
+#[cfg(feature = "opentelemetry-proto-metrics-experimental")]
+pub mod metrics_config {
+    use crate::opentelemetry::proto::metrics::experimental as base;
+    use crate::opentelemetry::proto::metrics::experimental::metric_config_server as skel;
+    use base::metric_config_response::schedule::{pattern, Pattern};
+    use base::metric_config_response::Schedule;
+    use prost::Message;
+    use std::future::Future;
+
+    pub use skel::MetricConfig;
+    pub use skel::MetricConfigServer;
+
+    /// Alias tonic request
+    pub type OtelMetricConfigRequest = tonic::Request<base::MetricConfigRequest>;
+
+    /// Alias tonic response
+    pub type OtelMetricConfigResponse = tonic::Response<base::MetricConfigResponse>;
+
+    /// Alias metric config callback fn
+    pub type OnMetricConfigFn = dyn Fn(OtelMetricConfigRequest) -> Result<OtelMetricConfigResponse, tonic::Status>
+        + Send
+        + Sync
+        + 'static;
+
+    /// Asynchronous metric config request handler
+    #[tonic::async_trait]
+    pub trait OtelMetricConfigHandler: Send + Sync + 'static {
+        /// Handles a metric config request
+        async fn on_metric_config(
+            &self,
+            request: OtelMetricConfigRequest,
+        ) -> Result<OtelMetricConfigResponse, tonic::Status>;
+    }
+
+    // Synchronous metric config callback fns are handlers, see `OnMetricConfigFn`
+    #[tonic::async_trait]
+    impl<F> OtelMetricConfigHandler for F
+    where
+        F: Fn(OtelMetricConfigRequest) -> Result<OtelMetricConfigResponse, tonic::Status>
+            + Send
+            + Sync
+            + 'static,
+    {
+        async fn on_metric_config(
+            &self,
+            request: OtelMetricConfigRequest,
+        ) -> Result<OtelMetricConfigResponse, tonic::Status> {
+            (self)(request)
+        }
+    }
+
+    /// Adapts an asynchronous metric config callback fn into a metric config handler
+    pub struct OnMetricConfigAsyncFn<F> {
+        on_metric_config: F,
+    }
+
+    /// Wraps an asynchronous metric config callback fn as a metric config handler
+    pub fn handler_fn<F, Fut>(on_metric_config: F) -> OnMetricConfigAsyncFn<F>
+    where
+        F: Fn(OtelMetricConfigRequest) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<OtelMetricConfigResponse, tonic::Status>> + Send + 'static,
+    {
+        OnMetricConfigAsyncFn { on_metric_config }
+    }
+
+    #[tonic::async_trait]
+    impl<F, Fut> OtelMetricConfigHandler for OnMetricConfigAsyncFn<F>
+    where
+        F: Fn(OtelMetricConfigRequest) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<OtelMetricConfigResponse, tonic::Status>> + Send + 'static,
+    {
+        async fn on_metric_config(
+            &self,
+            request: OtelMetricConfigRequest,
+        ) -> Result<OtelMetricConfigResponse, tonic::Status> {
+            (self.on_metric_config)(request).await
+        }
+    }
+
+    /// GRPC metric config service skeleton
+    pub struct OtelMetricConfigService {
+        on_metric_config: Box<dyn OtelMetricConfigHandler>,
+    }
+
+    impl OtelMetricConfigService {
+        /// Creates a metric config service with the specified metric config request handler
+        pub fn with_handler<H: OtelMetricConfigHandler>(handler: H) -> Self {
+            OtelMetricConfigService {
+                on_metric_config: Box::new(handler),
+            }
+        }
+    }
+
+    #[tonic::async_trait]
+    impl skel::MetricConfig for OtelMetricConfigService {
+        async fn get_metric_config(
+            &self,
+            request: tonic::Request<base::MetricConfigRequest>,
+        ) -> Result<tonic::Response<base::MetricConfigResponse>, tonic::Status> {
+            self.on_metric_config.on_metric_config(request).await
+        }
+    }
+
+    /// Creates a tonic service handler for open telemetry metric config requests
+    pub fn make_service<H: OtelMetricConfigHandler>(
+        handler: H,
+    ) -> skel::MetricConfigServer<OtelMetricConfigService> {
+        skel::MetricConfigServer::new(OtelMetricConfigService::with_handler(handler))
+    }
+
+    impl Pattern {
+        /// A pattern matching the metric name exactly
+        pub fn equals<S: Into<String>>(name: S) -> Self {
+            Pattern {
+                r#match: Some(pattern::Match::Equals(name.into())),
+            }
+        }
+
+        /// A pattern matching metric names with the specified prefix
+        pub fn starts_with<S: Into<String>>(prefix: S) -> Self {
+            Pattern {
+                r#match: Some(pattern::Match::StartsWith(prefix.into())),
+            }
+        }
+
+        /// Checks if the pattern matches a metric name, an empty pattern matches nothing
+        pub fn matches(&self, metric_name: &str) -> bool {
+            match &self.r#match {
+                Some(pattern::Match::Equals(name)) => metric_name == name,
+                Some(pattern::Match::StartsWith(prefix)) => metric_name.starts_with(prefix),
+                None => false,
+            }
+        }
+    }
+
+    impl Schedule {
+        /// Creates a schedule collecting matching metrics every `period_sec` seconds
+        pub fn with_period(period_sec: i32) -> Self {
+            Schedule {
+                period_sec,
+                ..Schedule::default()
+            }
+        }
+
+        /// Targets metrics matching the pattern
+        pub fn include(mut self, pattern: Pattern) -> Self {
+            self.inclusion_patterns.push(pattern);
+            self
+        }
+
+        /// Excludes metrics matching the pattern, even if they are included
+        pub fn exclude(mut self, pattern: Pattern) -> Self {
+            self.exclusion_patterns.push(pattern);
+            self
+        }
+
+        /// Checks if the schedule applies to a metric name
+        pub fn matches(&self, metric_name: &str) -> bool {
+            self.inclusion_patterns
+                .iter()
+                .any(|pattern| pattern.matches(metric_name))
+                && !self
+                    .exclusion_patterns
+                    .iter()
+                    .any(|pattern| pattern.matches(metric_name))
+        }
+    }
+
+    /// A metric config handler serving the same schedules to every resource
+    ///
+    /// The fingerprint is a deterministic hash of the configuration. Callers
+    /// presenting it as their `last_known_fingerprint` receive a response
+    /// carrying only the fingerprint and the suggested wait time.
+    #[derive(Clone, Debug)]
+    pub struct StaticMetricConfig {
+        config: base::MetricConfigResponse,
+        unchanged: base::MetricConfigResponse,
+    }
+
+    impl StaticMetricConfig {
+        /// Creates a configuration serving the specified schedules
+        pub fn new(schedules: Vec<Schedule>) -> Self {
+            Self::with_suggested_wait_time(schedules, 0)
+        }
+
+        /// Creates a configuration serving the specified schedules, suggesting callers
+        /// wait `suggested_wait_time_sec` seconds before asking again
+        pub fn with_suggested_wait_time(
+            schedules: Vec<Schedule>,
+            suggested_wait_time_sec: i32,
+        ) -> Self {
+            let mut config = base::MetricConfigResponse {
+                fingerprint: Vec::new(),
+                schedules,
+                suggested_wait_time_sec,
+            };
+            config.fingerprint = fingerprint(&config.encode_to_vec());
+            let unchanged = base::MetricConfigResponse {
+                fingerprint: config.fingerprint.clone(),
+                schedules: Vec::new(),
+                suggested_wait_time_sec,
+            };
+            StaticMetricConfig { config, unchanged }
+        }
+
+        /// The fingerprint of this configuration
+        pub fn fingerprint(&self) -> &[u8] {
+            &self.config.fingerprint
+        }
+
+        /// The schedules of this configuration
+        pub fn schedules(&self) -> &[Schedule] {
+            &self.config.schedules
+        }
+
+        /// The collection period of a metric, the smallest period of all
+        /// matching schedules, or `None` if no schedule applies
+        pub fn period_sec(&self, metric_name: &str) -> Option<i32> {
+            self.config
+                .schedules
+                .iter()
+                .filter(|schedule| schedule.matches(metric_name))
+                .map(|schedule| schedule.period_sec)
+                .min()
+        }
+
+        /// The response to a request presenting the specified last known fingerprint
+        pub fn response(&self, last_known_fingerprint: &[u8]) -> base::MetricConfigResponse {
+            if last_known_fingerprint == self.fingerprint() {
+                self.unchanged.clone()
+            } else {
+                self.config.clone()
+            }
+        }
+    }
+
+    #[tonic::async_trait]
+    impl OtelMetricConfigHandler for StaticMetricConfig {
+        async fn on_metric_config(
+            &self,
+            request: OtelMetricConfigRequest,
+        ) -> Result<OtelMetricConfigResponse, tonic::Status> {
+            Ok(tonic::Response::new(
+                self.response(&request.get_ref().last_known_fingerprint),
+            ))
+        }
+    }
+
+    // 64 bit FNV-1a, stable across builds and platforms unlike the std hashers
+    fn fingerprint(bytes: &[u8]) -> Vec<u8> {
+        let hash = bytes.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(0x0000_0100_0000_01b3)
+        });
+        hash.to_be_bytes().to_vec()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn schedules(period_sec: i32) -> Vec<Schedule> {
+            vec![Schedule::with_period(period_sec)
+                .include(Pattern::starts_with("http."))
+                .exclude(Pattern::equals("http.server.active_requests"))]
+        }
+
+        #[test]
+        fn fingerprints_are_fnv_1a() {
+            assert_eq!(fingerprint(b""), 0xcbf2_9ce4_8422_2325_u64.to_be_bytes());
+            assert_eq!(fingerprint(b"a"), 0xaf63_dc4c_8601_ec8c_u64.to_be_bytes());
+        }
+
+        #[test]
+        fn fingerprints_are_stable_across_calls() {
+            let config = StaticMetricConfig::with_suggested_wait_time(schedules(10), 30);
+            let again = StaticMetricConfig::with_suggested_wait_time(schedules(10), 30);
+            assert_eq!(config.fingerprint().len(), 8);
+            assert_eq!(config.fingerprint(), again.fingerprint());
+            assert_eq!(config.fingerprint(), config.clone().fingerprint());
+            assert_eq!(
+                config.response(&[]).fingerprint,
+                config.response(&[]).fingerprint
+            );
+        }
+
+        #[test]
+        fn fingerprints_change_with_the_config() {
+            let config = StaticMetricConfig::with_suggested_wait_time(schedules(10), 30);
+            let period = StaticMetricConfig::with_suggested_wait_time(schedules(20), 30);
+            let wait_time = StaticMetricConfig::with_suggested_wait_time(schedules(10), 60);
+            let empty = StaticMetricConfig::with_suggested_wait_time(Vec::new(), 30);
+            assert_ne!(config.fingerprint(), period.fingerprint());
+            assert_ne!(config.fingerprint(), wait_time.fingerprint());
+            assert_ne!(config.fingerprint(), empty.fingerprint());
+        }
+
+        #[test]
+        fn answers_known_fingerprints_without_schedules() {
+            let config = StaticMetricConfig::with_suggested_wait_time(schedules(10), 30);
+            let changed = StaticMetricConfig::new(schedules(20));
+
+            let unchanged = config.response(config.fingerprint());
+            assert_eq!(unchanged.fingerprint, config.fingerprint());
+            assert!(unchanged.schedules.is_empty());
+            assert_eq!(unchanged.suggested_wait_time_sec, 30);
+
+            let full = config.response(changed.fingerprint());
+            assert_eq!(full.schedules, config.schedules());
+            assert_eq!(config.period_sec("http.client.duration"), Some(10));
+            assert_eq!(config.period_sec("http.server.active_requests"), None);
+        }
+    }
+}
+
+/// Span sampling decisions for `TraceConfig` samplers
+#[cfg(feature = "otel-trace-config")]
+pub mod sampling;
+
+/// OTLP/HTTP receiver for log, metrics and trace events
+#[cfg(feature = "otel-http")]
+pub mod otlp_http;
+
+/// Batching OTLP gRPC exporters for spans, log records and metrics
+#[cfg(feature = "otel-exporter")]
+pub mod exporter;
+
+/// Persistent on-disk queue of export requests
+#[cfg(feature = "otel-disk-queue")]
+pub mod disk_queue;
+
+/// Unix domain socket transport for the gRPC collector
+#[cfg(all(unix, feature = "otel-uds"))]
+pub mod uds;
+
 /// A unified set of services that provide log, metrics and trace events
 #[cfg(feature = "otel-all")]
 pub mod all {
+    use crate::auth::{AuthInterceptor, Authenticator, Principal};
     use crate::opentelemetry::proto::collector::logs::v1 as logs_base;
     use crate::opentelemetry::proto::collector::metrics::v1 as metrics_base;
     use crate::opentelemetry::proto::collector::trace::v1 as trace_base;
+    use crate::throttling::{dispatch, OverflowPolicy};
+    use crate::validation::{enforce, ValidationPolicy};
     use async_channel::{Receiver, Sender};
+    use hyper::body::HttpBody;
+    use std::future::Future;
     use std::net::SocketAddr;
-    use tonic::transport::Server;
+    use std::pin::Pin;
+    use std::sync::Arc;
+    use std::task::{Context, Poll};
+    use std::time::{Duration, SystemTime};
+    use tonic::body::BoxBody;
+    use tonic::service::interceptor::InterceptedService;
+    use tonic::transport::server::Router;
+    use tonic::transport::{Body, Server};
+    use tower::{Layer, Service};
 
     /// Enumeration of protocol buffer messages that are sendable/receivable
+    ///
+    /// Each variant holds the export request, the address of the client and the
+    /// `RequestMetadata` of the request. The variants of 0.2 releases held only
+    /// the request and the address, so matches on them need a third field.
     pub enum OpenTelemetryEvents {
         /// A logs export request
-        Logs(logs_base::ExportLogsServiceRequest, Option<SocketAddr>),
+        Logs(
+            logs_base::ExportLogsServiceRequest,
+            Option<SocketAddr>,
+            RequestMetadata,
+        ),
         /// A metrics export request
         Metrics(
             metrics_base::ExportMetricsServiceRequest,
             Option<SocketAddr>,
+            RequestMetadata,
         ),
         /// A trace export request
-        Trace(trace_base::ExportTraceServiceRequest, Option<SocketAddr>),
+        Trace(
+            trace_base::ExportTraceServiceRequest,
+            Option<SocketAddr>,
+            RequestMetadata,
+        ),
+    }
+
+    impl OpenTelemetryEvents {
+        /// The address of the client that sent the export request
+        pub fn remote_addr(&self) -> Option<SocketAddr> {
+            match self {
+                Self::Logs(_, remote, _)
+                | Self::Metrics(_, remote, _)
+                | Self::Trace(_, remote, _) => *remote,
+            }
+        }
+
+        /// The metadata of the export request
+        pub fn metadata(&self) -> &RequestMetadata {
+            match self {
+                Self::Logs(_, _, meta) | Self::Metrics(_, _, meta) | Self::Trace(_, _, meta) => {
+                    meta
+                }
+            }
+        }
     }
+
     impl From<tonic::Request<logs_base::ExportLogsServiceRequest>> for OpenTelemetryEvents {
         fn from(req: tonic::Request<logs_base::ExportLogsServiceRequest>) -> Self {
             let remote = req.remote_addr();
-            Self::Logs(req.into_inner(), remote)
+            let meta = RequestMetadata::from_request(&req, &MetadataSelection::All);
+            Self::Logs(req.into_inner(), remote, meta)
         }
     }
     impl From<tonic::Request<metrics_base::ExportMetricsServiceRequest>> for OpenTelemetryEvents {
         fn from(req: tonic::Request<metrics_base::ExportMetricsServiceRequest>) -> Self {
             let remote = req.remote_addr();
-            Self::Metrics(req.into_inner(), remote)
+            let meta = RequestMetadata::from_request(&req, &MetadataSelection::All);
+            Self::Metrics(req.into_inner(), remote, meta)
         }
     }
     impl From<tonic::Request<trace_base::ExportTraceServiceRequest>> for OpenTelemetryEvents {
         fn from(req: tonic::Request<trace_base::ExportTraceServiceRequest>) -> Self {
             let remote = req.remote_addr();
-            Self::Trace(req.into_inner(), remote)
+            let meta = RequestMetadata::from_request(&req, &MetadataSelection::All);
+            Self::Trace(req.into_inner(), remote, meta)
+        }
+    }
+
+    /// Selects the request metadata carried by events
+    #[derive(Clone, Debug)]
+    pub enum MetadataSelection {
+        /// Keeps all request metadata
+        All,
+        /// Keeps only the metadata with the listed keys, case insensitive
+        Keys(Vec<String>),
+    }
+
+    impl Default for MetadataSelection {
+        fn default() -> Self {
+            MetadataSelection::All
+        }
+    }
+
+    /// Request context carried alongside the payload of an event
+    #[derive(Clone, Debug)]
+    pub struct RequestMetadata {
+        /// The selected gRPC metadata, or HTTP headers, of the request
+        pub metadata: tonic::metadata::MetadataMap,
+        /// When the export request was received
+        pub received_at: SystemTime,
+        /// The DER encoded TLS certificate chain presented by the client, if any
+        pub peer_certificates: Vec<Vec<u8>>,
+        /// The compression of the request payload, from `grpc-encoding`
+        /// or `content-encoding`
+        pub compression: Option<String>,
+        /// The principal named by the authenticator of the service, if any
+        pub principal: Option<Principal>,
+        /// The credentials of the client process on Unix domain socket connections
+        pub peer_credentials: Option<PeerCredentials>,
+    }
+
+    /// The credentials of a process connected over a Unix domain socket
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct PeerCredentials {
+        /// The user id of the process
+        pub uid: u32,
+        /// The group id of the process
+        pub gid: u32,
+        /// The process id, where the platform provides it
+        pub pid: Option<i32>,
+    }
+
+    impl RequestMetadata {
+        /// Captures the selected metadata of a request as it is received
+        pub fn from_request<T>(request: &tonic::Request<T>, selection: &MetadataSelection) -> Self {
+            let headers = request.metadata().clone().into_headers();
+            let headers = match selection {
+                MetadataSelection::All => headers,
+                MetadataSelection::Keys(keys) => {
+                    let mut selected = http::HeaderMap::new();
+                    for key in keys {
+                        if let Ok(name) = http::header::HeaderName::from_bytes(key.as_bytes()) {
+                            for value in headers.get_all(&name) {
+                                selected.append(name.clone(), value.clone());
+                            }
+                        }
+                    }
+                    selected
+                }
+            };
+            let compression = ["grpc-encoding", "content-encoding"]
+                .iter()
+                .find_map(|key| request.metadata().get(*key))
+                .and_then(|value| value.to_str().ok())
+                .map(ToString::to_string);
+            RequestMetadata {
+                metadata: tonic::metadata::MetadataMap::from_headers(headers),
+                received_at: SystemTime::now(),
+                peer_certificates: peer_certificates(request),
+                compression,
+                principal: request.extensions().get::<Principal>().cloned(),
+                peer_credentials: peer_credentials(request),
+            }
         }
     }
 
+    #[cfg(all(unix, feature = "otel-uds"))]
+    fn peer_credentials<T>(request: &tonic::Request<T>) -> Option<PeerCredentials> {
+        request
+            .extensions()
+            .get::<crate::uds::UdsConnectInfo>()
+            .and_then(crate::uds::UdsConnectInfo::peer_credentials)
+    }
+
+    #[cfg(not(all(unix, feature = "otel-uds")))]
+    fn peer_credentials<T>(_request: &tonic::Request<T>) -> Option<PeerCredentials> {
+        None
+    }
+
+    #[cfg(feature = "tls")]
+    fn peer_certificates<T>(request: &tonic::Request<T>) -> Vec<Vec<u8>> {
+        request
+            .peer_certs()
+            .map(|certs| certs.iter().map(|cert| cert.get_ref().to_vec()).collect())
+            .unwrap_or_default()
+    }
+
+    #[cfg(not(feature = "tls"))]
+    fn peer_certificates<T>(_request: &tonic::Request<T>) -> Vec<Vec<u8>> {
+        Vec::new()
+    }
+
     /// Alias receiver
     pub type OpenTelemetrySender = Sender<OpenTelemetryEvents>;
 
@@ -439,12 +1320,42 @@ pub mod all {
     /// Creates a logs service with the specified asynchronous sender channel
     pub struct LogsServiceForwarder {
         channel: Sender<OpenTelemetryEvents>,
+        overflow: OverflowPolicy<OpenTelemetryEvents>,
+        validation: ValidationPolicy,
+        metadata: MetadataSelection,
     }
 
     impl LogsServiceForwarder {
         /// Creates a logs service forwarding agent
         pub fn with_sender(channel: Sender<OpenTelemetryEvents>) -> Self {
-            LogsServiceForwarder { channel }
+            LogsServiceForwarder {
+                channel,
+                overflow: OverflowPolicy::default(),
+                validation: ValidationPolicy::default(),
+                metadata: MetadataSelection::default(),
+            }
+        }
+
+        /// Selects the request metadata carried by events, all metadata by default
+        pub fn with_metadata_selection(mut self, metadata: MetadataSelection) -> Self {
+            self.metadata = metadata;
+            self
+        }
+
+        /// Selects how the forwarder reacts when the channel is full, blocking by default
+        pub fn with_overflow_policy(
+            mut self,
+            overflow: OverflowPolicy<OpenTelemetryEvents>,
+        ) -> Self {
+            self.overflow = overflow;
+            self
+        }
+
+        /// Selects how the forwarder treats requests with invalid items, forwarding
+        /// them unvalidated by default
+        pub fn with_validation_policy(mut self, validation: ValidationPolicy) -> Self {
+            self.validation = validation;
+            self
         }
     }
 
@@ -454,27 +1365,55 @@ pub mod all {
             &self,
             request: tonic::Request<logs_base::ExportLogsServiceRequest>,
         ) -> Result<tonic::Response<logs_base::ExportLogsServiceResponse>, tonic::Status> {
-            match self.channel.send(OpenTelemetryEvents::from(request)).await {
-                Ok(_) => Ok(tonic::Response::new(
-                    logs_base::ExportLogsServiceResponse {},
-                )),
-                Err(e) => Err(tonic::Status::internal(&format!(
-                    "Logs gRPC forwarder channel sender failed to dispatch {}",
-                    e
-                ))),
-            }
+            let remote = request.remote_addr();
+            let meta = RequestMetadata::from_request(&request, &self.metadata);
+            let mut request = request.into_inner();
+            let response = enforce(self.validation, &mut request)?;
+            let event = OpenTelemetryEvents::Logs(request, remote, meta);
+            dispatch(&self.channel, &self.overflow, event, "Logs").await?;
+            Ok(tonic::Response::new(response))
         }
     }
 
     /// Creates a metrics service with the specified asynchronous sender channel
     pub struct MetricsServiceForwarder {
         channel: Sender<OpenTelemetryEvents>,
+        overflow: OverflowPolicy<OpenTelemetryEvents>,
+        validation: ValidationPolicy,
+        metadata: MetadataSelection,
     }
 
     impl MetricsServiceForwarder {
         /// Creates a metrics service forwarding agent
         pub fn with_sender(channel: Sender<OpenTelemetryEvents>) -> Self {
-            MetricsServiceForwarder { channel }
+            MetricsServiceForwarder {
+                channel,
+                overflow: OverflowPolicy::default(),
+                validation: ValidationPolicy::default(),
+                metadata: MetadataSelection::default(),
+            }
+        }
+
+        /// Selects the request metadata carried by events, all metadata by default
+        pub fn with_metadata_selection(mut self, metadata: MetadataSelection) -> Self {
+            self.metadata = metadata;
+            self
+        }
+
+        /// Selects how the forwarder reacts when the channel is full, blocking by default
+        pub fn with_overflow_policy(
+            mut self,
+            overflow: OverflowPolicy<OpenTelemetryEvents>,
+        ) -> Self {
+            self.overflow = overflow;
+            self
+        }
+
+        /// Selects how the forwarder treats requests with invalid items, forwarding
+        /// them unvalidated by default
+        pub fn with_validation_policy(mut self, validation: ValidationPolicy) -> Self {
+            self.validation = validation;
+            self
         }
     }
 
@@ -485,46 +1424,72 @@ pub mod all {
             request: tonic::Request<metrics_base::ExportMetricsServiceRequest>,
         ) -> Result<tonic::Response<metrics_base::ExportMetricsServiceResponse>, tonic::Status>
         {
-            match self.channel.send(OpenTelemetryEvents::from(request)).await {
-                Ok(_) => Ok(tonic::Response::new(
-                    metrics_base::ExportMetricsServiceResponse {},
-                )),
-                Err(e) => Err(tonic::Status::internal(&format!(
-                    "Metrics gRPC forwarder channel sender failed to dispatch {}",
-                    e
-                ))),
-            }
+            let remote = request.remote_addr();
+            let meta = RequestMetadata::from_request(&request, &self.metadata);
+            let mut request = request.into_inner();
+            let response = enforce(self.validation, &mut request)?;
+            let event = OpenTelemetryEvents::Metrics(request, remote, meta);
+            dispatch(&self.channel, &self.overflow, event, "Metrics").await?;
+            Ok(tonic::Response::new(response))
         }
     }
 
     /// Creates a trace service with the specified asynchronous sender channel
     pub struct TraceServiceForwarder {
         channel: Sender<OpenTelemetryEvents>,
+        overflow: OverflowPolicy<OpenTelemetryEvents>,
+        validation: ValidationPolicy,
+        metadata: MetadataSelection,
     }
 
     impl TraceServiceForwarder {
         /// Creates a trace service forwarding agent
         pub fn with_sender(channel: Sender<OpenTelemetryEvents>) -> Self {
-            TraceServiceForwarder { channel }
+            TraceServiceForwarder {
+                channel,
+                overflow: OverflowPolicy::default(),
+                validation: ValidationPolicy::default(),
+                metadata: MetadataSelection::default(),
+            }
+        }
+
+        /// Selects the request metadata carried by events, all metadata by default
+        pub fn with_metadata_selection(mut self, metadata: MetadataSelection) -> Self {
+            self.metadata = metadata;
+            self
+        }
+
+        /// Selects how the forwarder reacts when the channel is full, blocking by default
+        pub fn with_overflow_policy(
+            mut self,
+            overflow: OverflowPolicy<OpenTelemetryEvents>,
+        ) -> Self {
+            self.overflow = overflow;
+            self
+        }
+
+        /// Selects how the forwarder treats requests with invalid items, forwarding
+        /// them unvalidated by default
+        pub fn with_validation_policy(mut self, validation: ValidationPolicy) -> Self {
+            self.validation = validation;
+            self
         }
     }
 
     #[tonic::async_trait]
-    impl trace_base::trace_service_server::TraceService for TraceServiceForwarder {
+    impl super::trace::TraceService for TraceServiceForwarder {
         async fn export(
             &self,
             request: tonic::Request<trace_base::ExportTraceServiceRequest>,
         ) -> Result<tonic::Response<trace_base::ExportTraceServiceResponse>, tonic::Status>
         {
-            match self.channel.send(OpenTelemetryEvents::from(request)).await {
-                Ok(_) => Ok(tonic::Response::new(
-                    trace_base::ExportTraceServiceResponse {},
-                )),
-                Err(e) => Err(tonic::Status::internal(&format!(
-                    "Trace gRPC forwarder channel sender failed to dispatch {}",
-                    e
-                ))),
-            }
+            let remote = request.remote_addr();
+            let meta = RequestMetadata::from_request(&request, &self.metadata);
+            let mut request = request.into_inner();
+            let response = enforce(self.validation, &mut request)?;
+            let event = OpenTelemetryEvents::Trace(request, remote, meta);
+            dispatch(&self.channel, &self.overflow, event, "Trace").await?;
+            Ok(tonic::Response::new(response))
         }
     }
 
@@ -533,17 +1498,606 @@ pub mod all {
         addr: SocketAddr,
         sender: Sender<OpenTelemetryEvents>,
     ) -> Result<(), tonic::transport::Error> {
-        Server::builder()
-            .add_service(super::trace::TraceServiceServer::new(
-                TraceServiceForwarder::with_sender(sender.clone()),
-            ))
-            .add_service(super::logs::LogsServiceServer::new(
-                LogsServiceForwarder::with_sender(sender.clone()),
-            ))
-            .add_service(super::metrics::MetricsServiceServer::new(
-                MetricsServiceForwarder::with_sender(sender),
-            ))
-            .serve(addr)
+        make_with_shutdown(addr, sender, std::future::pending()).await
+    }
+
+    /// Spins up a `gRPC OpenTelemetry Collector` instance that shuts down
+    /// gracefully once the `signal` future completes
+    ///
+    /// On shutdown the collector stops accepting new connections and exports,
+    /// waits for in-flight exports to hand their `OpenTelemetryEvents` to the
+    /// channel, and then returns. The channel itself is left open so that the
+    /// receiving side can drain any queued events.
+    pub async fn make_with_shutdown<F: Future<Output = ()>>(
+        addr: SocketAddr,
+        sender: Sender<OpenTelemetryEvents>,
+        signal: F,
+    ) -> Result<(), tonic::transport::Error> {
+        CollectorBuilder::new(sender)
+            .serve_with_shutdown(addr, signal)
+            .await
+    }
+
+    /// Spins up a `gRPC OpenTelemetry Collector` instance on a Unix domain socket
+    #[cfg(all(unix, feature = "otel-uds"))]
+    pub async fn make_unix<P: AsRef<std::path::Path>>(
+        path: P,
+        sender: Sender<OpenTelemetryEvents>,
+    ) -> Result<(), crate::uds::Error> {
+        make_unix_with_shutdown(path, sender, std::future::pending()).await
+    }
+
+    /// Spins up a `gRPC OpenTelemetry Collector` instance on a Unix domain socket
+    /// that shuts down gracefully once the `signal` future completes
+    #[cfg(all(unix, feature = "otel-uds"))]
+    pub async fn make_unix_with_shutdown<P: AsRef<std::path::Path>, F: Future<Output = ()>>(
+        path: P,
+        sender: Sender<OpenTelemetryEvents>,
+        signal: F,
+    ) -> Result<(), crate::uds::Error> {
+        CollectorBuilder::new(sender)
+            .serve_unix_with_shutdown(path, signal)
             .await
     }
+
+    /// Boxed error as used by tonic services
+    type BoxError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+    /// Boxed response future
+    type BoxFuture<T, E> = Pin<Box<dyn Future<Output = Result<T, E>> + Send + 'static>>;
+
+    /// Size of the gRPC message frame header (compression flag and length)
+    const GRPC_HEADER_SIZE: usize = 5;
+
+    /// Tower layer limiting the size of incoming gRPC messages
+    #[derive(Clone, Debug, Default)]
+    pub struct MessageSizeLimitLayer {
+        limit: Option<usize>,
+    }
+
+    impl MessageSizeLimitLayer {
+        /// Creates a layer rejecting messages larger than `limit` bytes,
+        /// `None` disables the limit
+        pub fn new(limit: Option<usize>) -> Self {
+            MessageSizeLimitLayer { limit }
+        }
+    }
+
+    impl<S> Layer<S> for MessageSizeLimitLayer {
+        type Service = MessageSizeLimit<S>;
+
+        fn layer(&self, inner: S) -> Self::Service {
+            MessageSizeLimit {
+                inner,
+                limit: self.limit,
+            }
+        }
+    }
+
+    /// Service rejecting oversized gRPC messages with `RESOURCE_EXHAUSTED`
+    ///
+    /// The OpenTelemetry export calls are unary, so the request body is
+    /// buffered up to the limit before it is handed to the inner service. A
+    /// gzip compressed message is decompressed up to the limit as well and
+    /// handed on uncompressed, as tonic does not limit decompression. The
+    /// `CollectorBuilder` wraps each service in the limit before its
+    /// authenticator, so requests are authenticated before their body is read.
+    #[derive(Clone, Debug)]
+    pub struct MessageSizeLimit<S> {
+        inner: S,
+        limit: Option<usize>,
+    }
+
+    impl<S> Service<http::Request<Body>> for MessageSizeLimit<S>
+    where
+        S: Service<http::Request<Body>, Response = http::Response<BoxBody>>
+            + Clone
+            + Send
+            + 'static,
+        S::Future: Send + 'static,
+    {
+        type Response = http::Response<BoxBody>;
+        type Error = S::Error;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+
+        fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            self.inner.poll_ready(cx)
+        }
+
+        fn call(&mut self, request: http::Request<Body>) -> Self::Future {
+            let limit = match self.limit {
+                Some(limit) => limit,
+                None => return Box::pin(self.inner.call(request)),
+            };
+            // The ready service is moved into the future, its clone stays behind
+            let clone = self.inner.clone();
+            let mut inner = std::mem::replace(&mut self.inner, clone);
+            Box::pin(async move {
+                let (parts, mut body) = request.into_parts();
+                let mut buffered = Vec::new();
+                while let Some(chunk) = body.data().await {
+                    let chunk = match chunk {
+                        Ok(chunk) => chunk,
+                        Err(e) => {
+                            return Ok(tonic::Status::internal(format!(
+                                "Failed to read gRPC request body {}",
+                                e
+                            ))
+                            .to_http())
+                        }
+                    };
+                    if buffered.len() + chunk.len() > limit.saturating_add(GRPC_HEADER_SIZE) {
+                        return Ok(tonic::Status::resource_exhausted(format!(
+                            "gRPC message exceeds the maximum size of {} bytes",
+                            limit
+                        ))
+                        .to_http());
+                    }
+                    buffered.extend_from_slice(&chunk);
+                }
+                let buffered = match inflate(&parts.headers, buffered, limit) {
+                    Ok(buffered) => buffered,
+                    Err(status) => return Ok(status.to_http()),
+                };
+                inner
+                    .call(http::Request::from_parts(parts, Body::from(buffered)))
+                    .await
+            })
+        }
+    }
+
+    // Decompresses a gzip compressed message up to the limit, returning it as
+    // an uncompressed message
+    fn inflate(
+        headers: &http::HeaderMap,
+        body: Vec<u8>,
+        limit: usize,
+    ) -> Result<Vec<u8>, tonic::Status> {
+        use std::convert::TryFrom;
+        use std::io::Read;
+        let gzip = headers
+            .get("grpc-encoding")
+            .map_or(false, |encoding| encoding == "gzip");
+        if !gzip || body.first() != Some(&1) {
+            return Ok(body);
+        }
+        let mut length = [0; 4];
+        length.copy_from_slice(body.get(1..GRPC_HEADER_SIZE).unwrap_or(&[0; 4]));
+        if body.len() != GRPC_HEADER_SIZE + u32::from_be_bytes(length) as usize {
+            return Err(tonic::Status::invalid_argument(
+                "Expected a single compressed gRPC message",
+            ));
+        }
+        let mut inflated = Vec::new();
+        flate2::read::GzDecoder::new(&body[GRPC_HEADER_SIZE..])
+            .take((limit as u64).saturating_add(1))
+            .read_to_end(&mut inflated)
+            .map_err(|e| {
+                tonic::Status::invalid_argument(format!("Failed to decompress gRPC message {}", e))
+            })?;
+        let length = match u32::try_from(inflated.len()) {
+            Ok(length) if inflated.len() <= limit => length,
+            _ => {
+                return Err(tonic::Status::resource_exhausted(format!(
+                    "Decompressed gRPC message exceeds the maximum size of {} bytes",
+                    limit
+                )))
+            }
+        };
+        let mut message = Vec::with_capacity(GRPC_HEADER_SIZE + inflated.len());
+        message.push(0);
+        message.extend_from_slice(&length.to_be_bytes());
+        message.append(&mut inflated);
+        Ok(message)
+    }
+
+    impl<S: tonic::transport::NamedService> tonic::transport::NamedService for MessageSizeLimit<S> {
+        const NAME: &'static str = S::NAME;
+    }
+
+    /// Builder for a `gRPC OpenTelemetry Collector` with selectable signals
+    /// and transport settings
+    ///
+    /// All signals are enabled by default. Disabled signals are answered
+    /// with `UNIMPLEMENTED`.
+    pub struct CollectorBuilder {
+        sender: Sender<OpenTelemetryEvents>,
+        server: Server,
+        trace: bool,
+        logs: bool,
+        metrics: bool,
+        max_message_size: Option<usize>,
+        overflow: OverflowPolicy<OpenTelemetryEvents>,
+        validation: ValidationPolicy,
+        metadata: MetadataSelection,
+        authenticator: Option<Arc<dyn Authenticator>>,
+    }
+
+    impl CollectorBuilder {
+        /// Creates a collector builder forwarding events to an asynchronous channel sender
+        pub fn new(sender: Sender<OpenTelemetryEvents>) -> Self {
+            CollectorBuilder {
+                sender,
+                server: Server::builder(),
+                trace: true,
+                logs: true,
+                metrics: true,
+                max_message_size: None,
+                overflow: OverflowPolicy::default(),
+                validation: ValidationPolicy::default(),
+                metadata: MetadataSelection::default(),
+                authenticator: None,
+            }
+        }
+
+        /// Enables or disables the trace service
+        pub fn trace(mut self, enabled: bool) -> Self {
+            self.trace = enabled;
+            self
+        }
+
+        /// Enables or disables the logs service
+        pub fn logs(mut self, enabled: bool) -> Self {
+            self.logs = enabled;
+            self
+        }
+
+        /// Enables or disables the metrics service
+        pub fn metrics(mut self, enabled: bool) -> Self {
+            self.metrics = enabled;
+            self
+        }
+
+        /// Rejects export requests larger than `limit` bytes with `RESOURCE_EXHAUSTED`,
+        /// once the authenticator accepted them
+        pub fn max_message_size(mut self, limit: usize) -> Self {
+            self.max_message_size = Some(limit);
+            self
+        }
+
+        /// Selects how the forwarders react when the channel is full, blocking by default
+        pub fn overflow_policy(mut self, overflow: OverflowPolicy<OpenTelemetryEvents>) -> Self {
+            self.overflow = overflow;
+            self
+        }
+
+        /// Selects how the forwarders treat requests with invalid items, forwarding
+        /// them unvalidated by default
+        pub fn validation_policy(mut self, validation: ValidationPolicy) -> Self {
+            self.validation = validation;
+            self
+        }
+
+        /// Selects the request metadata carried by events, all metadata by default
+        pub fn metadata_selection(mut self, metadata: MetadataSelection) -> Self {
+            self.metadata = metadata;
+            self
+        }
+
+        /// Rejects requests the authenticator does not accept, attaching the
+        /// principal of accepted requests to their events
+        pub fn authenticator<A: Authenticator>(mut self, authenticator: A) -> Self {
+            self.authenticator = Some(Arc::new(authenticator));
+            self
+        }
+
+        /// Sets the maximum number of concurrent requests per connection
+        pub fn concurrency_limit_per_connection(mut self, limit: usize) -> Self {
+            self.server = self.server.concurrency_limit_per_connection(limit);
+            self
+        }
+
+        /// Sets the maximum number of concurrent HTTP/2 streams per connection
+        pub fn max_concurrent_streams(mut self, max: u32) -> Self {
+            self.server = self.server.max_concurrent_streams(max);
+            self
+        }
+
+        /// Sets a timeout on each export request
+        pub fn timeout(mut self, timeout: Duration) -> Self {
+            self.server.timeout(timeout);
+            self
+        }
+
+        /// Sets the TCP keepalive interval of accepted connections
+        pub fn tcp_keepalive(mut self, keepalive: Option<Duration>) -> Self {
+            self.server = self.server.tcp_keepalive(keepalive);
+            self
+        }
+
+        /// Enables or disables `TCP_NODELAY` on accepted connections
+        pub fn tcp_nodelay(mut self, enabled: bool) -> Self {
+            self.server = self.server.tcp_nodelay(enabled);
+            self
+        }
+
+        /// Sets the interval of HTTP/2 keepalive pings
+        pub fn http2_keepalive_interval(mut self, interval: Option<Duration>) -> Self {
+            self.server = self.server.http2_keepalive_interval(interval);
+            self
+        }
+
+        /// Sets the timeout for acknowledgements of HTTP/2 keepalive pings
+        pub fn http2_keepalive_timeout(mut self, timeout: Option<Duration>) -> Self {
+            self.server = self.server.http2_keepalive_timeout(timeout);
+            self
+        }
+
+        /// Serves TLS with the specified server identity
+        #[cfg(feature = "tls")]
+        pub fn tls_identity(
+            mut self,
+            identity: tonic::transport::Identity,
+        ) -> Result<Self, tonic::transport::Error> {
+            self.server = self
+                .server
+                .tls_config(tonic::transport::ServerTlsConfig::new().identity(identity))?;
+            Ok(self)
+        }
+
+        /// Creates a router with the enabled services that further tonic
+        /// services can be added to
+        #[allow(clippy::type_complexity)]
+        pub fn into_router(
+            mut self,
+        ) -> Router<
+            impl Service<
+                    http::Request<Body>,
+                    Response = http::Response<BoxBody>,
+                    Error = impl Into<BoxError> + Send,
+                    Future = impl Send + 'static,
+                > + Clone
+                + Send
+                + 'static,
+            impl Service<
+                    http::Request<Body>,
+                    Response = http::Response<BoxBody>,
+                    Error = impl Into<BoxError> + Send,
+                    Future = impl Send + 'static,
+                > + Clone
+                + Send
+                + 'static,
+            tower::layer::util::Identity,
+        > {
+            let limit = MessageSizeLimitLayer::new(self.max_message_size);
+            let trace = if self.trace {
+                let forwarder = TraceServiceForwarder::with_sender(self.sender.clone())
+                    .with_overflow_policy(self.overflow.clone())
+                    .with_validation_policy(self.validation)
+                    .with_metadata_selection(self.metadata.clone());
+                Some(InterceptedService::new(
+                    limit.layer(super::trace::TraceServiceServer::new(forwarder).accept_gzip()),
+                    AuthInterceptor::optional(self.authenticator.clone()),
+                ))
+            } else {
+                None
+            };
+            let logs = if self.logs {
+                let forwarder = LogsServiceForwarder::with_sender(self.sender.clone())
+                    .with_overflow_policy(self.overflow.clone())
+                    .with_validation_policy(self.validation)
+                    .with_metadata_selection(self.metadata.clone());
+                Some(InterceptedService::new(
+                    limit.layer(super::logs::LogsServiceServer::new(forwarder).accept_gzip()),
+                    AuthInterceptor::optional(self.authenticator.clone()),
+                ))
+            } else {
+                None
+            };
+            let metrics = if self.metrics {
+                let forwarder = MetricsServiceForwarder::with_sender(self.sender)
+                    .with_overflow_policy(self.overflow.clone())
+                    .with_validation_policy(self.validation)
+                    .with_metadata_selection(self.metadata.clone());
+                Some(InterceptedService::new(
+                    limit.layer(super::metrics::MetricsServiceServer::new(forwarder).accept_gzip()),
+                    AuthInterceptor::optional(self.authenticator.clone()),
+                ))
+            } else {
+                None
+            };
+            self.server
+                .add_optional_service(trace)
+                .add_optional_service(logs)
+                .add_optional_service(metrics)
+        }
+
+        /// Spins up the configured `gRPC OpenTelemetry Collector` instance
+        pub async fn serve(self, addr: SocketAddr) -> Result<(), tonic::transport::Error> {
+            self.into_router().serve(addr).await
+        }
+
+        /// Spins up the configured `gRPC OpenTelemetry Collector` instance that
+        /// shuts down gracefully once the `signal` future completes
+        pub async fn serve_with_shutdown<F: Future<Output = ()>>(
+            self,
+            addr: SocketAddr,
+            signal: F,
+        ) -> Result<(), tonic::transport::Error> {
+            self.into_router().serve_with_shutdown(addr, signal).await
+        }
+
+        /// Spins up the configured `gRPC OpenTelemetry Collector` instance on a
+        /// Unix domain socket, replacing a stale socket left behind at the path
+        #[cfg(all(unix, feature = "otel-uds"))]
+        pub async fn serve_unix<P: AsRef<std::path::Path>>(
+            self,
+            path: P,
+        ) -> Result<(), crate::uds::Error> {
+            self.serve_unix_with_shutdown(path, std::future::pending())
+                .await
+        }
+
+        /// Spins up the configured `gRPC OpenTelemetry Collector` instance on a
+        /// Unix domain socket that shuts down gracefully once the `signal` future
+        /// completes, removing the socket file
+        #[cfg(all(unix, feature = "otel-uds"))]
+        pub async fn serve_unix_with_shutdown<P: AsRef<std::path::Path>, F: Future<Output = ()>>(
+            self,
+            path: P,
+            signal: F,
+        ) -> Result<(), crate::uds::Error> {
+            let path = path.as_ref();
+            let incoming = crate::uds::UdsIncoming::bind(path)
+                .map_err(|e| crate::uds::Error::Bind(path.to_path_buf(), e))?;
+            let served = self
+                .into_router()
+                .serve_with_incoming_shutdown(incoming, signal)
+                .await;
+            // The socket is ours, a failed removal leaves a stale socket for the next bind
+            let _removed = std::fs::remove_file(path);
+            Ok(served?)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::auth::BearerTokenAuthenticator;
+        use std::convert::{Infallible, TryFrom};
+        use tower::ServiceExt;
+
+        fn frame(message: &[u8]) -> Vec<u8> {
+            let mut frame = vec![0];
+            frame.extend_from_slice(
+                &u32::try_from(message.len())
+                    .unwrap_or_default()
+                    .to_be_bytes(),
+            );
+            frame.extend_from_slice(message);
+            frame
+        }
+
+        // Answers with the size of the request body in the `x-body-size` header
+        fn echo() -> impl Service<
+            http::Request<Body>,
+            Response = http::Response<BoxBody>,
+            Error = Infallible,
+            Future = impl Send + 'static,
+        > + Clone
+               + Send
+               + 'static {
+            tower::service_fn(|request: http::Request<Body>| async move {
+                let size = hyper::body::to_bytes(request.into_body())
+                    .await
+                    .map_or(0, |body| body.len());
+                let mut response = http::Response::new(tonic::body::empty_body());
+                response
+                    .headers_mut()
+                    .insert("x-body-size", http::HeaderValue::from(size));
+                Ok::<_, Infallible>(response)
+            })
+        }
+
+        async fn call<S>(
+            service: S,
+            headers: &[(&'static str, &'static str)],
+            body: Vec<u8>,
+        ) -> Result<http::Response<BoxBody>, S::Error>
+        where
+            S: Service<http::Request<Body>, Response = http::Response<BoxBody>>,
+        {
+            let mut request = http::Request::new(Body::from(body));
+            for (name, value) in headers {
+                request
+                    .headers_mut()
+                    .insert(*name, http::HeaderValue::from_static(value));
+            }
+            service.oneshot(request).await
+        }
+
+        fn header<'a>(response: &'a http::Response<BoxBody>, name: &str) -> Option<&'a str> {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+        }
+
+        #[tokio::test]
+        async fn message_size_limit_accepts_messages_up_to_the_limit() -> Result<(), Infallible> {
+            let limited = MessageSizeLimitLayer::new(Some(10)).layer(echo());
+            let response = call(limited, &[], frame(&[7; 10])).await?;
+            assert_eq!(header(&response, "grpc-status"), None);
+            assert_eq!(header(&response, "x-body-size"), Some("15"));
+
+            let unlimited = MessageSizeLimitLayer::new(None).layer(echo());
+            let response = call(unlimited, &[], frame(&[7; 1000])).await?;
+            assert_eq!(header(&response, "x-body-size"), Some("1005"));
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn message_size_limit_rejects_larger_messages() -> Result<(), Infallible> {
+            let limited = MessageSizeLimitLayer::new(Some(10)).layer(echo());
+            let response = call(limited, &[], frame(&[7; 11])).await?;
+            assert_eq!(header(&response, "grpc-status"), Some("8"));
+            assert_eq!(header(&response, "x-body-size"), None);
+            Ok(())
+        }
+
+        fn gzip_frame(message: &[u8]) -> Vec<u8> {
+            use std::io::Write;
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            let compressed = encoder
+                .write_all(message)
+                .and_then(|()| encoder.finish())
+                .unwrap_or_default();
+            let mut frame = self::frame(&compressed);
+            frame[0] = 1;
+            frame
+        }
+
+        #[tokio::test]
+        async fn message_size_limit_applies_to_decompressed_messages() -> Result<(), Infallible> {
+            let gzip = [("grpc-encoding", "gzip")];
+            let bomb = gzip_frame(&vec![0; 1024 * 1024]);
+            assert!(bomb.len() < 4096);
+            let limited = MessageSizeLimitLayer::new(Some(4096)).layer(echo());
+            let response = call(limited, &gzip, bomb).await?;
+            assert_eq!(header(&response, "grpc-status"), Some("8"));
+
+            let limited = MessageSizeLimitLayer::new(Some(4096)).layer(echo());
+            let response = call(limited, &gzip, gzip_frame(&[7; 4096])).await?;
+            assert_eq!(header(&response, "grpc-status"), None);
+            assert_eq!(header(&response, "x-body-size"), Some("4101"));
+
+            let mut truncated = gzip_frame(&[7; 16]);
+            truncated.push(0);
+            let limited = MessageSizeLimitLayer::new(Some(4096)).layer(echo());
+            let response = call(limited, &gzip, truncated).await?;
+            assert_eq!(header(&response, "grpc-status"), Some("3"));
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn message_size_limit_runs_after_authentication() {
+            let service = || {
+                InterceptedService::new(
+                    MessageSizeLimitLayer::new(Some(4)).layer(echo()),
+                    AuthInterceptor::new(BearerTokenAuthenticator::new().token("t0k3n", "tenant")),
+                )
+            };
+            let status = call(service(), &[], frame(&[7; 100]))
+                .await
+                .err()
+                .and_then(|e| e.downcast::<tonic::Status>().ok());
+            assert_eq!(
+                status.map(|status| status.code()),
+                Some(tonic::Code::Unauthenticated)
+            );
+
+            let response = call(
+                service(),
+                &[("authorization", "Bearer t0k3n")],
+                frame(&[7; 100]),
+            )
+            .await
+            .ok();
+            assert_eq!(
+                response.as_ref().and_then(|r| header(r, "grpc-status")),
+                Some("8")
+            );
+        }
+    }
 }
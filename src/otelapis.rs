@@ -131,3 +131,108 @@ pub mod opentelemetry {
         }
     }
 }
+
+#[cfg_attr(
+    feature = "cargo-clippy",
+    allow(
+        clippy::all,
+        clippy::unwrap_used,
+        clippy::unnecessary_unwrap,
+        clippy::pedantic,
+    )
+)]
+#[allow(missing_docs)]
+
+/// Legacy opentelemetry-proto 0.9.0 APIs
+#[cfg(feature = "otel-legacy")]
+pub mod legacy {
+    /// Opentelemetry APIs
+    pub mod opentelemetry {
+        /// Protocols
+        pub mod proto {
+            /// Colector
+            pub mod collector {
+                /// Logs
+                pub mod logs {
+                    /// v1
+                    pub mod v1 {
+                        include!(concat!(
+                            env!("OUT_DIR"),
+                            "/legacy/opentelemetry.proto.collector.logs.v1.rs"
+                        ));
+                    }
+                }
+                /// Metrics
+                pub mod metrics {
+                    /// v1
+                    pub mod v1 {
+                        include!(concat!(
+                            env!("OUT_DIR"),
+                            "/legacy/opentelemetry.proto.collector.metrics.v1.rs"
+                        ));
+                    }
+                }
+                /// Traces
+                pub mod trace {
+                    /// v1
+                    pub mod v1 {
+                        include!(concat!(
+                            env!("OUT_DIR"),
+                            "/legacy/opentelemetry.proto.collector.trace.v1.rs"
+                        ));
+                    }
+                }
+            }
+            /// Common definitions
+            pub mod common {
+                /// v1
+                pub mod v1 {
+                    include!(concat!(
+                        env!("OUT_DIR"),
+                        "/legacy/opentelemetry.proto.common.v1.rs"
+                    ));
+                }
+            }
+            /// Logs
+            pub mod logs {
+                /// v1
+                pub mod v1 {
+                    include!(concat!(
+                        env!("OUT_DIR"),
+                        "/legacy/opentelemetry.proto.logs.v1.rs"
+                    ));
+                }
+            }
+            /// Metrics
+            pub mod metrics {
+                /// v1
+                pub mod v1 {
+                    include!(concat!(
+                        env!("OUT_DIR"),
+                        "/legacy/opentelemetry.proto.metrics.v1.rs"
+                    ));
+                }
+            }
+            /// resources
+            pub mod resource {
+                /// v1
+                pub mod v1 {
+                    include!(concat!(
+                        env!("OUT_DIR"),
+                        "/legacy/opentelemetry.proto.resource.v1.rs"
+                    ));
+                }
+            }
+            /// traces
+            pub mod trace {
+                /// v1
+                pub mod v1 {
+                    include!(concat!(
+                        env!("OUT_DIR"),
+                        "/legacy/opentelemetry.proto.trace.v1.rs"
+                    ));
+                }
+            }
+        }
+    }
+}
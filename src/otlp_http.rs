@@ -0,0 +1,1176 @@
+// Copyright 2020-2022, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Decodes `application/x-protobuf` and `application/json` export requests
+//! posted to `/v1/traces`, `/v1/logs` and `/v1/metrics` and dispatches them to
+//! the same tonic service implementations used by the gRPC collector, such as
+//! the services created by `make_service` or `make_forwarder` and the `all`
+//! forwarders. Responses use the encoding of the request.
+//!
+//! The `HttpExporter` is the client side, posting export requests to an
+//! OTLP/HTTP endpoint. HTTP errors are mapped onto gRPC statuses, with the
+//! `Retry-After` delay of `429 Too Many Requests` and `503 Service Unavailable`
+//! replies carried as retry information, so `retry::RetryPolicy` treats both
+//! transports alike.
+
+use crate::all::{
+    LogsServiceForwarder, MetricsServiceForwarder, OpenTelemetryEvents, TraceServiceForwarder,
+};
+use crate::auth::Authenticator;
+use crate::json::JSON_CONTENT_TYPE;
+use crate::logs::LogsService;
+use crate::metrics::MetricsService;
+use crate::opentelemetry::proto::collector::logs::v1::{
+    ExportLogsServiceRequest, ExportLogsServiceResponse,
+};
+use crate::opentelemetry::proto::collector::metrics::v1::{
+    ExportMetricsServiceRequest, ExportMetricsServiceResponse,
+};
+use crate::opentelemetry::proto::collector::trace::v1::{
+    ExportTraceServiceRequest, ExportTraceServiceResponse,
+};
+use crate::trace::TraceService;
+use async_channel::Sender;
+use hyper::body::HttpBody;
+use hyper::client::connect::Connect;
+use hyper::client::HttpConnector;
+use hyper::header::{HeaderName, HeaderValue, CONTENT_ENCODING, CONTENT_TYPE, RETRY_AFTER};
+use hyper::server::conn::{AddrIncoming, AddrStream};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Client, HeaderMap, Method, Request, Response, Server, StatusCode, Uri};
+use prost::Message;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::future::Future;
+use std::io::{Read, Write};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tonic::transport::server::{Connected, TcpConnectInfo};
+
+/// Path of the OTLP/HTTP trace export endpoint
+pub const TRACES_PATH: &str = "/v1/traces";
+
+/// Path of the OTLP/HTTP logs export endpoint
+pub const LOGS_PATH: &str = "/v1/logs";
+
+/// Path of the OTLP/HTTP metrics export endpoint
+pub const METRICS_PATH: &str = "/v1/metrics";
+
+/// Content type of protobuf encoded OTLP/HTTP payloads
+pub const PROTOBUF_CONTENT_TYPE: &str = "application/x-protobuf";
+
+/// The `google.rpc.Status` message returned in OTLP/HTTP error responses
+#[derive(Clone, PartialEq, Message, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RpcStatus {
+    /// The gRPC status code
+    #[prost(int32, tag = "1")]
+    pub code: i32,
+    /// A developer facing error message
+    #[prost(string, tag = "2")]
+    pub message: String,
+}
+
+/// Payload encoding of an OTLP/HTTP request or response
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    /// Binary protobuf, `application/x-protobuf`
+    Protobuf,
+    /// OTLP/JSON, `application/json`
+    Json,
+}
+
+impl Encoding {
+    /// Selects the encoding matching a `Content-Type` header value
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        if content_type.starts_with(PROTOBUF_CONTENT_TYPE) {
+            Some(Self::Protobuf)
+        } else if content_type.starts_with(JSON_CONTENT_TYPE) {
+            Some(Self::Json)
+        } else {
+            None
+        }
+    }
+
+    /// The `Content-Type` header value of this encoding
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Protobuf => PROTOBUF_CONTENT_TYPE,
+            Self::Json => JSON_CONTENT_TYPE,
+        }
+    }
+
+    fn encode<T: Message + Serialize>(self, message: &T) -> serde_json::Result<Vec<u8>> {
+        match self {
+            Self::Protobuf => Ok(message.encode_to_vec()),
+            Self::Json => crate::json::to_vec(message),
+        }
+    }
+}
+
+/// OTLP/HTTP receiver dispatching to tonic trace, logs and metrics services
+///
+/// Signals without a service are answered with `404 Not Found`.
+#[derive(Clone, Default)]
+pub struct HttpReceiver {
+    trace: Option<Arc<dyn TraceService>>,
+    logs: Option<Arc<dyn LogsService>>,
+    metrics: Option<Arc<dyn MetricsService>>,
+    max_message_size: Option<usize>,
+    authenticator: Option<Arc<dyn Authenticator>>,
+}
+
+impl HttpReceiver {
+    /// Creates a receiver without any services
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a receiver forwarding all signals to an asynchronous channel sender
+    pub fn with_sender(sender: Sender<OpenTelemetryEvents>) -> Self {
+        Self::new()
+            .with_trace(TraceServiceForwarder::with_sender(sender.clone()))
+            .with_logs(LogsServiceForwarder::with_sender(sender.clone()))
+            .with_metrics(MetricsServiceForwarder::with_sender(sender))
+    }
+
+    /// Dispatches trace export requests to the specified service
+    pub fn with_trace<S: TraceService>(mut self, service: S) -> Self {
+        self.trace = Some(Arc::new(service));
+        self
+    }
+
+    /// Dispatches logs export requests to the specified service
+    pub fn with_logs<S: LogsService>(mut self, service: S) -> Self {
+        self.logs = Some(Arc::new(service));
+        self
+    }
+
+    /// Dispatches metrics export requests to the specified service
+    pub fn with_metrics<S: MetricsService>(mut self, service: S) -> Self {
+        self.metrics = Some(Arc::new(service));
+        self
+    }
+
+    /// Rejects request bodies larger than `limit` bytes, after decompression,
+    /// with `413 Payload Too Large`
+    pub fn max_message_size(mut self, limit: usize) -> Self {
+        self.max_message_size = Some(limit);
+        self
+    }
+
+    /// Rejects requests the authenticator does not accept, storing the principal
+    /// of accepted requests in their extensions
+    pub fn with_authenticator<A: Authenticator>(mut self, authenticator: A) -> Self {
+        self.authenticator = Some(Arc::new(authenticator));
+        self
+    }
+
+    /// Serves OTLP/HTTP on the specified address
+    pub async fn serve(self, addr: SocketAddr) -> Result<(), hyper::Error> {
+        self.serve_with_shutdown(addr, std::future::pending()).await
+    }
+
+    /// Serves OTLP/HTTP on the specified address until the `signal` future completes
+    ///
+    /// On shutdown the receiver stops accepting new connections and returns
+    /// once in-flight exports have been dispatched.
+    pub async fn serve_with_shutdown<F: Future<Output = ()>>(
+        self,
+        addr: SocketAddr,
+        signal: F,
+    ) -> Result<(), hyper::Error> {
+        self.serve_incoming(AddrIncoming::bind(&addr)?, signal)
+            .await
+    }
+
+    async fn serve_incoming<F: Future<Output = ()>>(
+        self,
+        incoming: AddrIncoming,
+        signal: F,
+    ) -> Result<(), hyper::Error> {
+        let make_svc = make_service_fn(move |conn: &AddrStream| {
+            let receiver = self.clone();
+            let connect_info = conn.connect_info();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let receiver = receiver.clone();
+                    let connect_info = connect_info.clone();
+                    async move { Ok::<_, Infallible>(receiver.handle(request, connect_info).await) }
+                }))
+            }
+        });
+        Server::builder(incoming)
+            .serve(make_svc)
+            .with_graceful_shutdown(signal)
+            .await
+    }
+
+    /// Handles a single OTLP/HTTP request
+    pub async fn handle(
+        &self,
+        request: Request<Body>,
+        connect_info: TcpConnectInfo,
+    ) -> Response<Body> {
+        let content_type = request
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        let encoding = Encoding::from_content_type(&content_type);
+        let reply = encoding.unwrap_or(Encoding::Protobuf);
+        if request.method() != Method::POST {
+            return error_response(
+                StatusCode::METHOD_NOT_ALLOWED,
+                tonic::Code::Unimplemented,
+                "OTLP/HTTP only supports POST",
+                reply,
+            );
+        }
+        let encoding = match encoding {
+            Some(encoding) => encoding,
+            None if self.has_service(request.uri().path()) => {
+                return error_response(
+                    StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                    tonic::Code::InvalidArgument,
+                    &format!("Unsupported content type {:?}", content_type),
+                    reply,
+                )
+            }
+            None => return not_found(reply),
+        };
+        match request.uri().path() {
+            TRACES_PATH => match &self.trace {
+                Some(service) => {
+                    let request = match self.decode(request, encoding, connect_info).await {
+                        Ok(request) => request,
+                        Err(response) => return response,
+                    };
+                    to_response(service.export(request).await, encoding)
+                }
+                None => not_found(encoding),
+            },
+            LOGS_PATH => match &self.logs {
+                Some(service) => {
+                    let request = match self.decode(request, encoding, connect_info).await {
+                        Ok(request) => request,
+                        Err(response) => return response,
+                    };
+                    to_response(service.export(request).await, encoding)
+                }
+                None => not_found(encoding),
+            },
+            METRICS_PATH => match &self.metrics {
+                Some(service) => {
+                    let request = match self.decode(request, encoding, connect_info).await {
+                        Ok(request) => request,
+                        Err(response) => return response,
+                    };
+                    to_response(service.export(request).await, encoding)
+                }
+                None => not_found(encoding),
+            },
+            _ => not_found(encoding),
+        }
+    }
+
+    fn has_service(&self, path: &str) -> bool {
+        match path {
+            TRACES_PATH => self.trace.is_some(),
+            LOGS_PATH => self.logs.is_some(),
+            METRICS_PATH => self.metrics.is_some(),
+            _ => false,
+        }
+    }
+
+    async fn decode<T: Message + Default + DeserializeOwned>(
+        &self,
+        request: Request<Body>,
+        encoding: Encoding,
+        connect_info: TcpConnectInfo,
+    ) -> Result<tonic::Request<T>, Response<Body>> {
+        let (parts, body) = request.into_parts();
+        let content_encoding = parts.headers.get(CONTENT_ENCODING).cloned();
+        let mut request = tonic::Request::from_http(Request::from_parts(parts, ()));
+        request.extensions_mut().insert(connect_info);
+        // Unauthenticated requests are rejected before their body is read
+        if let Some(authenticator) = &self.authenticator {
+            request =
+                crate::auth::authenticate(authenticator.as_ref(), request).map_err(|status| {
+                    error_response(
+                        http_status(status.code()),
+                        status.code(),
+                        status.message(),
+                        encoding,
+                    )
+                })?;
+        }
+        let body = self.read_body(body, encoding).await?;
+        let body = match content_encoding {
+            None => body,
+            Some(content_encoding) if content_encoding == "identity" => body,
+            Some(content_encoding) if content_encoding == "gzip" => self.gunzip(&body, encoding)?,
+            Some(content_encoding) => {
+                return Err(error_response(
+                    StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                    tonic::Code::InvalidArgument,
+                    &format!("Unsupported content encoding {:?}", content_encoding),
+                    encoding,
+                ))
+            }
+        };
+        let message = match encoding {
+            Encoding::Protobuf => T::decode(body.as_slice()).map_err(|e| e.to_string()),
+            Encoding::Json => crate::json::from_slice(&body).map_err(|e| e.to_string()),
+        };
+        match message {
+            Ok(message) => Ok(request.map(|()| message)),
+            Err(e) => Err(error_response(
+                StatusCode::BAD_REQUEST,
+                tonic::Code::InvalidArgument,
+                &format!("Failed to decode {:?} payload {}", encoding, e),
+                encoding,
+            )),
+        }
+    }
+
+    async fn read_body(
+        &self,
+        mut body: Body,
+        encoding: Encoding,
+    ) -> Result<Vec<u8>, Response<Body>> {
+        let mut buffered = Vec::new();
+        while let Some(chunk) = body.data().await {
+            let chunk = chunk.map_err(|e| {
+                error_response(
+                    StatusCode::BAD_REQUEST,
+                    tonic::Code::InvalidArgument,
+                    &format!("Failed to read request body {}", e),
+                    encoding,
+                )
+            })?;
+            buffered.extend_from_slice(&chunk);
+            self.check_size(buffered.len(), encoding)?;
+        }
+        Ok(buffered)
+    }
+
+    fn gunzip(&self, body: &[u8], encoding: Encoding) -> Result<Vec<u8>, Response<Body>> {
+        let mut decoder = flate2::read::GzDecoder::new(body);
+        let mut decoded = Vec::new();
+        let mut chunk = [0_u8; 8192];
+        loop {
+            match decoder.read(&mut chunk) {
+                Ok(0) => return Ok(decoded),
+                Ok(n) => {
+                    decoded.extend_from_slice(&chunk[..n]);
+                    self.check_size(decoded.len(), encoding)?;
+                }
+                Err(e) => {
+                    return Err(error_response(
+                        StatusCode::BAD_REQUEST,
+                        tonic::Code::InvalidArgument,
+                        &format!("Failed to decompress gzip payload {}", e),
+                        encoding,
+                    ))
+                }
+            }
+        }
+    }
+
+    fn check_size(&self, size: usize, encoding: Encoding) -> Result<(), Response<Body>> {
+        match self.max_message_size {
+            Some(limit) if size > limit => Err(error_response(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                tonic::Code::ResourceExhausted,
+                &format!("Payload exceeds the maximum size of {} bytes", limit),
+                encoding,
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Spins up an `OTLP/HTTP OpenTelemetry Collector` instance
+pub async fn make(
+    addr: SocketAddr,
+    sender: Sender<OpenTelemetryEvents>,
+) -> Result<(), hyper::Error> {
+    HttpReceiver::with_sender(sender).serve(addr).await
+}
+
+/// Maps a gRPC status code onto the HTTP status code of the OTLP/HTTP specification
+pub fn http_status(code: tonic::Code) -> StatusCode {
+    match code {
+        tonic::Code::Ok => StatusCode::OK,
+        tonic::Code::InvalidArgument
+        | tonic::Code::FailedPrecondition
+        | tonic::Code::OutOfRange => StatusCode::BAD_REQUEST,
+        tonic::Code::Unauthenticated => StatusCode::UNAUTHORIZED,
+        tonic::Code::PermissionDenied => StatusCode::FORBIDDEN,
+        tonic::Code::NotFound | tonic::Code::Unimplemented => StatusCode::NOT_FOUND,
+        tonic::Code::ResourceExhausted => StatusCode::TOO_MANY_REQUESTS,
+        tonic::Code::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+        tonic::Code::DeadlineExceeded => StatusCode::GATEWAY_TIMEOUT,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Maps an HTTP error status of an OTLP/HTTP reply onto a gRPC status code
+///
+/// `429 Too Many Requests`, `502 Bad Gateway`, `503 Service Unavailable` and
+/// `504 Gateway Timeout` map onto retryable codes, as the OTLP/HTTP
+/// specification asks clients to retry them.
+pub fn grpc_code(status: StatusCode) -> tonic::Code {
+    match status {
+        StatusCode::OK => tonic::Code::Ok,
+        StatusCode::BAD_REQUEST | StatusCode::UNSUPPORTED_MEDIA_TYPE => {
+            tonic::Code::InvalidArgument
+        }
+        StatusCode::UNAUTHORIZED => tonic::Code::Unauthenticated,
+        StatusCode::FORBIDDEN => tonic::Code::PermissionDenied,
+        StatusCode::NOT_FOUND => tonic::Code::Unimplemented,
+        StatusCode::PAYLOAD_TOO_LARGE | StatusCode::TOO_MANY_REQUESTS => {
+            tonic::Code::ResourceExhausted
+        }
+        StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE => tonic::Code::Unavailable,
+        StatusCode::GATEWAY_TIMEOUT => tonic::Code::DeadlineExceeded,
+        status if status.is_client_error() => tonic::Code::InvalidArgument,
+        _ => tonic::Code::Internal,
+    }
+}
+
+fn to_response<T: Message + Serialize>(
+    result: Result<tonic::Response<T>, tonic::Status>,
+    encoding: Encoding,
+) -> Response<Body> {
+    match result {
+        Ok(response) => encoded_response(StatusCode::OK, &response.into_inner(), encoding),
+        Err(status) => {
+            let mut response = error_response(
+                http_status(status.code()),
+                status.code(),
+                status.message(),
+                encoding,
+            );
+            // Throttled exports carry their retry delay over to `Retry-After`
+            if let Some(delay) = crate::throttling::retry_after(&status) {
+                let seconds = delay.as_secs() + u64::from(delay.subsec_nanos() > 0);
+                response
+                    .headers_mut()
+                    .insert(RETRY_AFTER, HeaderValue::from(seconds));
+            }
+            response
+        }
+    }
+}
+
+fn not_found(encoding: Encoding) -> Response<Body> {
+    error_response(
+        StatusCode::NOT_FOUND,
+        tonic::Code::Unimplemented,
+        "No OTLP/HTTP service at this path",
+        encoding,
+    )
+}
+
+fn error_response(
+    status: StatusCode,
+    code: tonic::Code,
+    message: &str,
+    encoding: Encoding,
+) -> Response<Body> {
+    encoded_response(
+        status,
+        &RpcStatus {
+            code: code as i32,
+            message: message.to_string(),
+        },
+        encoding,
+    )
+}
+
+fn encoded_response<T: Message + Serialize>(
+    status: StatusCode,
+    message: &T,
+    encoding: Encoding,
+) -> Response<Body> {
+    let (status, body, encoding) = match encoding.encode(message) {
+        Ok(body) => (status, body, encoding),
+        // Protobuf encoding cannot fail, so the error is reported in it
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            RpcStatus {
+                code: tonic::Code::Internal as i32,
+                message: format!("Failed to encode Json response {}", e),
+            }
+            .encode_to_vec(),
+            Encoding::Protobuf,
+        ),
+    };
+    let mut response = Response::new(Body::from(body));
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static(encoding.content_type()),
+    );
+    response
+}
+
+/// OTLP/HTTP exporter posting export requests to `/v1/traces`, `/v1/logs`
+/// and `/v1/metrics` below an endpoint
+///
+/// Requests are protobuf encoded and gzip compressed by default. The default
+/// connector only speaks plain `http`, `https` endpoints need a TLS capable
+/// connector passed to `with_connector`.
+#[derive(Clone, Debug)]
+pub struct HttpExporter<C = HttpConnector> {
+    client: Client<C>,
+    endpoint: String,
+    encoding: Encoding,
+    gzip: bool,
+    headers: HeaderMap,
+}
+
+impl HttpExporter {
+    /// Creates an exporter for an endpoint such as `http://localhost:4318`
+    pub fn new<E: Into<String>>(endpoint: E) -> Result<Self, hyper::http::uri::InvalidUri> {
+        Self::with_connector(endpoint, HttpConnector::new())
+    }
+}
+
+impl<C> HttpExporter<C>
+where
+    C: Connect + Clone + Send + Sync + 'static,
+{
+    /// Creates an exporter connecting through a connector, such as a TLS
+    /// connector for `https://collector:4318`
+    pub fn with_connector<E: Into<String>>(
+        endpoint: E,
+        connector: C,
+    ) -> Result<Self, hyper::http::uri::InvalidUri> {
+        let endpoint = endpoint.into().trim_end_matches('/').to_string();
+        endpoint.parse::<Uri>()?;
+        Ok(HttpExporter {
+            client: Client::builder().build(connector),
+            endpoint,
+            encoding: Encoding::Protobuf,
+            gzip: true,
+            headers: HeaderMap::new(),
+        })
+    }
+
+    /// Selects the payload encoding of requests
+    pub fn with_encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Enables or disables gzip compression of requests
+    pub fn with_gzip(mut self, enabled: bool) -> Self {
+        self.gzip = enabled;
+        self
+    }
+
+    /// Adds a header sent with every request
+    pub fn with_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.append(name, value);
+        self
+    }
+
+    /// Exports spans to `/v1/traces`
+    pub async fn export_traces(
+        &self,
+        request: &ExportTraceServiceRequest,
+    ) -> Result<ExportTraceServiceResponse, tonic::Status> {
+        self.post(TRACES_PATH, request).await
+    }
+
+    /// Exports log records to `/v1/logs`
+    pub async fn export_logs(
+        &self,
+        request: &ExportLogsServiceRequest,
+    ) -> Result<ExportLogsServiceResponse, tonic::Status> {
+        self.post(LOGS_PATH, request).await
+    }
+
+    /// Exports metrics to `/v1/metrics`
+    pub async fn export_metrics(
+        &self,
+        request: &ExportMetricsServiceRequest,
+    ) -> Result<ExportMetricsServiceResponse, tonic::Status> {
+        self.post(METRICS_PATH, request).await
+    }
+
+    async fn post<T, R>(&self, path: &str, message: &T) -> Result<R, tonic::Status>
+    where
+        T: Message + Serialize,
+        R: Message + Default + DeserializeOwned,
+    {
+        let mut body = self.encoding.encode(message).map_err(|e| {
+            tonic::Status::internal(format!(
+                "Failed to encode {:?} payload {}",
+                self.encoding, e
+            ))
+        })?;
+        let mut request = Request::post(format!("{}{}", self.endpoint, path));
+        if let Some(headers) = request.headers_mut() {
+            headers.extend(self.headers.clone());
+            headers.insert(
+                CONTENT_TYPE,
+                HeaderValue::from_static(self.encoding.content_type()),
+            );
+            if self.gzip {
+                headers.insert(CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+            }
+        }
+        if self.gzip {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            body = encoder
+                .write_all(&body)
+                .and_then(|()| encoder.finish())
+                .map_err(|e| {
+                    tonic::Status::internal(format!("Failed to compress payload {}", e))
+                })?;
+        }
+        let request = request
+            .body(Body::from(body))
+            .map_err(|e| tonic::Status::internal(format!("Invalid OTLP/HTTP request {}", e)))?;
+        let response =
+            self.client.request(request).await.map_err(|e| {
+                tonic::Status::unavailable(format!("OTLP/HTTP request failed {}", e))
+            })?;
+        let status = response.status();
+        let encoding = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(Encoding::from_content_type)
+            .unwrap_or(self.encoding);
+        let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_retry_after);
+        let body = hyper::body::to_bytes(response.into_body())
+            .await
+            .map_err(|e| {
+                tonic::Status::unavailable(format!("Failed to read OTLP/HTTP response {}", e))
+            })?;
+        if status.is_success() {
+            return decode_response(&body, encoding).map_err(|e| {
+                tonic::Status::internal(format!("Failed to decode {:?} response {}", encoding, e))
+            });
+        }
+        let message = decode_response::<RpcStatus>(&body, encoding)
+            .ok()
+            .map(|rpc_status| rpc_status.message)
+            .filter(|message| !message.is_empty())
+            .unwrap_or_else(|| status.to_string());
+        let code = match (grpc_code(status), retry_after) {
+            // Without a delay from the server 429 is retried like 503
+            (tonic::Code::ResourceExhausted, None) if status == StatusCode::TOO_MANY_REQUESTS => {
+                tonic::Code::Unavailable
+            }
+            (code, _) => code,
+        };
+        Err(match retry_after {
+            Some(delay)
+                if status == StatusCode::TOO_MANY_REQUESTS
+                    || status == StatusCode::SERVICE_UNAVAILABLE =>
+            {
+                crate::throttling::status_with_retry_info(code, &message, delay)
+            }
+            _ => tonic::Status::new(code, message),
+        })
+    }
+}
+
+// Parses a `Retry-After` value, either a delay in seconds or an HTTP-date
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    match value.parse::<u64>() {
+        Ok(seconds) => Some(Duration::from_secs(seconds)),
+        Err(_) => httpdate::parse_http_date(value)
+            .ok()
+            .map(|date| date.duration_since(SystemTime::now()).unwrap_or_default()),
+    }
+}
+
+fn decode_response<T: Message + Default + DeserializeOwned>(
+    body: &[u8],
+    encoding: Encoding,
+) -> Result<T, String> {
+    match encoding {
+        Encoding::Protobuf => T::decode(body).map_err(|e| e.to_string()),
+        Encoding::Json if body.is_empty() => Ok(T::default()),
+        Encoding::Json => crate::json::from_slice(body).map_err(|e| e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::BearerTokenAuthenticator;
+    use crate::throttling::OverflowPolicy;
+    use async_channel::{bounded, Receiver};
+
+    type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+    // Serves a receiver on a free local port
+    fn spawn(receiver: HttpReceiver) -> Result<SocketAddr, hyper::Error> {
+        let incoming = AddrIncoming::bind(&SocketAddr::from(([127, 0, 0, 1], 0)))?;
+        let addr = incoming.local_addr();
+        tokio::spawn(receiver.serve_incoming(incoming, std::future::pending()));
+        Ok(addr)
+    }
+
+    fn receiver() -> Result<(SocketAddr, Receiver<OpenTelemetryEvents>), hyper::Error> {
+        let (tx, rx) = bounded(8);
+        let addr = spawn(HttpReceiver::with_sender(tx).max_message_size(1024))?;
+        Ok((addr, rx))
+    }
+
+    async fn send(
+        addr: SocketAddr,
+        method: Method,
+        path: &str,
+        headers: &[(&'static str, &'static str)],
+        body: Vec<u8>,
+    ) -> Result<Response<Body>, Box<dyn std::error::Error>> {
+        let mut request = Request::builder()
+            .method(method)
+            .uri(format!("http://{}{}", addr, path));
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        Ok(Client::new()
+            .request(request.body(Body::from(body))?)
+            .await?)
+    }
+
+    async fn post(
+        addr: SocketAddr,
+        path: &str,
+        headers: &[(&'static str, &'static str)],
+        body: Vec<u8>,
+    ) -> Result<Response<Body>, Box<dyn std::error::Error>> {
+        send(addr, Method::POST, path, headers, body).await
+    }
+
+    const PROTOBUF: (&str, &str) = ("content-type", "application/x-protobuf");
+    const JSON: (&str, &str) = ("content-type", "application/json");
+
+    async fn read_status(
+        response: Response<Body>,
+    ) -> Result<(StatusCode, RpcStatus), Box<dyn std::error::Error>> {
+        let status = response.status();
+        let encoding = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(Encoding::from_content_type)
+            .unwrap_or(Encoding::Protobuf);
+        let body = hyper::body::to_bytes(response.into_body()).await?;
+        Ok((status, decode_response(&body, encoding)?))
+    }
+
+    fn gzip(body: &[u8]) -> std::io::Result<Vec<u8>> {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(body)?;
+        encoder.finish()
+    }
+
+    #[tokio::test]
+    async fn receives_protobuf_and_json_requests() -> TestResult {
+        let (addr, rx) = receiver()?;
+        let request = ExportTraceServiceRequest::default().encode_to_vec();
+        let response = post(addr, TRACES_PATH, &[PROTOBUF], request).await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(CONTENT_TYPE),
+            Some(&HeaderValue::from_static(PROTOBUF_CONTENT_TYPE))
+        );
+        let body = hyper::body::to_bytes(response.into_body()).await?;
+        assert_eq!(
+            ExportTraceServiceResponse::decode(body)?,
+            ExportTraceServiceResponse::default()
+        );
+        assert!(matches!(rx.recv().await?, OpenTelemetryEvents::Trace(..)));
+
+        let response = post(
+            addr,
+            LOGS_PATH,
+            &[JSON],
+            br#"{"resourceLogs": []}"#.to_vec(),
+        )
+        .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(CONTENT_TYPE),
+            Some(&HeaderValue::from_static(JSON_CONTENT_TYPE))
+        );
+        assert!(matches!(rx.recv().await?, OpenTelemetryEvents::Logs(..)));
+
+        let request = ExportMetricsServiceRequest::default().encode_to_vec();
+        let response = post(
+            addr,
+            METRICS_PATH,
+            &[PROTOBUF, ("content-encoding", "gzip")],
+            gzip(&request)?,
+        )
+        .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(matches!(rx.recv().await?, OpenTelemetryEvents::Metrics(..)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn limits_the_decompressed_size() -> TestResult {
+        let (addr, rx) = receiver()?;
+        let body = gzip(&[0; 1025])?;
+        assert!(body.len() < 1024);
+        let response = post(
+            addr,
+            TRACES_PATH,
+            &[PROTOBUF, ("content-encoding", "gzip")],
+            body,
+        )
+        .await?;
+        let (status, rpc_status) = read_status(response).await?;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(rpc_status.code, tonic::Code::ResourceExhausted as i32);
+
+        let response = post(addr, TRACES_PATH, &[PROTOBUF], vec![0; 1025]).await?;
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert!(rx.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rejects_unroutable_requests() -> TestResult {
+        let (tx, _rx) = bounded(8);
+        let addr = spawn(HttpReceiver::new().with_trace(TraceServiceForwarder::with_sender(tx)))?;
+
+        let (status, rpc_status) =
+            read_status(post(addr, "/v1/other", &[PROTOBUF], vec![]).await?).await?;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(rpc_status.code, tonic::Code::Unimplemented as i32);
+        let response = post(addr, LOGS_PATH, &[JSON], b"{}".to_vec()).await?;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = post(addr, LOGS_PATH, &[("content-type", "text/plain")], vec![]).await?;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = send(addr, Method::GET, TRACES_PATH, &[PROTOBUF], vec![]).await?;
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+        let (status, rpc_status) =
+            read_status(post(addr, TRACES_PATH, &[("content-type", "text/plain")], vec![]).await?)
+                .await?;
+        assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert_eq!(rpc_status.code, tonic::Code::InvalidArgument as i32);
+        let response = post(
+            addr,
+            TRACES_PATH,
+            &[PROTOBUF, ("content-encoding", "br")],
+            vec![],
+        )
+        .await?;
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn answers_malformed_payloads_with_an_rpc_status() -> TestResult {
+        let (addr, rx) = receiver()?;
+        let (status, rpc_status) =
+            read_status(post(addr, TRACES_PATH, &[PROTOBUF], vec![0xff; 3]).await?).await?;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(rpc_status.code, tonic::Code::InvalidArgument as i32);
+        assert!(rpc_status
+            .message
+            .starts_with("Failed to decode Protobuf payload"));
+
+        let response = post(
+            addr,
+            TRACES_PATH,
+            &[JSON],
+            b"{\"resourceSpans\": 1}".to_vec(),
+        )
+        .await?;
+        let (status, rpc_status) = read_status(response).await?;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(rpc_status
+            .message
+            .starts_with("Failed to decode Json payload"));
+        assert!(rx.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn authenticates_before_reading_the_body() -> TestResult {
+        let (tx, rx) = bounded(8);
+        let addr = spawn(
+            HttpReceiver::with_sender(tx)
+                .with_authenticator(BearerTokenAuthenticator::new().token("t0k3n", "tenant")),
+        )?;
+        // The body never ends, so reading it would not answer
+        let (_body_tx, body) = Body::channel();
+        let request = Request::post(format!("http://{}{}", addr, TRACES_PATH))
+            .header(CONTENT_TYPE, PROTOBUF_CONTENT_TYPE)
+            .body(body)?;
+        let response =
+            tokio::time::timeout(Duration::from_secs(5), Client::new().request(request)).await??;
+        let (status, rpc_status) = read_status(response).await?;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(rpc_status.code, tonic::Code::Unauthenticated as i32);
+
+        let request = ExportTraceServiceRequest::default().encode_to_vec();
+        let response = post(
+            addr,
+            TRACES_PATH,
+            &[PROTOBUF, ("authorization", "Bearer t0k3n")],
+            request,
+        )
+        .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        let event = rx.recv().await?;
+        assert_eq!(
+            event
+                .metadata()
+                .principal
+                .as_ref()
+                .map(|principal| principal.name()),
+            Some("tenant")
+        );
+        assert_eq!(event.remote_addr().map(|addr| addr.ip()), Some(addr.ip()));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn carries_the_retry_delay_of_throttled_exports() -> TestResult {
+        let (tx, _rx) = bounded(1);
+        let forwarder =
+            TraceServiceForwarder::with_sender(tx).with_overflow_policy(OverflowPolicy::Reject {
+                retry_after: Duration::from_millis(1500),
+            });
+        let addr = spawn(HttpReceiver::new().with_trace(forwarder))?;
+        let request = ExportTraceServiceRequest::default().encode_to_vec();
+        let response = post(addr, TRACES_PATH, &[PROTOBUF], request.clone()).await?;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = post(addr, TRACES_PATH, &[PROTOBUF], request).await?;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(
+            response.headers().get(RETRY_AFTER),
+            Some(&HeaderValue::from(2))
+        );
+        let (_, rpc_status) = read_status(response).await?;
+        assert_eq!(rpc_status.code, tonic::Code::ResourceExhausted as i32);
+        Ok(())
+    }
+
+    #[test]
+    fn parses_retry_after_seconds_and_dates() {
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after(" 0 "), Some(Duration::from_secs(0)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::from_secs(0))
+        );
+        let date = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(3600));
+        let delay = parse_retry_after(&date).unwrap_or_default();
+        assert!(delay > Duration::from_secs(3500) && delay <= Duration::from_secs(3600));
+        assert_eq!(parse_retry_after("soon"), None);
+        assert_eq!(parse_retry_after("-1"), None);
+    }
+
+    // Replies to every request with a fixed response
+    fn mock(
+        status: StatusCode,
+        headers: &'static [(&'static str, &'static str)],
+        body: Vec<u8>,
+    ) -> Result<SocketAddr, hyper::Error> {
+        let incoming = AddrIncoming::bind(&SocketAddr::from(([127, 0, 0, 1], 0)))?;
+        let addr = incoming.local_addr();
+        let make_svc = make_service_fn(move |_: &AddrStream| {
+            let body = body.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |_: Request<Body>| {
+                    let mut response = Response::new(Body::from(body.clone()));
+                    *response.status_mut() = status;
+                    for (name, value) in headers {
+                        response
+                            .headers_mut()
+                            .insert(*name, HeaderValue::from_static(value));
+                    }
+                    async move { Ok::<_, Infallible>(response) }
+                }))
+            }
+        });
+        tokio::spawn(Server::builder(incoming).serve(make_svc));
+        Ok(addr)
+    }
+
+    async fn export_to(addr: SocketAddr) -> Result<tonic::Status, Box<dyn std::error::Error>> {
+        let exporter = HttpExporter::new(format!("http://{}", addr))?;
+        match exporter
+            .export_traces(&ExportTraceServiceRequest::default())
+            .await
+        {
+            Ok(_) => Err("the export succeeded".into()),
+            Err(status) => Ok(status),
+        }
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    struct Unserializable {}
+
+    impl Serialize for Unserializable {
+        fn serialize<S: serde::Serializer>(&self, _serializer: S) -> Result<S::Ok, S::Error> {
+            Err(serde::ser::Error::custom("not serializable"))
+        }
+    }
+
+    #[test]
+    fn maps_http_statuses_onto_grpc_codes() {
+        for (status, code) in [
+            (StatusCode::OK, tonic::Code::Ok),
+            (StatusCode::BAD_REQUEST, tonic::Code::InvalidArgument),
+            (StatusCode::UNAUTHORIZED, tonic::Code::Unauthenticated),
+            (StatusCode::FORBIDDEN, tonic::Code::PermissionDenied),
+            (StatusCode::NOT_FOUND, tonic::Code::Unimplemented),
+            (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                tonic::Code::ResourceExhausted,
+            ),
+            (
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                tonic::Code::InvalidArgument,
+            ),
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                tonic::Code::ResourceExhausted,
+            ),
+            (StatusCode::CONFLICT, tonic::Code::InvalidArgument),
+            (StatusCode::INTERNAL_SERVER_ERROR, tonic::Code::Internal),
+            (StatusCode::BAD_GATEWAY, tonic::Code::Unavailable),
+            (StatusCode::SERVICE_UNAVAILABLE, tonic::Code::Unavailable),
+            (StatusCode::GATEWAY_TIMEOUT, tonic::Code::DeadlineExceeded),
+        ] {
+            assert_eq!(grpc_code(status), code, "{}", status);
+        }
+    }
+
+    #[tokio::test]
+    async fn exports_to_an_http_receiver() -> TestResult {
+        let (tx, rx) = bounded(8);
+        let addr = spawn(
+            HttpReceiver::with_sender(tx)
+                .with_authenticator(BearerTokenAuthenticator::new().token("t0k3n", "tenant")),
+        )?;
+        let exporter = HttpExporter::new(format!("http://{}/", addr))?.with_header(
+            hyper::header::AUTHORIZATION,
+            HeaderValue::from_static("Bearer t0k3n"),
+        );
+        exporter
+            .export_traces(&ExportTraceServiceRequest::default())
+            .await?;
+        assert!(matches!(rx.recv().await?, OpenTelemetryEvents::Trace(..)));
+
+        let exporter = exporter.with_encoding(Encoding::Json).with_gzip(false);
+        exporter
+            .export_logs(&ExportLogsServiceRequest::default())
+            .await?;
+        assert!(matches!(rx.recv().await?, OpenTelemetryEvents::Logs(..)));
+        exporter
+            .export_metrics(&ExportMetricsServiceRequest::default())
+            .await?;
+        assert!(matches!(rx.recv().await?, OpenTelemetryEvents::Metrics(..)));
+
+        let status = export_to(addr).await?;
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn turns_error_responses_into_statuses() -> TestResult {
+        let status = export_to(mock(StatusCode::TOO_MANY_REQUESTS, &[], vec![])?).await?;
+        assert_eq!(status.code(), tonic::Code::Unavailable);
+        assert_eq!(crate::throttling::retry_after(&status), None);
+
+        let addr = mock(
+            StatusCode::TOO_MANY_REQUESTS,
+            &[("retry-after", "3")],
+            vec![],
+        )?;
+        let status = export_to(addr).await?;
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+        assert_eq!(
+            crate::throttling::retry_after(&status),
+            Some(Duration::from_secs(3))
+        );
+
+        let addr = mock(
+            StatusCode::SERVICE_UNAVAILABLE,
+            &[("retry-after", "Wed, 21 Oct 2015 07:28:00 GMT")],
+            vec![],
+        )?;
+        let status = export_to(addr).await?;
+        assert_eq!(status.code(), tonic::Code::Unavailable);
+        assert_eq!(
+            crate::throttling::retry_after(&status),
+            Some(Duration::ZERO)
+        );
+
+        let rpc_status = RpcStatus {
+            code: tonic::Code::InvalidArgument as i32,
+            message: "no spans".to_string(),
+        };
+        let addr = mock(
+            StatusCode::BAD_REQUEST,
+            &[("content-type", PROTOBUF_CONTENT_TYPE)],
+            rpc_status.encode_to_vec(),
+        )?;
+        let status = export_to(addr).await?;
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        assert_eq!(status.message(), "no spans");
+
+        let status = export_to(mock(StatusCode::INTERNAL_SERVER_ERROR, &[], vec![])?).await?;
+        assert_eq!(status.code(), tonic::Code::Internal);
+        assert_eq!(status.message(), "500 Internal Server Error");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reports_json_encoding_errors() -> TestResult {
+        // Nothing listens here, the request fails before it is sent
+        let exporter = HttpExporter::new("http://127.0.0.1:1")?.with_encoding(Encoding::Json);
+        let result: Result<ExportTraceServiceResponse, _> =
+            exporter.post(TRACES_PATH, &Unserializable {}).await;
+        let status = result.err().ok_or("the export succeeded")?;
+        assert_eq!(status.code(), tonic::Code::Internal);
+        assert!(status.message().contains("not serializable"));
+
+        let response = encoded_response(StatusCode::OK, &Unserializable {}, Encoding::Json);
+        let (status, rpc_status) = read_status(response).await?;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(rpc_status.code, tonic::Code::Internal as i32);
+        Ok(())
+    }
+}
@@ -0,0 +1,213 @@
+// Copyright 2020-2022, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Partial success of export requests, reporting the number of rejected
+//! items and why they were rejected in the `partial_success` field of the
+//! export responses.
+//!
+//! Trace responses count rejected spans, logs responses rejected log records
+//! and metrics responses rejected data points. Handlers reply with a partial
+//! success when they drop items, forwarders do so for the items stripped by
+//! their `ValidationPolicy`.
+//!
+//! ```ignore
+//! let response = ExportTraceServiceResponse::rejected(2, "spans without a name");
+//! Ok(tonic::Response::new(response))
+//! ```
+
+/// Export responses reporting partially rejected requests
+pub trait PartialSuccess: Default {
+    /// Creates a response reporting rejected items, a full success when the
+    /// count is zero and the message empty
+    fn rejected<M: Into<String>>(count: i64, message: M) -> Self;
+
+    /// The number of rejected items
+    fn rejected_count(&self) -> i64;
+
+    /// Why items were rejected, if the server said so
+    fn error_message(&self) -> Option<&str>;
+}
+
+/// Export requests and their responses
+pub trait ExportRequest {
+    /// The response to the request
+    type Response: PartialSuccess;
+
+    /// The number of items a partial success of the request counts
+    fn item_count(&self) -> usize;
+}
+
+#[cfg(feature = "otel-trace")]
+mod trace {
+    use super::{ExportRequest, PartialSuccess};
+    use crate::opentelemetry::proto::collector::trace::v1::{
+        ExportTracePartialSuccess, ExportTraceServiceRequest, ExportTraceServiceResponse,
+    };
+
+    impl PartialSuccess for ExportTraceServiceResponse {
+        fn rejected<M: Into<String>>(count: i64, message: M) -> Self {
+            let error_message = message.into();
+            if count == 0 && error_message.is_empty() {
+                return Self::default();
+            }
+            ExportTraceServiceResponse {
+                partial_success: Some(ExportTracePartialSuccess {
+                    rejected_spans: count,
+                    error_message,
+                }),
+            }
+        }
+
+        fn rejected_count(&self) -> i64 {
+            self.partial_success
+                .as_ref()
+                .map_or(0, |partial| partial.rejected_spans)
+        }
+
+        fn error_message(&self) -> Option<&str> {
+            self.partial_success
+                .as_ref()
+                .map(|partial| partial.error_message.as_str())
+                .filter(|message| !message.is_empty())
+        }
+    }
+
+    impl ExportRequest for ExportTraceServiceRequest {
+        type Response = ExportTraceServiceResponse;
+
+        /// The number of spans
+        fn item_count(&self) -> usize {
+            self.resource_spans
+                .iter()
+                .flat_map(|resource| &resource.scope_spans)
+                .map(|scope| scope.spans.len())
+                .sum()
+        }
+    }
+}
+
+#[cfg(feature = "otel-logs")]
+mod logs {
+    use super::{ExportRequest, PartialSuccess};
+    use crate::opentelemetry::proto::collector::logs::v1::{
+        ExportLogsPartialSuccess, ExportLogsServiceRequest, ExportLogsServiceResponse,
+    };
+
+    impl PartialSuccess for ExportLogsServiceResponse {
+        fn rejected<M: Into<String>>(count: i64, message: M) -> Self {
+            let error_message = message.into();
+            if count == 0 && error_message.is_empty() {
+                return Self::default();
+            }
+            ExportLogsServiceResponse {
+                partial_success: Some(ExportLogsPartialSuccess {
+                    rejected_log_records: count,
+                    error_message,
+                }),
+            }
+        }
+
+        fn rejected_count(&self) -> i64 {
+            self.partial_success
+                .as_ref()
+                .map_or(0, |partial| partial.rejected_log_records)
+        }
+
+        fn error_message(&self) -> Option<&str> {
+            self.partial_success
+                .as_ref()
+                .map(|partial| partial.error_message.as_str())
+                .filter(|message| !message.is_empty())
+        }
+    }
+
+    impl ExportRequest for ExportLogsServiceRequest {
+        type Response = ExportLogsServiceResponse;
+
+        /// The number of log records
+        fn item_count(&self) -> usize {
+            self.resource_logs
+                .iter()
+                .flat_map(|resource| &resource.scope_logs)
+                .map(|scope| scope.log_records.len())
+                .sum()
+        }
+    }
+}
+
+#[cfg(feature = "otel-metrics")]
+pub(crate) use self::metrics::data_point_count;
+
+#[cfg(feature = "otel-metrics")]
+mod metrics {
+    use super::{ExportRequest, PartialSuccess};
+    use crate::opentelemetry::proto::collector::metrics::v1::{
+        ExportMetricsPartialSuccess, ExportMetricsServiceRequest, ExportMetricsServiceResponse,
+    };
+    use crate::opentelemetry::proto::metrics::v1::{metric, Metric};
+
+    impl PartialSuccess for ExportMetricsServiceResponse {
+        fn rejected<M: Into<String>>(count: i64, message: M) -> Self {
+            let error_message = message.into();
+            if count == 0 && error_message.is_empty() {
+                return Self::default();
+            }
+            ExportMetricsServiceResponse {
+                partial_success: Some(ExportMetricsPartialSuccess {
+                    rejected_data_points: count,
+                    error_message,
+                }),
+            }
+        }
+
+        fn rejected_count(&self) -> i64 {
+            self.partial_success
+                .as_ref()
+                .map_or(0, |partial| partial.rejected_data_points)
+        }
+
+        fn error_message(&self) -> Option<&str> {
+            self.partial_success
+                .as_ref()
+                .map(|partial| partial.error_message.as_str())
+                .filter(|message| !message.is_empty())
+        }
+    }
+
+    // The data points of a metric, the items metrics responses count
+    pub(crate) fn data_point_count(metric: &Metric) -> usize {
+        match &metric.data {
+            None => 0,
+            Some(metric::Data::Gauge(gauge)) => gauge.data_points.len(),
+            Some(metric::Data::Sum(sum)) => sum.data_points.len(),
+            Some(metric::Data::Histogram(histogram)) => histogram.data_points.len(),
+            Some(metric::Data::ExponentialHistogram(histogram)) => histogram.data_points.len(),
+            Some(metric::Data::Summary(summary)) => summary.data_points.len(),
+        }
+    }
+
+    impl ExportRequest for ExportMetricsServiceRequest {
+        type Response = ExportMetricsServiceResponse;
+
+        /// The number of data points
+        fn item_count(&self) -> usize {
+            self.resource_metrics
+                .iter()
+                .flat_map(|resource| &resource.scope_metrics)
+                .flat_map(|scope| &scope.metrics)
+                .map(data_point_count)
+                .sum()
+        }
+    }
+}
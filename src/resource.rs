@@ -0,0 +1,214 @@
+// Copyright 2020-2022, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Accessors for the semantic convention attributes of a `Resource` and the
+//! merging of resources following the OpenTelemetry resource specification.
+//!
+//! ```ignore
+//! let resource = merge(&detected, &configured);
+//! let service = resource.service_name().unwrap_or("unknown_service");
+//! ```
+
+use crate::opentelemetry::proto::common::v1::{any_value, AnyValue};
+use crate::opentelemetry::proto::resource::v1::Resource;
+use crate::semconv::resource as semconv;
+
+impl Resource {
+    /// The value of the first attribute with the key
+    pub fn attribute(&self, key: &str) -> Option<&AnyValue> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.key == key)
+            .and_then(|attribute| attribute.value.as_ref())
+    }
+
+    /// The string value of the first attribute with the key
+    pub fn str_attribute(&self, key: &str) -> Option<&str> {
+        match self.attribute(key)?.value.as_ref()? {
+            any_value::Value::StringValue(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// The `service.name` of the resource
+    pub fn service_name(&self) -> Option<&str> {
+        self.str_attribute(semconv::SERVICE_NAME)
+    }
+
+    /// The `service.namespace` of the resource
+    pub fn service_namespace(&self) -> Option<&str> {
+        self.str_attribute(semconv::SERVICE_NAMESPACE)
+    }
+
+    /// The `service.instance.id` of the resource
+    pub fn service_instance_id(&self) -> Option<&str> {
+        self.str_attribute(semconv::SERVICE_INSTANCE_ID)
+    }
+
+    /// The `service.version` of the resource
+    pub fn service_version(&self) -> Option<&str> {
+        self.str_attribute(semconv::SERVICE_VERSION)
+    }
+
+    /// The `host.name` of the resource
+    pub fn host_name(&self) -> Option<&str> {
+        self.str_attribute(semconv::HOST_NAME)
+    }
+
+    /// The `deployment.environment` of the resource
+    pub fn deployment_environment(&self) -> Option<&str> {
+        self.str_attribute(semconv::DEPLOYMENT_ENVIRONMENT)
+    }
+}
+
+/// Merges two resources, the attributes and entity references of the
+/// updating resource take precedence over those of the old resource
+///
+/// Attributes keep the order of the old resource, with the attributes only
+/// the updating resource has appended. Dropped attribute counts add up.
+pub fn merge(old: &Resource, updating: &Resource) -> Resource {
+    let mut merged = old.clone();
+    for attribute in &updating.attributes {
+        match merged
+            .attributes
+            .iter_mut()
+            .find(|known| known.key == attribute.key)
+        {
+            Some(known) => *known = attribute.clone(),
+            None => merged.attributes.push(attribute.clone()),
+        }
+    }
+    for entity in &updating.entity_refs {
+        match merged
+            .entity_refs
+            .iter_mut()
+            .find(|known| known.r#type == entity.r#type)
+        {
+            Some(known) => *known = entity.clone(),
+            None => merged.entity_refs.push(entity.clone()),
+        }
+    }
+    merged.dropped_attributes_count = old
+        .dropped_attributes_count
+        .saturating_add(updating.dropped_attributes_count);
+    merged
+}
+
+/// Merges the schema URLs of two resources, `None` if both are set and differ
+///
+/// The specification leaves the merged resource undefined in that case.
+pub fn merge_schema_url<'a>(old: &'a str, updating: &'a str) -> Option<&'a str> {
+    if old.is_empty() {
+        Some(updating)
+    } else if updating.is_empty() || old == updating {
+        Some(old)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opentelemetry::proto::common::v1::EntityRef;
+    use crate::value::key_value;
+
+    fn entity(r#type: &str, id_key: &str) -> EntityRef {
+        EntityRef {
+            r#type: r#type.to_string(),
+            id_keys: vec![id_key.to_string()],
+            ..EntityRef::default()
+        }
+    }
+
+    #[test]
+    fn updating_attributes_take_precedence() {
+        let old = Resource {
+            attributes: vec![
+                key_value(semconv::SERVICE_NAME, "old"),
+                key_value(semconv::HOST_NAME, "host"),
+            ],
+            dropped_attributes_count: 2,
+            entity_refs: vec![entity("service", "service.name"), entity("host", "host.id")],
+        };
+        let updating = Resource {
+            attributes: vec![
+                key_value(semconv::SERVICE_VERSION, "1.0"),
+                key_value(semconv::SERVICE_NAME, "new"),
+            ],
+            dropped_attributes_count: 3,
+            entity_refs: vec![entity("service", "service.instance.id")],
+        };
+        let merged = merge(&old, &updating);
+        assert_eq!(
+            merged.attributes,
+            vec![
+                key_value(semconv::SERVICE_NAME, "new"),
+                key_value(semconv::HOST_NAME, "host"),
+                key_value(semconv::SERVICE_VERSION, "1.0"),
+            ]
+        );
+        assert_eq!(merged.service_name(), Some("new"));
+        assert_eq!(merged.host_name(), Some("host"));
+        assert_eq!(merged.dropped_attributes_count, 5);
+        assert_eq!(
+            merged.entity_refs,
+            vec![
+                entity("service", "service.instance.id"),
+                entity("host", "host.id")
+            ]
+        );
+
+        let saturated = Resource {
+            dropped_attributes_count: u32::MAX,
+            ..Resource::default()
+        };
+        assert_eq!(
+            merge(&saturated, &updating).dropped_attributes_count,
+            u32::MAX
+        );
+        assert_eq!(
+            merge(&Resource::default(), &Resource::default()),
+            Resource::default()
+        );
+    }
+
+    #[test]
+    fn reads_semantic_convention_attributes() {
+        let resource = Resource {
+            attributes: vec![
+                key_value(semconv::SERVICE_NAMESPACE, "shop"),
+                key_value(semconv::SERVICE_INSTANCE_ID, 7_i64),
+                key_value(semconv::DEPLOYMENT_ENVIRONMENT, "production"),
+            ],
+            ..Resource::default()
+        };
+        assert_eq!(resource.service_namespace(), Some("shop"));
+        assert_eq!(resource.service_instance_id(), None);
+        assert!(resource.attribute(semconv::SERVICE_INSTANCE_ID).is_some());
+        assert_eq!(resource.deployment_environment(), Some("production"));
+        assert_eq!(resource.service_name(), None);
+    }
+
+    #[test]
+    fn merges_schema_urls() {
+        let v1 = "https://opentelemetry.io/schemas/1.25.0";
+        let v2 = "https://opentelemetry.io/schemas/1.26.0";
+        assert_eq!(merge_schema_url("", ""), Some(""));
+        assert_eq!(merge_schema_url("", v2), Some(v2));
+        assert_eq!(merge_schema_url(v1, ""), Some(v1));
+        assert_eq!(merge_schema_url(v1, v1), Some(v1));
+        assert_eq!(merge_schema_url(v1, v2), None);
+    }
+}
@@ -0,0 +1,413 @@
+// Copyright 2020-2022, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Retries export calls that failed with a status the OTLP specification
+//! classifies as retryable:
+//!
+//! * `CANCELLED`, `DEADLINE_EXCEEDED`, `ABORTED`, `OUT_OF_RANGE`,
+//!   `UNAVAILABLE` and `DATA_LOSS` are always retryable
+//! * `RESOURCE_EXHAUSTED` is retryable when the server sent a
+//!   `google.rpc.RetryInfo` detail
+//! * all other codes are not retryable
+//!
+//! Retries back off exponentially with jitter, or wait for the delay of a
+//! `google.rpc.RetryInfo` detail when the server sent one, until the maximum
+//! elapsed time would be exceeded.
+//!
+//! ```ignore
+//! let client = TraceServiceClient::connect("http://0.0.0.0:4317").await?;
+//! let mut client = RetryingClient::new(client, RetryPolicy::new());
+//! client.export(request).await?;
+//! ```
+
+use crate::throttling::retry_after;
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+use tokio::time::Instant;
+use tonic::{Code, Status};
+
+/// Default delay before the first retry
+pub const DEFAULT_INITIAL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Default upper bound of the backoff delay
+pub const DEFAULT_MAX_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Default time after which no further retry is attempted
+pub const DEFAULT_MAX_ELAPSED_TIME: Duration = Duration::from_secs(60);
+
+/// Checks if a failed export may be retried, following the OTLP specification
+pub fn is_retryable(status: &Status) -> bool {
+    match status.code() {
+        Code::Cancelled
+        | Code::DeadlineExceeded
+        | Code::Aborted
+        | Code::OutOfRange
+        | Code::Unavailable
+        | Code::DataLoss => true,
+        Code::ResourceExhausted => retry_after(status).is_some(),
+        _ => false,
+    }
+}
+
+/// Exponential backoff settings for retrying failed exports
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    initial_interval: Duration,
+    max_interval: Duration,
+    multiplier: f64,
+    randomization_factor: f64,
+    max_elapsed_time: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            initial_interval: DEFAULT_INITIAL_INTERVAL,
+            max_interval: DEFAULT_MAX_INTERVAL,
+            multiplier: 1.5,
+            randomization_factor: 0.5,
+            max_elapsed_time: DEFAULT_MAX_ELAPSED_TIME,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Creates a policy with the backoff defaults of the OpenTelemetry SDKs
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the delay before the first retry, at most the maximum interval
+    pub fn initial_interval(mut self, interval: Duration) -> Self {
+        self.initial_interval = interval;
+        self
+    }
+
+    /// Sets the upper bound of the backoff delay
+    pub fn max_interval(mut self, interval: Duration) -> Self {
+        self.max_interval = interval;
+        self
+    }
+
+    /// Sets the factor the backoff delay grows by after each retry
+    pub fn multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier.max(1.0);
+        self
+    }
+
+    /// Sets the jitter, a factor of 0.5 randomizes each delay by up to 50% in
+    /// either direction
+    pub fn randomization_factor(mut self, factor: f64) -> Self {
+        self.randomization_factor = factor.max(0.0).min(1.0);
+        self
+    }
+
+    /// Sets the time after which no further retry is attempted
+    pub fn max_elapsed_time(mut self, elapsed: Duration) -> Self {
+        self.max_elapsed_time = elapsed;
+        self
+    }
+
+    /// Calls `attempt` until it succeeds, fails with a status that is not
+    /// retryable, or the next retry would exceed the maximum elapsed time
+    pub async fn call<F, Fut, T>(&self, mut attempt: F) -> Result<T, Status>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, Status>>,
+    {
+        let started = Instant::now();
+        let mut interval = self.initial_interval.min(self.max_interval);
+        loop {
+            let status = match attempt().await {
+                Ok(response) => return Ok(response),
+                Err(status) => status,
+            };
+            if !is_retryable(&status) {
+                return Err(status);
+            }
+            let delay = retry_after(&status).unwrap_or_else(|| self.jitter(interval));
+            match started.elapsed().checked_add(delay) {
+                Some(elapsed) if elapsed <= self.max_elapsed_time => (),
+                _ => return Err(status),
+            }
+            tokio::time::sleep(delay).await;
+            interval = saturating_mul(interval, self.multiplier).min(self.max_interval);
+        }
+    }
+
+    fn jitter(&self, interval: Duration) -> Duration {
+        // A fresh `RandomState` is randomly keyed, which is random enough for jitter
+        let random = RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64;
+        let delta = self.randomization_factor * (2.0 * random - 1.0);
+        saturating_mul(interval, 1.0 + delta)
+    }
+}
+
+// Scales a duration by a non negative factor, saturating instead of
+// panicking when the product overflows
+fn saturating_mul(duration: Duration, factor: f64) -> Duration {
+    let secs = duration.as_secs_f64() * factor;
+    if secs >= u64::MAX as f64 {
+        Duration::MAX
+    } else {
+        Duration::from_secs_f64(secs)
+    }
+}
+
+/// Wraps a generated client so its export calls are retried under a policy
+#[derive(Clone, Debug)]
+pub struct RetryingClient<C> {
+    client: C,
+    policy: RetryPolicy,
+}
+
+impl<C> RetryingClient<C> {
+    /// Wraps a client
+    pub fn new(client: C, policy: RetryPolicy) -> Self {
+        RetryingClient { client, policy }
+    }
+
+    /// The retry policy of the client
+    pub fn policy(&self) -> &RetryPolicy {
+        &self.policy
+    }
+
+    /// Unwraps the client
+    pub fn into_inner(self) -> C {
+        self.client
+    }
+}
+
+#[cfg(feature = "otel-trace")]
+mod trace {
+    use super::RetryingClient;
+    use crate::opentelemetry::proto::collector::trace::v1::trace_service_client::TraceServiceClient;
+    use crate::opentelemetry::proto::collector::trace::v1::{
+        ExportTraceServiceRequest, ExportTraceServiceResponse,
+    };
+    use tonic::codegen::{Body, StdError};
+
+    impl<T> RetryingClient<TraceServiceClient<T>>
+    where
+        T: tonic::client::GrpcService<tonic::body::BoxBody> + Clone,
+        T::ResponseBody: Body + Send + 'static,
+        T::Error: Into<StdError>,
+        <T::ResponseBody as Body>::Error: Into<StdError> + Send,
+    {
+        /// Exports spans, retrying retryable failures
+        pub async fn export(
+            &mut self,
+            request: ExportTraceServiceRequest,
+        ) -> Result<tonic::Response<ExportTraceServiceResponse>, tonic::Status> {
+            let client = &self.client;
+            let request = &request;
+            self.policy
+                .call(|| {
+                    let mut client = client.clone();
+                    let request = request.clone();
+                    async move { client.export(request).await }
+                })
+                .await
+        }
+    }
+}
+
+#[cfg(feature = "otel-logs")]
+mod logs {
+    use super::RetryingClient;
+    use crate::opentelemetry::proto::collector::logs::v1::logs_service_client::LogsServiceClient;
+    use crate::opentelemetry::proto::collector::logs::v1::{
+        ExportLogsServiceRequest, ExportLogsServiceResponse,
+    };
+    use tonic::codegen::{Body, StdError};
+
+    impl<T> RetryingClient<LogsServiceClient<T>>
+    where
+        T: tonic::client::GrpcService<tonic::body::BoxBody> + Clone,
+        T::ResponseBody: Body + Send + 'static,
+        T::Error: Into<StdError>,
+        <T::ResponseBody as Body>::Error: Into<StdError> + Send,
+    {
+        /// Exports log records, retrying retryable failures
+        pub async fn export(
+            &mut self,
+            request: ExportLogsServiceRequest,
+        ) -> Result<tonic::Response<ExportLogsServiceResponse>, tonic::Status> {
+            let client = &self.client;
+            let request = &request;
+            self.policy
+                .call(|| {
+                    let mut client = client.clone();
+                    let request = request.clone();
+                    async move { client.export(request).await }
+                })
+                .await
+        }
+    }
+}
+
+#[cfg(feature = "otel-metrics")]
+mod metrics {
+    use super::RetryingClient;
+    use crate::opentelemetry::proto::collector::metrics::v1::metrics_service_client::MetricsServiceClient;
+    use crate::opentelemetry::proto::collector::metrics::v1::{
+        ExportMetricsServiceRequest, ExportMetricsServiceResponse,
+    };
+    use tonic::codegen::{Body, StdError};
+
+    impl<T> RetryingClient<MetricsServiceClient<T>>
+    where
+        T: tonic::client::GrpcService<tonic::body::BoxBody> + Clone,
+        T::ResponseBody: Body + Send + 'static,
+        T::Error: Into<StdError>,
+        <T::ResponseBody as Body>::Error: Into<StdError> + Send,
+    {
+        /// Exports metrics, retrying retryable failures
+        pub async fn export(
+            &mut self,
+            request: ExportMetricsServiceRequest,
+        ) -> Result<tonic::Response<ExportMetricsServiceResponse>, tonic::Status> {
+            let client = &self.client;
+            let request = &request;
+            self.policy
+                .call(|| {
+                    let mut client = client.clone();
+                    let request = request.clone();
+                    async move { client.export(request).await }
+                })
+                .await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::throttling::status_with_retry_info;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy::new()
+            .initial_interval(Duration::from_secs(1))
+            .max_interval(Duration::from_secs(4))
+            .multiplier(2.0)
+            .randomization_factor(0.0)
+            .max_elapsed_time(Duration::from_secs(10))
+    }
+
+    // Fails every attempt with a status, returning the number of attempts and
+    // the time they took
+    async fn failures<F: Fn() -> Status>(policy: &RetryPolicy, status: F) -> (usize, Duration) {
+        let started = Instant::now();
+        let mut attempts = 0;
+        let result = policy
+            .call(|| {
+                attempts += 1;
+                let status = status();
+                async move { Err::<(), _>(status) }
+            })
+            .await;
+        assert_eq!(result.err().map(|e| e.code()), Some(status().code()));
+        (attempts, started.elapsed())
+    }
+
+    #[test]
+    fn classifies_retryable_statuses() {
+        for code in [
+            Code::Cancelled,
+            Code::DeadlineExceeded,
+            Code::Aborted,
+            Code::OutOfRange,
+            Code::Unavailable,
+            Code::DataLoss,
+        ] {
+            assert!(is_retryable(&Status::new(code, "")), "{:?}", code);
+        }
+        for code in [
+            Code::Unknown,
+            Code::InvalidArgument,
+            Code::PermissionDenied,
+            Code::Unauthenticated,
+            Code::Internal,
+            Code::ResourceExhausted,
+        ] {
+            assert!(!is_retryable(&Status::new(code, "")), "{:?}", code);
+        }
+        let throttled =
+            status_with_retry_info(Code::ResourceExhausted, "slow down", Duration::from_secs(1));
+        assert!(is_retryable(&throttled));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn backs_off_until_the_maximum_elapsed_time() {
+        // Retries after 1s, 2s and 4s, the next 4s would exceed 10s
+        let (attempts, elapsed) = failures(&policy(), || Status::unavailable("down")).await;
+        assert_eq!(attempts, 4);
+        assert_eq!(elapsed, Duration::from_secs(7));
+
+        let (attempts, elapsed) = failures(&policy(), || Status::invalid_argument("bad")).await;
+        assert_eq!(attempts, 1);
+        assert_eq!(elapsed, Duration::ZERO);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn waits_for_the_delay_of_the_server() {
+        let started = Instant::now();
+        let mut throttled = true;
+        let result = policy()
+            .call(|| {
+                let result = if throttled {
+                    Err(status_with_retry_info(
+                        Code::ResourceExhausted,
+                        "slow down",
+                        Duration::from_secs(9),
+                    ))
+                } else {
+                    Ok(())
+                };
+                throttled = false;
+                async move { result }
+            })
+            .await;
+        assert!(result.is_ok());
+        assert_eq!(started.elapsed(), Duration::from_secs(9));
+
+        // A delay beyond the maximum elapsed time is not waited for
+        let status = || {
+            status_with_retry_info(
+                Code::ResourceExhausted,
+                "slow down",
+                Duration::from_secs(11),
+            )
+        };
+        assert_eq!(failures(&policy(), status).await, (1, Duration::ZERO));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn clamps_the_backoff_to_the_maximum_interval() {
+        let policy = policy()
+            .initial_interval(Duration::from_secs(3600))
+            .multiplier(f64::INFINITY)
+            .max_interval(Duration::from_secs(3))
+            .max_elapsed_time(Duration::from_secs(10));
+        let (attempts, elapsed) = failures(&policy, || Status::unavailable("down")).await;
+        assert_eq!(attempts, 4);
+        assert_eq!(elapsed, Duration::from_secs(9));
+
+        let policy = policy.max_interval(Duration::MAX).randomization_factor(1.0);
+        let (attempts, _) = failures(&policy, || Status::unavailable("down")).await;
+        assert_eq!(attempts, 1);
+    }
+}
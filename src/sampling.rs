@@ -0,0 +1,296 @@
+// Copyright 2020-2022, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A `TraceSampler` evaluates the sampler of a `TraceConfig` against spans:
+//!
+//! * `ConstantSampler` keeps or drops every span, or follows the parent's
+//!   decision, taken from the sampled flag of spans with a parent span id
+//! * `TraceIdRatioBased` keeps a span when the last 8 bytes of its trace id,
+//!   read as a big endian integer and shifted right by one bit, fall below the
+//!   ratio of 2^63, so all spans of a trace share one decision
+//! * `RateLimitingSampler` keeps at most `qps` spans per second
+//! * a config without a sampler keeps every span
+//!
+//! ```ignore
+//! let sampler = TraceSampler::new(TraceConfig {
+//!     sampler: Some(Sampler::TraceIdRatioBased(TraceIdRatioBased { sampling_ratio: 0.1 })),
+//!     ..TraceConfig::default()
+//! });
+//! sampler.retain(&mut request);
+//! ```
+
+use crate::opentelemetry::proto::collector::trace::v1::ExportTraceServiceRequest;
+use crate::opentelemetry::proto::trace::v1::constant_sampler::ConstantDecision;
+use crate::opentelemetry::proto::trace::v1::trace_config::Sampler;
+use crate::opentelemetry::proto::trace::v1::{Span, TraceConfig};
+use std::convert::TryInto;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// The outcome of a sampling decision
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Decision {
+    /// The span is kept
+    Keep,
+    /// The span is dropped
+    Drop,
+}
+
+impl Decision {
+    /// Checks if the span is kept
+    pub fn is_keep(self) -> bool {
+        self == Decision::Keep
+    }
+}
+
+impl From<bool> for Decision {
+    fn from(keep: bool) -> Self {
+        if keep {
+            Decision::Keep
+        } else {
+            Decision::Drop
+        }
+    }
+}
+
+/// Evaluates the sampler of a trace configuration
+#[derive(Debug)]
+pub struct TraceSampler {
+    config: TraceConfig,
+    bucket: Mutex<TokenBucket>,
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl TraceSampler {
+    /// Creates a sampler for the specified configuration
+    pub fn new(config: TraceConfig) -> Self {
+        let tokens = match &config.sampler {
+            Some(Sampler::RateLimitingSampler(limit)) => limit.qps.max(0) as f64,
+            _ => 0.0,
+        };
+        TraceSampler {
+            config,
+            bucket: Mutex::new(TokenBucket {
+                tokens,
+                refilled: Instant::now(),
+            }),
+        }
+    }
+
+    /// The configuration of this sampler
+    pub fn config(&self) -> &TraceConfig {
+        &self.config
+    }
+
+    /// Decides whether to keep a span, taking the parent decision from the
+    /// sampled trace flag of spans with a parent span id
+    pub fn sample(&self, span: &Span) -> Decision {
+        let parent = if span.parent_span_id.is_empty() {
+            None
+        } else {
+            Some(span.flags & SAMPLED_FLAG != 0)
+        };
+        self.sample_with_parent(span, parent)
+    }
+
+    /// Decides whether to keep a span, given the decision for its parent span
+    /// or `None` for root spans
+    pub fn sample_with_parent(&self, span: &Span, parent: Option<bool>) -> Decision {
+        match &self.config.sampler {
+            None => Decision::Keep,
+            Some(Sampler::ConstantSampler(constant)) => match constant.decision() {
+                ConstantDecision::AlwaysOff => Decision::Drop,
+                ConstantDecision::AlwaysOn => Decision::Keep,
+                ConstantDecision::AlwaysParent => parent.unwrap_or_default().into(),
+            },
+            Some(Sampler::TraceIdRatioBased(ratio)) => {
+                trace_id_ratio(&span.trace_id, ratio.sampling_ratio)
+            }
+            Some(Sampler::RateLimitingSampler(limit)) => self.rate_limit(limit.qps, Instant::now()),
+        }
+    }
+
+    /// Removes the dropped spans from an export request
+    pub fn retain(&self, request: &mut ExportTraceServiceRequest) {
+        for resource_spans in &mut request.resource_spans {
+            for scope_spans in &mut resource_spans.scope_spans {
+                scope_spans.spans.retain(|span| self.sample(span).is_keep());
+            }
+        }
+    }
+
+    // The bucket starts with `qps` tokens and refills by `qps` tokens per second
+    fn rate_limit(&self, qps: i64, now: Instant) -> Decision {
+        let qps = qps.max(0) as f64;
+        // A poisoned bucket still holds a valid token count
+        let mut bucket = match self.bucket.lock() {
+            Ok(bucket) => bucket,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let elapsed = now.saturating_duration_since(bucket.refilled).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * qps).min(qps);
+        bucket.refilled = bucket.refilled.max(now);
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Decision::Keep
+        } else {
+            Decision::Drop
+        }
+    }
+}
+
+// The sampled bit of the W3C trace flags
+const SAMPLED_FLAG: u32 = 0x01;
+
+// Same upper bound as the OpenTelemetry SDKs, spans with invalid trace ids are dropped
+fn trace_id_ratio(trace_id: &[u8], ratio: f64) -> Decision {
+    if ratio >= 1.0 {
+        return Decision::Keep;
+    }
+    if ratio <= 0.0 || ratio.is_nan() {
+        return Decision::Drop;
+    }
+    let bound = (ratio * (1_u64 << 63) as f64) as u64;
+    match trace_id.get(8..16).and_then(|bytes| bytes.try_into().ok()) {
+        Some(bytes) if trace_id.len() == 16 => (u64::from_be_bytes(bytes) >> 1 < bound).into(),
+        _ => Decision::Drop,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opentelemetry::proto::trace::v1::{ConstantSampler, RateLimitingSampler};
+    use std::time::Duration;
+
+    fn sampler(sampler: Sampler) -> TraceSampler {
+        TraceSampler::new(TraceConfig {
+            sampler: Some(sampler),
+            ..TraceConfig::default()
+        })
+    }
+
+    fn constant(decision: ConstantDecision) -> TraceSampler {
+        sampler(Sampler::ConstantSampler(ConstantSampler {
+            decision: decision as i32,
+        }))
+    }
+
+    fn rate_limited(qps: i64) -> TraceSampler {
+        sampler(Sampler::RateLimitingSampler(RateLimitingSampler { qps }))
+    }
+
+    fn child(sampled: bool) -> Span {
+        Span {
+            parent_span_id: vec![1; 8],
+            flags: if sampled { SAMPLED_FLAG } else { 0 },
+            ..Span::default()
+        }
+    }
+
+    #[test]
+    fn constant_samplers_keep_drop_or_follow_the_parent() {
+        let root = Span::default();
+        let parent = constant(ConstantDecision::AlwaysParent);
+        assert_eq!(parent.sample(&child(true)), Decision::Keep);
+        assert_eq!(parent.sample(&child(false)), Decision::Drop);
+        assert_eq!(parent.sample(&root), Decision::Drop);
+        assert_eq!(parent.sample_with_parent(&root, Some(true)), Decision::Keep);
+
+        let on = constant(ConstantDecision::AlwaysOn);
+        assert_eq!(on.sample(&child(false)), Decision::Keep);
+        let off = constant(ConstantDecision::AlwaysOff);
+        assert_eq!(off.sample(&child(true)), Decision::Drop);
+        let unset = TraceSampler::new(TraceConfig::default());
+        assert_eq!(unset.sample(&root), Decision::Keep);
+
+        let mut request = ExportTraceServiceRequest {
+            resource_spans: vec![crate::opentelemetry::proto::trace::v1::ResourceSpans {
+                scope_spans: vec![crate::opentelemetry::proto::trace::v1::ScopeSpans {
+                    spans: vec![child(true), child(false), root],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+        };
+        parent.retain(&mut request);
+        assert_eq!(
+            request.resource_spans[0].scope_spans[0].spans,
+            [child(true)]
+        );
+    }
+
+    #[test]
+    fn rate_limiter_starts_with_a_full_bucket() {
+        let sampler = rate_limited(3);
+        let start = Instant::now();
+        let decisions: Vec<_> = (0..4).map(|_| sampler.rate_limit(3, start)).collect();
+        assert_eq!(
+            decisions,
+            [
+                Decision::Keep,
+                Decision::Keep,
+                Decision::Keep,
+                Decision::Drop
+            ]
+        );
+
+        // A third of a second refills one token
+        let later = start + Duration::from_millis(400);
+        assert_eq!(sampler.rate_limit(3, later), Decision::Keep);
+        assert_eq!(sampler.rate_limit(3, later), Decision::Drop);
+
+        // The bucket holds at most `qps` tokens
+        let much_later = later + Duration::from_secs(10);
+        let kept = (0..10)
+            .filter(|_| sampler.rate_limit(3, much_later).is_keep())
+            .count();
+        assert_eq!(kept, 3);
+    }
+
+    #[test]
+    fn rate_limiter_without_a_rate_drops_everything() {
+        for qps in [0, -5] {
+            let sampler = rate_limited(qps);
+            let start = Instant::now();
+            assert_eq!(sampler.sample(&Span::default()), Decision::Drop);
+            assert_eq!(
+                sampler.rate_limit(qps, start + Duration::from_secs(60)),
+                Decision::Drop
+            );
+        }
+    }
+
+    fn trace_id(low: u64) -> Vec<u8> {
+        let mut trace_id = vec![0xff; 8];
+        trace_id.extend_from_slice(&low.to_be_bytes());
+        trace_id
+    }
+
+    #[test]
+    fn ratio_compares_the_upper_63_bits_of_the_low_8_bytes() {
+        let half = 1_u64 << 63;
+        assert_eq!(trace_id_ratio(&trace_id(half - 1), 0.5), Decision::Keep);
+        assert_eq!(trace_id_ratio(&trace_id(half), 0.5), Decision::Drop);
+        assert_eq!(trace_id_ratio(&trace_id(1), 1e-20), Decision::Drop);
+        assert_eq!(trace_id_ratio(&trace_id(u64::MAX), 1.0), Decision::Keep);
+        assert_eq!(trace_id_ratio(&trace_id(0), 0.0), Decision::Drop);
+        assert_eq!(trace_id_ratio(&[0; 8], 0.5), Decision::Drop);
+    }
+}
@@ -0,0 +1,358 @@
+// Copyright 2020-2022, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Attribute names of the OpenTelemetry semantic conventions, for resources
+//! and for spans, log records and metrics.
+//!
+//! The constants are maintained by hand and mirror a subset of the attribute
+//! registry of semantic conventions v1.26.0, see `SCHEMA_URL`. Each constant
+//! is the attribute id upper-cased with `.` replaced by `_`. To move to a new
+//! release, update `SCHEMA_URL` and the ids pinned by the tests, check every name against the `model` registry
+//! of the release at <https://github.com/open-telemetry/semantic-conventions>,
+//! and move renamed attributes into `deprecated`, naming their replacement.
+//!
+//! ```ignore
+//! let route = span
+//!     .attributes
+//!     .iter()
+//!     .find(|attribute| attribute.key == semconv::trace::HTTP_ROUTE);
+//! ```
+
+/// Schema URL of the semantic conventions release the constants mirror
+pub const SCHEMA_URL: &str = "https://opentelemetry.io/schemas/1.26.0";
+
+/// Resource attribute names
+pub mod resource {
+    /// Logical name of the service
+    pub const SERVICE_NAME: &str = "service.name";
+    /// Namespace of the service name
+    pub const SERVICE_NAMESPACE: &str = "service.namespace";
+    /// Unique id of the service instance
+    pub const SERVICE_INSTANCE_ID: &str = "service.instance.id";
+    /// Version of the service
+    pub const SERVICE_VERSION: &str = "service.version";
+
+    /// Name of the telemetry SDK
+    pub const TELEMETRY_SDK_NAME: &str = "telemetry.sdk.name";
+    /// Language of the telemetry SDK
+    pub const TELEMETRY_SDK_LANGUAGE: &str = "telemetry.sdk.language";
+    /// Version of the telemetry SDK
+    pub const TELEMETRY_SDK_VERSION: &str = "telemetry.sdk.version";
+
+    /// Name of the deployment environment, such as `production`
+    pub const DEPLOYMENT_ENVIRONMENT: &str = "deployment.environment";
+
+    /// Unique id of the host
+    pub const HOST_ID: &str = "host.id";
+    /// Name of the host
+    pub const HOST_NAME: &str = "host.name";
+    /// Type of the host
+    pub const HOST_TYPE: &str = "host.type";
+    /// CPU architecture of the host
+    pub const HOST_ARCH: &str = "host.arch";
+
+    /// Operating system type
+    pub const OS_TYPE: &str = "os.type";
+    /// Operating system description
+    pub const OS_DESCRIPTION: &str = "os.description";
+    /// Operating system version
+    pub const OS_VERSION: &str = "os.version";
+
+    /// Process id
+    pub const PROCESS_PID: &str = "process.pid";
+    /// Name of the process executable
+    pub const PROCESS_EXECUTABLE_NAME: &str = "process.executable.name";
+    /// Path of the process executable
+    pub const PROCESS_EXECUTABLE_PATH: &str = "process.executable.path";
+    /// Command line of the process
+    pub const PROCESS_COMMAND_LINE: &str = "process.command_line";
+    /// Owner of the process
+    pub const PROCESS_OWNER: &str = "process.owner";
+    /// Name of the process runtime
+    pub const PROCESS_RUNTIME_NAME: &str = "process.runtime.name";
+    /// Version of the process runtime
+    pub const PROCESS_RUNTIME_VERSION: &str = "process.runtime.version";
+
+    /// Container id
+    pub const CONTAINER_ID: &str = "container.id";
+    /// Container name
+    pub const CONTAINER_NAME: &str = "container.name";
+    /// Name of the container image
+    pub const CONTAINER_IMAGE_NAME: &str = "container.image.name";
+    /// Tags of the container image
+    pub const CONTAINER_IMAGE_TAGS: &str = "container.image.tags";
+
+    /// Name of the Kubernetes cluster
+    pub const K8S_CLUSTER_NAME: &str = "k8s.cluster.name";
+    /// Name of the Kubernetes node
+    pub const K8S_NODE_NAME: &str = "k8s.node.name";
+    /// Name of the Kubernetes namespace
+    pub const K8S_NAMESPACE_NAME: &str = "k8s.namespace.name";
+    /// Name of the Kubernetes pod
+    pub const K8S_POD_NAME: &str = "k8s.pod.name";
+    /// UID of the Kubernetes pod
+    pub const K8S_POD_UID: &str = "k8s.pod.uid";
+    /// Name of the Kubernetes deployment
+    pub const K8S_DEPLOYMENT_NAME: &str = "k8s.deployment.name";
+
+    /// Cloud provider, such as `aws`
+    pub const CLOUD_PROVIDER: &str = "cloud.provider";
+    /// Cloud platform, such as `aws_ec2`
+    pub const CLOUD_PLATFORM: &str = "cloud.platform";
+    /// Cloud account id
+    pub const CLOUD_ACCOUNT_ID: &str = "cloud.account.id";
+    /// Cloud region
+    pub const CLOUD_REGION: &str = "cloud.region";
+    /// Cloud availability zone
+    pub const CLOUD_AVAILABILITY_ZONE: &str = "cloud.availability_zone";
+}
+
+/// Span, log record and metric attribute names
+pub mod trace {
+    /// HTTP request method
+    pub const HTTP_REQUEST_METHOD: &str = "http.request.method";
+    /// HTTP response status code
+    pub const HTTP_RESPONSE_STATUS_CODE: &str = "http.response.status_code";
+    /// Matched route template
+    pub const HTTP_ROUTE: &str = "http.route";
+    /// Full request URL
+    pub const URL_FULL: &str = "url.full";
+    /// Path of the request URL
+    pub const URL_PATH: &str = "url.path";
+    /// Query string of the request URL
+    pub const URL_QUERY: &str = "url.query";
+    /// URI scheme
+    pub const URL_SCHEME: &str = "url.scheme";
+    /// Value of the `User-Agent` header
+    pub const USER_AGENT_ORIGINAL: &str = "user_agent.original";
+
+    /// Server host name or address
+    pub const SERVER_ADDRESS: &str = "server.address";
+    /// Server port
+    pub const SERVER_PORT: &str = "server.port";
+    /// Client address
+    pub const CLIENT_ADDRESS: &str = "client.address";
+    /// Client port
+    pub const CLIENT_PORT: &str = "client.port";
+    /// Transport protocol, such as `tcp`
+    pub const NETWORK_TRANSPORT: &str = "network.transport";
+    /// Application protocol name, such as `http`
+    pub const NETWORK_PROTOCOL_NAME: &str = "network.protocol.name";
+    /// Application protocol version, such as `1.1`
+    pub const NETWORK_PROTOCOL_VERSION: &str = "network.protocol.version";
+
+    /// Database system, such as `postgresql`
+    pub const DB_SYSTEM: &str = "db.system";
+    /// Database name
+    pub const DB_NAMESPACE: &str = "db.namespace";
+    /// Database query text
+    pub const DB_QUERY_TEXT: &str = "db.query.text";
+    /// Database operation, such as `SELECT`
+    pub const DB_OPERATION_NAME: &str = "db.operation.name";
+
+    /// RPC system, such as `grpc`
+    pub const RPC_SYSTEM: &str = "rpc.system";
+    /// Full name of the RPC service
+    pub const RPC_SERVICE: &str = "rpc.service";
+    /// Name of the RPC method
+    pub const RPC_METHOD: &str = "rpc.method";
+    /// gRPC status code
+    pub const RPC_GRPC_STATUS_CODE: &str = "rpc.grpc.status_code";
+
+    /// Messaging system, such as `kafka`
+    pub const MESSAGING_SYSTEM: &str = "messaging.system";
+    /// Message destination name
+    pub const MESSAGING_DESTINATION_NAME: &str = "messaging.destination.name";
+    /// Messaging operation type, such as `receive`
+    pub const MESSAGING_OPERATION_TYPE: &str = "messaging.operation.type";
+
+    /// Type of an exception
+    pub const EXCEPTION_TYPE: &str = "exception.type";
+    /// Message of an exception
+    pub const EXCEPTION_MESSAGE: &str = "exception.message";
+    /// Stack trace of an exception
+    pub const EXCEPTION_STACKTRACE: &str = "exception.stacktrace";
+    /// Name of span events recording exceptions
+    pub const EXCEPTION_EVENT_NAME: &str = "exception";
+
+    /// Function name of the code
+    pub const CODE_FUNCTION: &str = "code.function";
+    /// Namespace of the code
+    pub const CODE_NAMESPACE: &str = "code.namespace";
+    /// Source file of the code
+    pub const CODE_FILEPATH: &str = "code.filepath";
+    /// Line number of the code
+    pub const CODE_LINENO: &str = "code.lineno";
+
+    /// Id of the end user
+    pub const ENDUSER_ID: &str = "enduser.id";
+    /// Id of the thread
+    pub const THREAD_ID: &str = "thread.id";
+    /// Name of the thread
+    pub const THREAD_NAME: &str = "thread.name";
+}
+
+/// Attribute names replaced in earlier semantic conventions releases, still
+/// sent by older instrumentations
+pub mod deprecated {
+    /// HTTP request method, replaced by `http.request.method`
+    pub const HTTP_METHOD: &str = "http.method";
+    /// HTTP response status code, replaced by `http.response.status_code`
+    pub const HTTP_STATUS_CODE: &str = "http.status_code";
+    /// Full request URL, replaced by `url.full`
+    pub const HTTP_URL: &str = "http.url";
+    /// Request target, replaced by `url.path` and `url.query`
+    pub const HTTP_TARGET: &str = "http.target";
+    /// URI scheme, replaced by `url.scheme`
+    pub const HTTP_SCHEME: &str = "http.scheme";
+    /// Value of the `User-Agent` header, replaced by `user_agent.original`
+    pub const HTTP_USER_AGENT: &str = "http.user_agent";
+
+    /// Remote peer name, replaced by `server.address`
+    pub const NET_PEER_NAME: &str = "net.peer.name";
+    /// Remote peer port, replaced by `server.port`
+    pub const NET_PEER_PORT: &str = "net.peer.port";
+    /// Transport protocol, replaced by `network.transport`
+    pub const NET_TRANSPORT: &str = "net.transport";
+
+    /// Database name, replaced by `db.namespace`
+    pub const DB_NAME: &str = "db.name";
+    /// Database statement, replaced by `db.query.text`
+    pub const DB_STATEMENT: &str = "db.statement";
+    /// Database operation, replaced by `db.operation.name`
+    pub const DB_OPERATION: &str = "db.operation";
+
+    /// Message destination name, replaced by `messaging.destination.name`
+    pub const MESSAGING_DESTINATION: &str = "messaging.destination";
+    /// Messaging operation, replaced by `messaging.operation.type`
+    pub const MESSAGING_OPERATION: &str = "messaging.operation";
+
+    /// Tag of the container image, replaced by `container.image.tags`
+    pub const CONTAINER_IMAGE_TAG: &str = "container.image.tag";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Pairs each constant with its attribute id, as a literal that is
+    // checked against the registry when moving to a new release
+    macro_rules! pinned {
+        ($($module:ident::$name:ident => $id:literal,)*) => {
+            vec![$((stringify!($name), $module::$name, $id)),*]
+        };
+    }
+
+    #[test]
+    fn names_match_the_registry() {
+        let pinned = pinned! {
+            resource::SERVICE_NAME => "service.name",
+            resource::SERVICE_NAMESPACE => "service.namespace",
+            resource::SERVICE_INSTANCE_ID => "service.instance.id",
+            resource::SERVICE_VERSION => "service.version",
+            resource::TELEMETRY_SDK_NAME => "telemetry.sdk.name",
+            resource::TELEMETRY_SDK_LANGUAGE => "telemetry.sdk.language",
+            resource::TELEMETRY_SDK_VERSION => "telemetry.sdk.version",
+            resource::DEPLOYMENT_ENVIRONMENT => "deployment.environment",
+            resource::HOST_ID => "host.id",
+            resource::HOST_NAME => "host.name",
+            resource::HOST_TYPE => "host.type",
+            resource::HOST_ARCH => "host.arch",
+            resource::OS_TYPE => "os.type",
+            resource::OS_DESCRIPTION => "os.description",
+            resource::OS_VERSION => "os.version",
+            resource::PROCESS_PID => "process.pid",
+            resource::PROCESS_EXECUTABLE_NAME => "process.executable.name",
+            resource::PROCESS_EXECUTABLE_PATH => "process.executable.path",
+            resource::PROCESS_COMMAND_LINE => "process.command_line",
+            resource::PROCESS_OWNER => "process.owner",
+            resource::PROCESS_RUNTIME_NAME => "process.runtime.name",
+            resource::PROCESS_RUNTIME_VERSION => "process.runtime.version",
+            resource::CONTAINER_ID => "container.id",
+            resource::CONTAINER_NAME => "container.name",
+            resource::CONTAINER_IMAGE_NAME => "container.image.name",
+            resource::CONTAINER_IMAGE_TAGS => "container.image.tags",
+            resource::K8S_CLUSTER_NAME => "k8s.cluster.name",
+            resource::K8S_NODE_NAME => "k8s.node.name",
+            resource::K8S_NAMESPACE_NAME => "k8s.namespace.name",
+            resource::K8S_POD_NAME => "k8s.pod.name",
+            resource::K8S_POD_UID => "k8s.pod.uid",
+            resource::K8S_DEPLOYMENT_NAME => "k8s.deployment.name",
+            resource::CLOUD_PROVIDER => "cloud.provider",
+            resource::CLOUD_PLATFORM => "cloud.platform",
+            resource::CLOUD_ACCOUNT_ID => "cloud.account.id",
+            resource::CLOUD_REGION => "cloud.region",
+            resource::CLOUD_AVAILABILITY_ZONE => "cloud.availability_zone",
+            trace::HTTP_REQUEST_METHOD => "http.request.method",
+            trace::HTTP_RESPONSE_STATUS_CODE => "http.response.status_code",
+            trace::HTTP_ROUTE => "http.route",
+            trace::URL_FULL => "url.full",
+            trace::URL_PATH => "url.path",
+            trace::URL_QUERY => "url.query",
+            trace::URL_SCHEME => "url.scheme",
+            trace::USER_AGENT_ORIGINAL => "user_agent.original",
+            trace::SERVER_ADDRESS => "server.address",
+            trace::SERVER_PORT => "server.port",
+            trace::CLIENT_ADDRESS => "client.address",
+            trace::CLIENT_PORT => "client.port",
+            trace::NETWORK_TRANSPORT => "network.transport",
+            trace::NETWORK_PROTOCOL_NAME => "network.protocol.name",
+            trace::NETWORK_PROTOCOL_VERSION => "network.protocol.version",
+            trace::DB_SYSTEM => "db.system",
+            trace::DB_NAMESPACE => "db.namespace",
+            trace::DB_QUERY_TEXT => "db.query.text",
+            trace::DB_OPERATION_NAME => "db.operation.name",
+            trace::RPC_SYSTEM => "rpc.system",
+            trace::RPC_SERVICE => "rpc.service",
+            trace::RPC_METHOD => "rpc.method",
+            trace::RPC_GRPC_STATUS_CODE => "rpc.grpc.status_code",
+            trace::MESSAGING_SYSTEM => "messaging.system",
+            trace::MESSAGING_DESTINATION_NAME => "messaging.destination.name",
+            trace::MESSAGING_OPERATION_TYPE => "messaging.operation.type",
+            trace::EXCEPTION_TYPE => "exception.type",
+            trace::EXCEPTION_MESSAGE => "exception.message",
+            trace::EXCEPTION_STACKTRACE => "exception.stacktrace",
+            trace::EXCEPTION_EVENT_NAME => "exception",
+            trace::CODE_FUNCTION => "code.function",
+            trace::CODE_NAMESPACE => "code.namespace",
+            trace::CODE_FILEPATH => "code.filepath",
+            trace::CODE_LINENO => "code.lineno",
+            trace::ENDUSER_ID => "enduser.id",
+            trace::THREAD_ID => "thread.id",
+            trace::THREAD_NAME => "thread.name",
+            deprecated::HTTP_METHOD => "http.method",
+            deprecated::HTTP_STATUS_CODE => "http.status_code",
+            deprecated::HTTP_URL => "http.url",
+            deprecated::HTTP_TARGET => "http.target",
+            deprecated::HTTP_SCHEME => "http.scheme",
+            deprecated::HTTP_USER_AGENT => "http.user_agent",
+            deprecated::NET_PEER_NAME => "net.peer.name",
+            deprecated::NET_PEER_PORT => "net.peer.port",
+            deprecated::NET_TRANSPORT => "net.transport",
+            deprecated::DB_NAME => "db.name",
+            deprecated::DB_STATEMENT => "db.statement",
+            deprecated::DB_OPERATION => "db.operation",
+            deprecated::MESSAGING_DESTINATION => "messaging.destination",
+            deprecated::MESSAGING_OPERATION => "messaging.operation",
+            deprecated::CONTAINER_IMAGE_TAG => "container.image.tag",
+        };
+        for (name, value, id) in pinned {
+            assert_eq!(value, id, "{}", name);
+            if name != "EXCEPTION_EVENT_NAME" {
+                assert_eq!(name, id.to_uppercase().replace('.', "_"), "{}", id);
+            }
+        }
+        assert_eq!(SCHEMA_URL, "https://opentelemetry.io/schemas/1.26.0");
+    }
+}
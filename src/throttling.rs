@@ -0,0 +1,271 @@
+// Copyright 2020-2022, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The OTLP specification asks servers that cannot keep up to answer with
+//! `RESOURCE_EXHAUSTED` or `UNAVAILABLE` and a `google.rpc.RetryInfo` detail,
+//! so clients back off for the given delay before retrying. Forwarders select
+//! how they react to a full bounded channel with an `OverflowPolicy`, and reply
+//! `UNAVAILABLE` once the receiving end of their channel has been dropped.
+//!
+//! ```ignore
+//! let (tx, rx) = bounded(128);
+//! let svc = tremor_otelapis::logs::OtelLogsServiceForwarder::with_sender(tx)
+//!     .with_overflow_policy(OverflowPolicy::Reject {
+//!         retry_after: Duration::from_secs(1),
+//!     });
+//! ```
+
+use async_channel::Receiver;
+use prost::Message;
+use std::convert::TryFrom;
+use std::time::Duration;
+use tonic::{Code, Status};
+
+/// Type URL of `google.rpc.RetryInfo` status details
+pub const RETRY_INFO_TYPE_URL: &str = "type.googleapis.com/google.rpc.RetryInfo";
+
+/// Retry delay suggested when a forwarder channel is closed
+pub const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+/// The `google.rpc.RetryInfo` message telling clients how long to back off
+#[derive(Clone, PartialEq, Message)]
+pub struct RetryInfo {
+    /// The minimum delay before the client retries
+    #[prost(message, optional, tag = "1")]
+    pub retry_delay: Option<prost_types::Duration>,
+}
+
+// The `google.rpc.Status` message carried in the `grpc-status-details-bin` trailer
+#[derive(Clone, PartialEq, Message)]
+struct StatusDetails {
+    #[prost(int32, tag = "1")]
+    code: i32,
+    #[prost(string, tag = "2")]
+    message: String,
+    #[prost(message, repeated, tag = "3")]
+    details: Vec<prost_types::Any>,
+}
+
+/// Creates a status with a `google.rpc.RetryInfo` detail
+pub fn status_with_retry_info(code: Code, message: &str, retry_after: Duration) -> Status {
+    let retry_info = RetryInfo {
+        retry_delay: Some(prost_types::Duration {
+            seconds: i64::try_from(retry_after.as_secs()).unwrap_or(i64::MAX),
+            nanos: retry_after.subsec_nanos() as i32,
+        }),
+    };
+    let details = StatusDetails {
+        code: code as i32,
+        message: message.to_string(),
+        details: vec![prost_types::Any {
+            type_url: RETRY_INFO_TYPE_URL.to_string(),
+            value: retry_info.encode_to_vec(),
+        }],
+    };
+    Status::with_details(code, message, details.encode_to_vec().into())
+}
+
+/// The retry delay of a status carrying a `google.rpc.RetryInfo` detail
+pub fn retry_after(status: &Status) -> Option<Duration> {
+    let details = StatusDetails::decode(status.details()).ok()?;
+    let detail = details
+        .details
+        .iter()
+        .find(|detail| detail.type_url == RETRY_INFO_TYPE_URL)?;
+    let delay = RetryInfo::decode(detail.value.as_slice())
+        .ok()?
+        .retry_delay?;
+    Some(Duration::new(
+        u64::try_from(delay.seconds).ok()?,
+        u32::try_from(delay.nanos).ok()?,
+    ))
+}
+
+/// How a forwarder reacts when its bounded channel is full
+pub enum OverflowPolicy<T> {
+    /// Waits until the channel has capacity
+    Block,
+    /// Waits up to `timeout` for capacity, then replies `RESOURCE_EXHAUSTED`
+    BlockWithTimeout {
+        /// The maximum time to wait for capacity
+        timeout: Duration,
+        /// The delay suggested to the client
+        retry_after: Duration,
+    },
+    /// Replies `RESOURCE_EXHAUSTED` immediately
+    Reject {
+        /// The delay suggested to the client
+        retry_after: Duration,
+    },
+    /// Discards the oldest queued message, received from the channel's receiver,
+    /// to make room for the new one
+    ///
+    /// The receiver is a clone of the consumer's receiver. Once it is the only
+    /// receiver left the consumer is gone, and the forwarder replies
+    /// `UNAVAILABLE`. Replies `RESOURCE_EXHAUSTED` if the channel is still full
+    /// after a few attempts, as when other senders refill it or the receiver
+    /// belongs to another channel.
+    DropOldest(Receiver<T>),
+}
+
+#[cfg(any(
+    feature = "otel-trace",
+    feature = "otel-logs",
+    feature = "otel-metrics"
+))]
+impl<T> OverflowPolicy<T> {
+    fn retry_after(&self) -> Duration {
+        match self {
+            OverflowPolicy::BlockWithTimeout { retry_after, .. }
+            | OverflowPolicy::Reject { retry_after } => *retry_after,
+            OverflowPolicy::Block | OverflowPolicy::DropOldest(_) => DEFAULT_RETRY_AFTER,
+        }
+    }
+}
+
+impl<T> Default for OverflowPolicy<T> {
+    fn default() -> Self {
+        OverflowPolicy::Block
+    }
+}
+
+impl<T> Clone for OverflowPolicy<T> {
+    fn clone(&self) -> Self {
+        match self {
+            OverflowPolicy::Block => OverflowPolicy::Block,
+            OverflowPolicy::BlockWithTimeout {
+                timeout,
+                retry_after,
+            } => OverflowPolicy::BlockWithTimeout {
+                timeout: *timeout,
+                retry_after: *retry_after,
+            },
+            OverflowPolicy::Reject { retry_after } => OverflowPolicy::Reject {
+                retry_after: *retry_after,
+            },
+            OverflowPolicy::DropOldest(receiver) => OverflowPolicy::DropOldest(receiver.clone()),
+        }
+    }
+}
+
+// Sends a message on a forwarder channel according to the overflow policy
+#[cfg(any(
+    feature = "otel-trace",
+    feature = "otel-logs",
+    feature = "otel-metrics"
+))]
+pub(crate) async fn dispatch<T>(
+    channel: &async_channel::Sender<T>,
+    policy: &OverflowPolicy<T>,
+    message: T,
+    signal: &str,
+) -> Result<(), Status> {
+    use async_channel::TrySendError;
+    let closed = || {
+        status_with_retry_info(
+            Code::Unavailable,
+            &format!("{} gRPC forwarder channel is closed", signal),
+            policy.retry_after(),
+        )
+    };
+    let full = || {
+        status_with_retry_info(
+            Code::ResourceExhausted,
+            &format!("{} gRPC forwarder channel is full", signal),
+            policy.retry_after(),
+        )
+    };
+    match policy {
+        OverflowPolicy::Block => channel.send(message).await.map_err(|_| closed()),
+        OverflowPolicy::BlockWithTimeout { timeout, .. } => {
+            match tokio::time::timeout(*timeout, channel.send(message)).await {
+                Ok(sent) => sent.map_err(|_| closed()),
+                Err(_) => Err(full()),
+            }
+        }
+        OverflowPolicy::Reject { .. } => channel.try_send(message).map_err(|e| match e {
+            TrySendError::Full(_) => full(),
+            TrySendError::Closed(_) => closed(),
+        }),
+        OverflowPolicy::DropOldest(receiver) => {
+            // The policy's clone keeps the channel open after the consumer dropped its receiver
+            if receiver.receiver_count() <= 1 {
+                return Err(closed());
+            }
+            // Bounds the retries when the receiver does not drain this channel
+            const ATTEMPTS: usize = 8;
+            let mut message = message;
+            for _ in 0..ATTEMPTS {
+                match channel.try_send(message) {
+                    Ok(()) => return Ok(()),
+                    Err(TrySendError::Full(rejected)) => {
+                        // Another sender may refill the slot, so retry a few times
+                        let _oldest = receiver.try_recv();
+                        message = rejected;
+                    }
+                    Err(TrySendError::Closed(_)) => return Err(closed()),
+                }
+            }
+            Err(full())
+        }
+    }
+}
+
+#[cfg(all(test, feature = "otel-trace"))]
+mod tests {
+    use super::*;
+    use async_channel::bounded;
+
+    #[tokio::test]
+    async fn drop_oldest_makes_room_for_new_messages() {
+        let (tx, rx) = bounded(2);
+        let policy = OverflowPolicy::DropOldest(rx.clone());
+        for message in 0..4 {
+            assert!(dispatch(&tx, &policy, message, "Test").await.is_ok());
+        }
+        assert_eq!(rx.try_recv().ok(), Some(2));
+        assert_eq!(rx.try_recv().ok(), Some(3));
+    }
+
+    #[tokio::test]
+    async fn drop_oldest_gives_up_on_a_foreign_receiver() {
+        let (tx, rx) = bounded(1);
+        let (_other_tx, other_rx) = bounded(1);
+        let _other_consumer = other_rx.clone();
+        let policy = OverflowPolicy::DropOldest(other_rx);
+        assert!(dispatch(&tx, &policy, 1, "Test").await.is_ok());
+        let status = dispatch(&tx, &policy, 2, "Test").await.err();
+        assert_eq!(
+            status.as_ref().map(Status::code),
+            Some(Code::ResourceExhausted)
+        );
+        assert_eq!(
+            status.as_ref().and_then(retry_after),
+            Some(DEFAULT_RETRY_AFTER)
+        );
+        assert_eq!(rx.try_recv().ok(), Some(1));
+    }
+
+    #[tokio::test]
+    async fn drop_oldest_replies_unavailable_without_a_consumer() {
+        let (tx, rx) = bounded(2);
+        let policy = OverflowPolicy::DropOldest(rx.clone());
+        assert!(dispatch(&tx, &policy, 1, "Test").await.is_ok());
+        drop(rx);
+        for message in 2..5 {
+            let status = dispatch(&tx, &policy, message, "Test").await.err();
+            assert_eq!(status.as_ref().map(Status::code), Some(Code::Unavailable));
+        }
+    }
+}
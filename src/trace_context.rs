@@ -0,0 +1,726 @@
+// Copyright 2020-2022, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Typed trace and span identifiers and the W3C Trace Context `traceparent`
+//! and `tracestate` headers.
+//!
+//! The generated `Span`, `Link` and `LogRecord` messages carry their
+//! identifiers as raw bytes, the accessors added here only return
+//! identifiers of the correct length that are not all zero.
+//!
+//! ```ignore
+//! let parent: TraceParent = headers["traceparent"].to_str()?.parse()?;
+//! let span = SpanBuilder::new("GET /users").remote_parent(&parent).build();
+//! assert_eq!(span.trace_id(), Some(parent.trace_id));
+//! ```
+
+use std::collections::hash_map::RandomState;
+use std::convert::TryFrom;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::str::FromStr;
+
+/// Maximum number of entries of a `tracestate` header
+pub const MAX_TRACE_STATE_ENTRIES: usize = 32;
+
+/// Trace flag of sampled traces
+pub const FLAG_SAMPLED: u8 = 0x01;
+
+/// The error of parsing an identifier or a trace context header
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// An identifier of the wrong length, in bytes
+    Length {
+        /// The expected length
+        expected: usize,
+        /// The actual length
+        found: usize,
+    },
+    /// An identifier that is not hex encoded
+    Hex,
+    /// A malformed `traceparent` header
+    TraceParent(&'static str),
+    /// A malformed `tracestate` header
+    TraceState(&'static str),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Length { expected, found } => write!(
+                f,
+                "Expected an identifier of {} bytes, found {} bytes",
+                expected, found
+            ),
+            Error::Hex => write!(f, "Identifier is not hex encoded"),
+            Error::TraceParent(reason) => write!(f, "Invalid traceparent: {}", reason),
+            Error::TraceState(reason) => write!(f, "Invalid tracestate: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+// Identifiers of a fixed length, formatted as lowercase hex
+macro_rules! id {
+    ($(#[$doc:meta])* $name:ident, $len:literal) => {
+        $(#[$doc])*
+        #[derive(Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub struct $name([u8; $len]);
+
+        impl $name {
+            /// The all zero, invalid identifier
+            pub const INVALID: $name = $name([0; $len]);
+
+            /// Creates an identifier from its bytes
+            pub const fn from_bytes(bytes: [u8; $len]) -> Self {
+                $name(bytes)
+            }
+
+            /// Creates a random, valid identifier
+            pub fn random() -> Self {
+                let mut bytes = [0; $len];
+                random_bytes(&mut bytes);
+                $name(bytes)
+            }
+
+            /// Parses an identifier from hex
+            pub fn from_hex(hex: &str) -> Result<Self, Error> {
+                let mut bytes = [0; $len];
+                decode_hex(hex, &mut bytes)?;
+                Ok($name(bytes))
+            }
+
+            /// The bytes of the identifier
+            pub fn to_bytes(self) -> [u8; $len] {
+                self.0
+            }
+
+            /// The bytes of the identifier
+            pub fn as_bytes(&self) -> &[u8] {
+                &self.0
+            }
+
+            /// Checks if the identifier is not all zero
+            pub fn is_valid(&self) -> bool {
+                self.0.iter().any(|b| *b != 0)
+            }
+
+            // The identifier in a message, if it has the correct length and is valid
+            #[cfg(any(feature = "otel-trace", feature = "otel-logs"))]
+            fn from_message(bytes: &[u8]) -> Option<Self> {
+                Self::try_from(bytes).ok().filter(Self::is_valid)
+            }
+        }
+
+        impl From<[u8; $len]> for $name {
+            fn from(bytes: [u8; $len]) -> Self {
+                $name(bytes)
+            }
+        }
+
+        impl From<$name> for Vec<u8> {
+            fn from(id: $name) -> Self {
+                id.0.to_vec()
+            }
+        }
+
+        impl TryFrom<&[u8]> for $name {
+            type Error = Error;
+
+            fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+                let mut id = [0; $len];
+                if bytes.len() != $len {
+                    return Err(Error::Length {
+                        expected: $len,
+                        found: bytes.len(),
+                    });
+                }
+                id.copy_from_slice(bytes);
+                Ok($name(id))
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = Error;
+
+            fn from_str(hex: &str) -> Result<Self, Self::Err> {
+                Self::from_hex(hex)
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                for b in &self.0 {
+                    write!(f, "{:02x}", b)?;
+                }
+                Ok(())
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}({})", stringify!($name), self)
+            }
+        }
+    };
+}
+
+id!(
+    /// A 16 byte trace identifier
+    TraceId,
+    16
+);
+
+id!(
+    /// An 8 byte span identifier
+    SpanId,
+    8
+);
+
+fn random_bytes(bytes: &mut [u8]) {
+    for chunk in bytes.chunks_mut(8) {
+        // A fresh `RandomState` is randomly keyed
+        let random = RandomState::new().build_hasher().finish().to_be_bytes();
+        chunk.copy_from_slice(&random[..chunk.len()]);
+    }
+    if bytes.iter().all(|b| *b == 0) {
+        bytes[bytes.len() - 1] = 1;
+    }
+}
+
+fn decode_hex(hex: &str, bytes: &mut [u8]) -> Result<(), Error> {
+    if hex.len() % 2 != 0 {
+        return Err(Error::Hex);
+    }
+    if hex.len() != bytes.len() * 2 {
+        return Err(Error::Length {
+            expected: bytes.len(),
+            found: hex.len() / 2,
+        });
+    }
+    for (byte, pair) in bytes.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        *byte = nibble(pair[0])? << 4 | nibble(pair[1])?;
+    }
+    Ok(())
+}
+
+fn nibble(digit: u8) -> Result<u8, Error> {
+    match digit {
+        b'0'..=b'9' => Ok(digit - b'0'),
+        b'a'..=b'f' => Ok(digit - b'a' + 10),
+        b'A'..=b'F' => Ok(digit - b'A' + 10),
+        _ => Err(Error::Hex),
+    }
+}
+
+fn is_lower_hex(s: &str) -> bool {
+    s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+/// A W3C `traceparent` header
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TraceParent {
+    /// The trace of the parent span
+    pub trace_id: TraceId,
+    /// The parent span
+    pub parent_id: SpanId,
+    /// The trace flags
+    pub flags: u8,
+}
+
+impl TraceParent {
+    /// Creates a `traceparent` for a span
+    pub fn new(trace_id: TraceId, parent_id: SpanId, flags: u8) -> Self {
+        TraceParent {
+            trace_id,
+            parent_id,
+            flags,
+        }
+    }
+
+    /// Checks if the trace is sampled
+    pub fn sampled(&self) -> bool {
+        self.flags & FLAG_SAMPLED != 0
+    }
+}
+
+impl FromStr for TraceParent {
+    type Err = Error;
+
+    /// Parses a `traceparent` header, future versions are parsed as version 00
+    fn from_str(header: &str) -> Result<Self, Self::Err> {
+        let header = header.trim();
+        let mut fields = header.split('-');
+        let version = fields.next().unwrap_or_default();
+        if version.len() != 2 || !is_lower_hex(version) {
+            return Err(Error::TraceParent("malformed version"));
+        }
+        if version == "ff" {
+            return Err(Error::TraceParent("unsupported version"));
+        }
+        let trace_id = fields.next().unwrap_or_default();
+        let parent_id = fields.next().unwrap_or_default();
+        let flags = fields.next().unwrap_or_default();
+        if version == "00" && fields.next().is_some() {
+            return Err(Error::TraceParent("trailing fields"));
+        }
+        if trace_id.len() != 32 || !is_lower_hex(trace_id) {
+            return Err(Error::TraceParent("malformed trace id"));
+        }
+        if parent_id.len() != 16 || !is_lower_hex(parent_id) {
+            return Err(Error::TraceParent("malformed parent id"));
+        }
+        if flags.len() != 2 || !is_lower_hex(flags) {
+            return Err(Error::TraceParent("malformed flags"));
+        }
+        let trace_id = TraceId::from_hex(trace_id)?;
+        let parent_id = SpanId::from_hex(parent_id)?;
+        if !trace_id.is_valid() {
+            return Err(Error::TraceParent("all zero trace id"));
+        }
+        if !parent_id.is_valid() {
+            return Err(Error::TraceParent("all zero parent id"));
+        }
+        let mut flag_bytes = [0; 1];
+        decode_hex(flags, &mut flag_bytes)?;
+        Ok(TraceParent {
+            trace_id,
+            parent_id,
+            flags: flag_bytes[0],
+        })
+    }
+}
+
+impl fmt::Display for TraceParent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "00-{}-{}-{:02x}",
+            self.trace_id, self.parent_id, self.flags
+        )
+    }
+}
+
+/// A W3C `tracestate` header, the most recently updated entry first
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TraceState {
+    entries: Vec<(String, String)>,
+}
+
+impl TraceState {
+    /// Creates an empty trace state
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The value of a key
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(known, _)| known == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Sets the value of a key and moves it to the front, dropping the last
+    /// entry when the state is full
+    pub fn insert<K: Into<String>, V: Into<String>>(
+        &mut self,
+        key: K,
+        value: V,
+    ) -> Result<(), Error> {
+        let key = key.into();
+        let value = value.into();
+        if !is_valid_key(&key) {
+            return Err(Error::TraceState("malformed key"));
+        }
+        if !is_valid_value(&value) {
+            return Err(Error::TraceState("malformed value"));
+        }
+        self.remove(&key);
+        self.entries.insert(0, (key, value));
+        self.entries.truncate(MAX_TRACE_STATE_ENTRIES);
+        Ok(())
+    }
+
+    /// Removes a key, returning its value
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        let index = self.entries.iter().position(|(known, _)| known == key)?;
+        Some(self.entries.remove(index).1)
+    }
+
+    /// The entries in header order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    /// The number of entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Checks if there are no entries
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl FromStr for TraceState {
+    type Err = Error;
+
+    /// Parses a `tracestate` header, failing on malformed or duplicate entries
+    fn from_str(header: &str) -> Result<Self, Self::Err> {
+        let mut entries: Vec<(String, String)> = Vec::new();
+        for member in header.split(',') {
+            let member = member.trim_matches(|c| c == ' ' || c == '\t');
+            if member.is_empty() {
+                continue;
+            }
+            let (key, value) = match member.find('=') {
+                Some(index) => (&member[..index], &member[index + 1..]),
+                None => return Err(Error::TraceState("entry without value")),
+            };
+            if !is_valid_key(key) {
+                return Err(Error::TraceState("malformed key"));
+            }
+            if !is_valid_value(value) {
+                return Err(Error::TraceState("malformed value"));
+            }
+            if entries.iter().any(|(known, _)| known == key) {
+                return Err(Error::TraceState("duplicate key"));
+            }
+            entries.push((key.to_string(), value.to_string()));
+        }
+        if entries.len() > MAX_TRACE_STATE_ENTRIES {
+            return Err(Error::TraceState("too many entries"));
+        }
+        Ok(TraceState { entries })
+    }
+}
+
+impl fmt::Display for TraceState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, (key, value)) in self.entries.iter().enumerate() {
+            if index > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{}={}", key, value)?;
+        }
+        Ok(())
+    }
+}
+
+fn is_key_char(b: u8) -> bool {
+    matches!(b, b'a'..=b'z' | b'0'..=b'9' | b'_' | b'-' | b'*' | b'/')
+}
+
+// `simple-key` or `tenant-id@system-id`
+fn is_valid_key(key: &str) -> bool {
+    let bytes = key.as_bytes();
+    match key.find('@') {
+        None => {
+            !bytes.is_empty()
+                && bytes.len() <= 256
+                && bytes[0].is_ascii_lowercase()
+                && bytes.iter().all(|b| is_key_char(*b))
+        }
+        Some(at) => {
+            let (tenant, system) = (&bytes[..at], &bytes[at + 1..]);
+            !tenant.is_empty()
+                && tenant.len() <= 241
+                && (tenant[0].is_ascii_lowercase() || tenant[0].is_ascii_digit())
+                && tenant.iter().all(|b| is_key_char(*b))
+                && !system.is_empty()
+                && system.len() <= 14
+                && system[0].is_ascii_lowercase()
+                && system.iter().all(|b| is_key_char(*b))
+        }
+    }
+}
+
+// Printable ASCII except `,` and `=`, not ending in a space
+fn is_valid_value(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= 256
+        && !value.ends_with(' ')
+        && value
+            .bytes()
+            .all(|b| (0x20..=0x7e).contains(&b) && b != b',' && b != b'=')
+}
+
+#[cfg(feature = "otel-trace")]
+mod trace {
+    use super::{SpanId, TraceId, TraceParent};
+    use crate::opentelemetry::proto::trace::v1::span::Link;
+    use crate::opentelemetry::proto::trace::v1::{Span, SpanFlags};
+
+    impl Span {
+        /// The trace id, if it is valid
+        pub fn trace_id(&self) -> Option<TraceId> {
+            TraceId::from_message(&self.trace_id)
+        }
+
+        /// The span id, if it is valid
+        pub fn span_id(&self) -> Option<SpanId> {
+            SpanId::from_message(&self.span_id)
+        }
+
+        /// The span id of the parent span, `None` for root spans
+        pub fn parent_span_id(&self) -> Option<SpanId> {
+            SpanId::from_message(&self.parent_span_id)
+        }
+
+        /// Sets the trace id
+        pub fn set_trace_id(&mut self, trace_id: TraceId) {
+            self.trace_id = trace_id.into();
+        }
+
+        /// Sets the span id
+        pub fn set_span_id(&mut self, span_id: SpanId) {
+            self.span_id = span_id.into();
+        }
+
+        /// Sets the span id of the parent span
+        pub fn set_parent_span_id(&mut self, parent_span_id: SpanId) {
+            self.parent_span_id = parent_span_id.into();
+        }
+
+        /// The `traceparent` propagating the span to its children, if its
+        /// identifiers are valid
+        pub fn traceparent(&self) -> Option<TraceParent> {
+            Some(TraceParent::new(
+                self.trace_id()?,
+                self.span_id()?,
+                (self.flags & SpanFlags::TraceFlagsMask as u32) as u8,
+            ))
+        }
+    }
+
+    impl Link {
+        /// The trace id of the linked span, if it is valid
+        pub fn trace_id(&self) -> Option<TraceId> {
+            TraceId::from_message(&self.trace_id)
+        }
+
+        /// The span id of the linked span, if it is valid
+        pub fn span_id(&self) -> Option<SpanId> {
+            SpanId::from_message(&self.span_id)
+        }
+
+        /// Sets the trace id of the linked span
+        pub fn set_trace_id(&mut self, trace_id: TraceId) {
+            self.trace_id = trace_id.into();
+        }
+
+        /// Sets the span id of the linked span
+        pub fn set_span_id(&mut self, span_id: SpanId) {
+            self.span_id = span_id.into();
+        }
+    }
+}
+
+#[cfg(feature = "otel-logs")]
+mod logs {
+    use super::{SpanId, TraceId};
+    use crate::opentelemetry::proto::logs::v1::LogRecord;
+
+    impl LogRecord {
+        /// The trace id of the correlated span, if it is valid
+        pub fn trace_id(&self) -> Option<TraceId> {
+            TraceId::from_message(&self.trace_id)
+        }
+
+        /// The span id of the correlated span, if it is valid
+        pub fn span_id(&self) -> Option<SpanId> {
+            SpanId::from_message(&self.span_id)
+        }
+
+        /// Sets the trace id of the correlated span
+        pub fn set_trace_id(&mut self, trace_id: TraceId) {
+            self.trace_id = trace_id.into();
+        }
+
+        /// Sets the span id of the correlated span
+        pub fn set_span_id(&mut self, span_id: SpanId) {
+            self.span_id = span_id.into();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRACE_ID: &str = "0af7651916cd43dd8448eb211c80319c";
+    const PARENT_ID: &str = "b7ad6b7169203331";
+
+    fn traceparent(header: &str) -> Result<TraceParent, Error> {
+        header.parse()
+    }
+
+    fn tracestate(header: &str) -> Result<TraceState, Error> {
+        header.parse()
+    }
+
+    #[test]
+    fn parses_valid_traceparents() -> Result<(), Error> {
+        let header = format!("00-{}-{}-01", TRACE_ID, PARENT_ID);
+        let parent = traceparent(&header)?;
+        assert_eq!(parent.trace_id, TraceId::from_hex(TRACE_ID)?);
+        assert_eq!(parent.parent_id, SpanId::from_hex(PARENT_ID)?);
+        assert!(parent.sampled());
+        assert_eq!(parent.to_string(), header);
+
+        let unsampled = traceparent(&format!(" 00-{}-{}-00\t", TRACE_ID, PARENT_ID))?;
+        assert!(!unsampled.sampled());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_future_versions_as_version_00() -> Result<(), Error> {
+        let parent = traceparent(&format!(
+            "cc-{}-{}-09-what-the-future-will-be-like",
+            TRACE_ID, PARENT_ID
+        ))?;
+        assert_eq!(parent.flags, 0x09);
+        assert_eq!(
+            parent.to_string(),
+            format!("00-{}-{}-09", TRACE_ID, PARENT_ID)
+        );
+        assert!(traceparent(&format!("cc-{}-{}-01.what", TRACE_ID, PARENT_ID)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_invalid_traceparents() {
+        let invalid = [
+            format!("ff-{}-{}-01", TRACE_ID, PARENT_ID),
+            format!("00-{}-{}-01-extra", TRACE_ID, PARENT_ID),
+            format!("0-{}-{}-01", TRACE_ID, PARENT_ID),
+            format!("000-{}-{}-01", TRACE_ID, PARENT_ID),
+            format!("0g-{}-{}-01", TRACE_ID, PARENT_ID),
+            format!("00-{}-{}-01", TRACE_ID.to_uppercase(), PARENT_ID),
+            format!("00-{}-{}-01", TRACE_ID, PARENT_ID.to_uppercase()),
+            format!("00-{}-{}-0", TRACE_ID, PARENT_ID),
+            format!("00-{}-{}-0x", TRACE_ID, PARENT_ID),
+            format!("00-{}-{}-01", &TRACE_ID[1..], PARENT_ID),
+            format!("00-{}-{}-01", TRACE_ID, &PARENT_ID[1..]),
+            format!("00-{}-{}-01", "0".repeat(32), PARENT_ID),
+            format!("00-{}-{}-01", TRACE_ID, "0".repeat(16)),
+            format!("00_{}_{}_01", TRACE_ID, PARENT_ID),
+            format!("00-{}-{}", TRACE_ID, PARENT_ID),
+            String::new(),
+        ];
+        for header in &invalid {
+            assert!(traceparent(header).is_err(), "{:?} was accepted", header);
+        }
+        assert_eq!(
+            traceparent(&format!("ff-{}-{}-01", TRACE_ID, PARENT_ID)),
+            Err(Error::TraceParent("unsupported version"))
+        );
+    }
+
+    #[test]
+    fn parses_valid_tracestates() -> Result<(), Error> {
+        let state = tracestate("rojo=00f067aa0ba902b7, congo=t61rcWkgMzE,,\t")?;
+        assert_eq!(state.get("rojo"), Some("00f067aa0ba902b7"));
+        assert_eq!(state.get("congo"), Some("t61rcWkgMzE"));
+        assert_eq!(state.to_string(), "rojo=00f067aa0ba902b7,congo=t61rcWkgMzE");
+
+        let state = tracestate("1tenant@vendor=1,a-b_c*d/e=x y!~")?;
+        assert_eq!(state.get("1tenant@vendor"), Some("1"));
+        assert_eq!(state.get("a-b_c*d/e"), Some("x y!~"));
+        assert!(tracestate("")?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn checks_the_key_grammar() {
+        let valid = [
+            format!("{}=1", "a".repeat(256)),
+            format!("{}@{}=1", "t".repeat(241), "s".repeat(14)),
+            "0@v=1".to_string(),
+        ];
+        for header in &valid {
+            assert!(tracestate(header).is_ok(), "{:?} was rejected", header);
+        }
+        let invalid = [
+            format!("{}=1", "a".repeat(257)),
+            format!("{}@v=1", "t".repeat(242)),
+            format!("t@{}=1", "s".repeat(15)),
+            "Foo=1".to_string(),
+            "1foo=1".to_string(),
+            "foo.bar=1".to_string(),
+            "@vendor=1".to_string(),
+            "tenant@=1".to_string(),
+            "tenant@1vendor=1".to_string(),
+            "a@b@c=1".to_string(),
+            "=1".to_string(),
+        ];
+        for header in &invalid {
+            assert!(tracestate(header).is_err(), "{:?} was accepted", header);
+        }
+    }
+
+    #[test]
+    fn checks_the_value_grammar() {
+        assert!(tracestate(&format!("foo={}", "v".repeat(256))).is_ok());
+        for header in &[
+            format!("foo={}", "v".repeat(257)),
+            "foo".to_string(),
+            "foo=".to_string(),
+            "foo=1=2".to_string(),
+            "foo=\u{e9}".to_string(),
+            "foo=a\u{7f}".to_string(),
+        ] {
+            assert!(tracestate(header).is_err(), "{:?} was accepted", header);
+        }
+    }
+
+    #[test]
+    fn rejects_duplicate_keys() {
+        assert_eq!(
+            tracestate("foo=1,bar=2,foo=3"),
+            Err(Error::TraceState("duplicate key"))
+        );
+    }
+
+    #[test]
+    fn limits_the_number_of_entries() -> Result<(), Error> {
+        let entries = |count: usize| {
+            (0..count)
+                .map(|index| format!("k{}=v", index))
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        assert_eq!(tracestate(&entries(32))?.len(), 32);
+        assert_eq!(
+            tracestate(&entries(33)),
+            Err(Error::TraceState("too many entries"))
+        );
+
+        let mut state = tracestate(&entries(32))?;
+        state.insert("new", "1")?;
+        assert_eq!(state.len(), MAX_TRACE_STATE_ENTRIES);
+        assert_eq!(state.iter().next(), Some(("new", "1")));
+        assert_eq!(state.get("k31"), None);
+        state.insert("k5", "updated")?;
+        assert_eq!(state.iter().next(), Some(("k5", "updated")));
+        assert_eq!(state.len(), MAX_TRACE_STATE_ENTRIES);
+        assert!(state.insert("Bad", "1").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,272 @@
+// Copyright 2020-2022, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Serves the `gRPC OpenTelemetry Collector` on a Unix domain socket, so agents
+//! on the same host export without the TCP stack and access is controlled by
+//! the permissions of the socket file. Events of Unix socket connections have
+//! no remote address, their `RequestMetadata` carries the `PeerCredentials` of
+//! the connected process instead.
+//!
+//! ```ignore
+//! let (tx, rx) = bounded(128);
+//! tremor_otelapis::all::make_unix("/run/otel/collector.sock", tx).await?;
+//! ```
+
+use crate::all::PeerCredentials;
+use futures_core::Stream;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::os::unix::fs::FileTypeExt;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::time::Sleep;
+use tonic::transport::server::Connected;
+
+/// Errors of a collector served on a Unix domain socket
+#[derive(Debug)]
+pub enum Error {
+    /// The socket could not be bound
+    Bind(PathBuf, io::Error),
+    /// The gRPC server failed
+    Transport(tonic::transport::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Bind(path, e) => write!(f, "Failed to bind {}: {}", path.display(), e),
+            Error::Transport(e) => write!(f, "gRPC transport error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Bind(_, e) => Some(e),
+            Error::Transport(e) => Some(e),
+        }
+    }
+}
+
+impl From<tonic::transport::Error> for Error {
+    fn from(e: tonic::transport::Error) -> Self {
+        Error::Transport(e)
+    }
+}
+
+/// Connection info of Unix domain socket connections, available from the
+/// request extensions
+#[derive(Clone, Debug)]
+pub struct UdsConnectInfo {
+    peer_credentials: Option<PeerCredentials>,
+}
+
+impl UdsConnectInfo {
+    /// The credentials of the connected process, where the platform provides them
+    pub fn peer_credentials(&self) -> Option<PeerCredentials> {
+        self.peer_credentials
+    }
+}
+
+/// A connection accepted on a Unix domain socket
+#[derive(Debug)]
+pub struct UdsStream(UnixStream);
+
+impl Connected for UdsStream {
+    type ConnectInfo = UdsConnectInfo;
+
+    fn connect_info(&self) -> Self::ConnectInfo {
+        let peer_credentials = self.0.peer_cred().ok().map(|cred| PeerCredentials {
+            uid: cred.uid(),
+            gid: cred.gid(),
+            pid: cred.pid(),
+        });
+        UdsConnectInfo { peer_credentials }
+    }
+}
+
+impl AsyncRead for UdsStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for UdsStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_shutdown(cx)
+    }
+}
+
+// Pause after accept errors such as running out of file descriptors
+const ACCEPT_ERROR_DELAY: Duration = Duration::from_secs(1);
+
+/// The stream of connections accepted on a Unix domain socket, for use with
+/// `Router::serve_with_incoming`
+///
+/// Accept errors are logged instead of ending the stream. Connections reset
+/// before they were accepted are skipped, after other errors accepting pauses
+/// for a second.
+#[derive(Debug)]
+pub struct UdsIncoming {
+    listener: UnixListener,
+    paused: Option<Pin<Box<Sleep>>>,
+}
+
+impl UdsIncoming {
+    /// Binds a Unix domain socket, replacing a stale socket left behind at the path
+    ///
+    /// A socket file is stale when no process accepts connections on it anymore.
+    /// Binding fails if the socket is still in use or the path is not a socket.
+    pub fn bind<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        remove_stale_socket(path)?;
+        Ok(UdsIncoming {
+            listener: UnixListener::bind(path)?,
+            paused: None,
+        })
+    }
+}
+
+impl Stream for UdsIncoming {
+    type Item = io::Result<UdsStream>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if let Some(paused) = &mut self.paused {
+            if paused.as_mut().poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+            self.paused = None;
+        }
+        loop {
+            match self.listener.poll_accept(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Ok((stream, _addr))) => {
+                    return Poll::Ready(Some(Ok(UdsStream(stream))))
+                }
+                Poll::Ready(Err(e)) if is_connection_error(&e) => {
+                    log::debug!(
+                        "Unix socket connection failed before it was accepted: {}",
+                        e
+                    );
+                }
+                Poll::Ready(Err(e)) => {
+                    log::error!("Failed to accept a Unix socket connection: {}", e);
+                    let mut paused = Box::pin(tokio::time::sleep(ACCEPT_ERROR_DELAY));
+                    if paused.as_mut().poll(cx).is_pending() {
+                        self.paused = Some(paused);
+                        return Poll::Pending;
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Errors of a single connection, which leave the listener usable
+fn is_connection_error(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::ConnectionReset
+    )
+}
+
+fn remove_stale_socket(path: &Path) -> io::Result<()> {
+    let metadata = match std::fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    if !metadata.file_type().is_socket() {
+        return Ok(());
+    }
+    match std::os::unix::net::UnixStream::connect(path) {
+        Ok(_) => Err(io::Error::new(
+            io::ErrorKind::AddrInUse,
+            format!("{} is in use by another process", path.display()),
+        )),
+        Err(e) if e.kind() == io::ErrorKind::ConnectionRefused => std::fs::remove_file(path),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn replaces_stale_sockets() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("collector.sock");
+        // The file outlives the listener
+        drop(std::os::unix::net::UnixListener::bind(&path)?);
+        assert!(path.exists());
+        let _incoming = UdsIncoming::bind(&path)?;
+        assert!(std::os::unix::net::UnixStream::connect(&path).is_ok());
+
+        let fresh = dir.path().join("fresh.sock");
+        let _incoming = UdsIncoming::bind(&fresh)?;
+        assert!(fresh.exists());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn refuses_sockets_in_use() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("collector.sock");
+        let _live = std::os::unix::net::UnixListener::bind(&path)?;
+        let error = UdsIncoming::bind(&path).err().map(|e| e.kind());
+        assert_eq!(error, Some(io::ErrorKind::AddrInUse));
+        assert!(path.exists());
+
+        // Files other than sockets are left alone
+        let file = dir.path().join("file");
+        std::fs::write(&file, "data")?;
+        assert!(UdsIncoming::bind(&file).is_err());
+        assert_eq!(std::fs::read_to_string(&file)?, "data");
+        Ok(())
+    }
+
+    #[test]
+    fn classifies_connection_errors() {
+        assert!(is_connection_error(
+            &io::ErrorKind::ConnectionAborted.into()
+        ));
+        assert!(is_connection_error(&io::ErrorKind::ConnectionReset.into()));
+        assert!(!is_connection_error(&io::Error::from_raw_os_error(24)));
+    }
+}
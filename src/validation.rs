@@ -0,0 +1,858 @@
+// Copyright 2020-2022, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Structural validation of export requests against the OTLP data model.
+//!
+//! Each invalid span, log record or metric is reported once, with its first
+//! violation and its location in the request. Receivers validate requests
+//! according to a `ValidationPolicy`, rejecting requests with invalid items
+//! with `INVALID_ARGUMENT` or stripping the invalid items and reporting them
+//! as a partial success.
+//!
+//! ```ignore
+//! for error in request.validate() {
+//!     eprintln!("{}", error);
+//! }
+//! let forwarder = TraceServiceForwarder::with_sender(sender)
+//!     .with_validation_policy(ValidationPolicy::Strip);
+//! ```
+
+#[cfg(any(
+    feature = "otel-trace",
+    feature = "otel-logs",
+    feature = "otel-metrics"
+))]
+use crate::partial_success::{ExportRequest, PartialSuccess};
+use std::fmt;
+
+/// How a receiver treats export requests with invalid items
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValidationPolicy {
+    /// Forwards requests without validating them
+    Off,
+    /// Replies `INVALID_ARGUMENT` to requests with invalid items
+    Reject,
+    /// Removes invalid items, forwards the remaining ones and replies with a
+    /// partial success
+    Strip,
+}
+
+impl Default for ValidationPolicy {
+    fn default() -> Self {
+        ValidationPolicy::Off
+    }
+}
+
+/// A rule of the OTLP data model violated by an item
+#[derive(Clone, Debug, PartialEq)]
+pub enum Violation {
+    /// The trace id is not 16 bytes or is all zero
+    InvalidTraceId,
+    /// The span id is not 8 bytes or is all zero
+    InvalidSpanId,
+    /// The parent span id is neither empty nor a valid span id
+    InvalidParentSpanId,
+    /// The link at the index has an invalid trace id or span id
+    InvalidLink(usize),
+    /// The span or metric has no name
+    MissingName,
+    /// The span ends before it starts
+    EndBeforeStart,
+    /// The severity number is not defined
+    InvalidSeverityNumber(i32),
+    /// The metric has no data
+    MissingData,
+    /// The aggregation temporality of a sum or histogram is unspecified
+    UnspecifiedTemporality,
+    /// The data point at the index has no value
+    MissingValue(usize),
+    /// The data point at the index has a bucket count per explicit bound plus one
+    BucketCountMismatch {
+        /// The index of the data point
+        point: usize,
+        /// The number of explicit bounds plus one
+        expected: usize,
+        /// The number of bucket counts
+        found: usize,
+    },
+    /// The explicit bounds of the data point at the index are not strictly increasing
+    UnsortedBounds(usize),
+    /// The bucket counts of the data point at the index do not add up to its count
+    CountMismatch(usize),
+    /// A quantile of the data point at the index is outside of `[0, 1]`
+    InvalidQuantile(usize),
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::InvalidTraceId => write!(f, "invalid trace id"),
+            Violation::InvalidSpanId => write!(f, "invalid span id"),
+            Violation::InvalidParentSpanId => write!(f, "invalid parent span id"),
+            Violation::InvalidLink(link) => write!(f, "invalid ids in link {}", link),
+            Violation::MissingName => write!(f, "missing name"),
+            Violation::EndBeforeStart => write!(f, "end time before start time"),
+            Violation::InvalidSeverityNumber(severity) => {
+                write!(f, "undefined severity number {}", severity)
+            }
+            Violation::MissingData => write!(f, "missing data"),
+            Violation::UnspecifiedTemporality => write!(f, "unspecified aggregation temporality"),
+            Violation::MissingValue(point) => write!(f, "data point {} has no value", point),
+            Violation::BucketCountMismatch {
+                point,
+                expected,
+                found,
+            } => write!(
+                f,
+                "data point {} has {} bucket counts, expected {}",
+                point, found, expected
+            ),
+            Violation::UnsortedBounds(point) => write!(
+                f,
+                "data point {} has explicit bounds that are not strictly increasing",
+                point
+            ),
+            Violation::CountMismatch(point) => write!(
+                f,
+                "data point {} has bucket counts that do not add up to its count",
+                point
+            ),
+            Violation::InvalidQuantile(point) => {
+                write!(f, "data point {} has a quantile outside of [0, 1]", point)
+            }
+        }
+    }
+}
+
+/// The location of an item in an export request, ordered as in the request
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Location {
+    /// The index of the resource group
+    pub resource: usize,
+    /// The index of the scope group within the resource group
+    pub scope: usize,
+    /// The index of the item within the scope group
+    pub item: usize,
+}
+
+/// An invalid item of an export request
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidationError {
+    /// Where the item is in the request
+    pub location: Location,
+    /// The first rule the item violates
+    pub violation: Violation,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "resource {}, scope {}, item {}: {}",
+            self.location.resource, self.location.scope, self.location.item, self.violation
+        )
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Export requests validated against the OTLP data model
+pub trait Validate {
+    /// The validated items, such as `spans`, as named in error messages
+    const ITEMS: &'static str;
+
+    /// The invalid items of the request, in request order
+    fn validate(&self) -> Vec<ValidationError>;
+
+    /// Removes the invalid items of the request, returning them as errors
+    fn strip_invalid(&mut self) -> Vec<ValidationError>;
+
+    /// The number of items a partial success counts for the invalid items,
+    /// one per invalid item unless the request counts smaller units
+    fn rejected_count(&self, errors: &[ValidationError]) -> usize {
+        errors.len()
+    }
+}
+
+// Validates a request according to the policy, returning the response to
+// reply with once it is forwarded, a partial success if items were stripped
+#[cfg(any(
+    feature = "otel-trace",
+    feature = "otel-logs",
+    feature = "otel-metrics"
+))]
+pub(crate) fn enforce<R: Validate + ExportRequest>(
+    policy: ValidationPolicy,
+    request: &mut R,
+) -> Result<R::Response, tonic::Status> {
+    match policy {
+        ValidationPolicy::Off => Ok(R::Response::default()),
+        ValidationPolicy::Strip => {
+            let errors = request.validate();
+            let first = match errors.first() {
+                None => return Ok(R::Response::default()),
+                Some(first) => first,
+            };
+            let rejected = request.rejected_count(&errors);
+            let message = invalid_items::<R>(&errors, first);
+            request.strip_invalid();
+            Ok(R::Response::rejected(rejected as i64, message))
+        }
+        ValidationPolicy::Reject => {
+            let errors = request.validate();
+            match errors.first() {
+                None => Ok(R::Response::default()),
+                Some(first) => Err(tonic::Status::invalid_argument(invalid_items::<R>(
+                    &errors, first,
+                ))),
+            }
+        }
+    }
+}
+
+#[cfg(any(
+    feature = "otel-trace",
+    feature = "otel-logs",
+    feature = "otel-metrics"
+))]
+fn invalid_items<R: Validate>(errors: &[ValidationError], first: &ValidationError) -> String {
+    format!(
+        "{} invalid {}, the first at {}",
+        errors.len(),
+        R::ITEMS,
+        first
+    )
+}
+
+// Removes the items of a scope group that have errors, which `validate`
+// returns sorted by location
+#[cfg(any(
+    feature = "otel-trace",
+    feature = "otel-logs",
+    feature = "otel-metrics"
+))]
+fn retain_valid<T>(items: &mut Vec<T>, errors: &[ValidationError], resource: usize, scope: usize) {
+    let mut item = 0;
+    items.retain(|_| {
+        let location = Location {
+            resource,
+            scope,
+            item,
+        };
+        item += 1;
+        errors
+            .binary_search_by(|error| error.location.cmp(&location))
+            .is_err()
+    });
+}
+
+#[cfg(feature = "otel-trace")]
+pub use self::trace::validate_span;
+
+#[cfg(feature = "otel-trace")]
+mod trace {
+    use super::{retain_valid, Location, Validate, ValidationError, Violation};
+    use crate::opentelemetry::proto::collector::trace::v1::ExportTraceServiceRequest;
+    use crate::opentelemetry::proto::trace::v1::Span;
+
+    /// Checks a span against the OTLP data model
+    pub fn validate_span(span: &Span) -> Result<(), Violation> {
+        if span.trace_id().is_none() {
+            return Err(Violation::InvalidTraceId);
+        }
+        if span.span_id().is_none() {
+            return Err(Violation::InvalidSpanId);
+        }
+        if !span.parent_span_id.is_empty() && span.parent_span_id().is_none() {
+            return Err(Violation::InvalidParentSpanId);
+        }
+        if span.name.is_empty() {
+            return Err(Violation::MissingName);
+        }
+        if span.end_time_unix_nano < span.start_time_unix_nano {
+            return Err(Violation::EndBeforeStart);
+        }
+        if let Some(link) = span
+            .links
+            .iter()
+            .position(|link| link.trace_id().is_none() || link.span_id().is_none())
+        {
+            return Err(Violation::InvalidLink(link));
+        }
+        Ok(())
+    }
+
+    impl Validate for ExportTraceServiceRequest {
+        const ITEMS: &'static str = "spans";
+
+        fn validate(&self) -> Vec<ValidationError> {
+            let mut errors = Vec::new();
+            for (resource, resource_spans) in self.resource_spans.iter().enumerate() {
+                for (scope, scope_spans) in resource_spans.scope_spans.iter().enumerate() {
+                    for (item, span) in scope_spans.spans.iter().enumerate() {
+                        if let Err(violation) = validate_span(span) {
+                            errors.push(ValidationError {
+                                location: Location {
+                                    resource,
+                                    scope,
+                                    item,
+                                },
+                                violation,
+                            });
+                        }
+                    }
+                }
+            }
+            errors
+        }
+
+        fn strip_invalid(&mut self) -> Vec<ValidationError> {
+            let errors = self.validate();
+            if !errors.is_empty() {
+                for (resource, resource_spans) in self.resource_spans.iter_mut().enumerate() {
+                    for (scope, scope_spans) in resource_spans.scope_spans.iter_mut().enumerate() {
+                        retain_valid(&mut scope_spans.spans, &errors, resource, scope);
+                    }
+                }
+            }
+            errors
+        }
+    }
+}
+
+#[cfg(feature = "otel-logs")]
+pub use self::logs::validate_log_record;
+
+#[cfg(feature = "otel-logs")]
+mod logs {
+    use super::{retain_valid, Location, Validate, ValidationError, Violation};
+    use crate::opentelemetry::proto::collector::logs::v1::ExportLogsServiceRequest;
+    use crate::opentelemetry::proto::logs::v1::{LogRecord, SeverityNumber};
+
+    /// Checks a log record against the OTLP data model, the trace id and
+    /// span id are optional
+    pub fn validate_log_record(record: &LogRecord) -> Result<(), Violation> {
+        if !record.trace_id.is_empty() && record.trace_id().is_none() {
+            return Err(Violation::InvalidTraceId);
+        }
+        if !record.span_id.is_empty() && record.span_id().is_none() {
+            return Err(Violation::InvalidSpanId);
+        }
+        if SeverityNumber::from_i32(record.severity_number).is_none() {
+            return Err(Violation::InvalidSeverityNumber(record.severity_number));
+        }
+        Ok(())
+    }
+
+    impl Validate for ExportLogsServiceRequest {
+        const ITEMS: &'static str = "log records";
+
+        fn validate(&self) -> Vec<ValidationError> {
+            let mut errors = Vec::new();
+            for (resource, resource_logs) in self.resource_logs.iter().enumerate() {
+                for (scope, scope_logs) in resource_logs.scope_logs.iter().enumerate() {
+                    for (item, record) in scope_logs.log_records.iter().enumerate() {
+                        if let Err(violation) = validate_log_record(record) {
+                            errors.push(ValidationError {
+                                location: Location {
+                                    resource,
+                                    scope,
+                                    item,
+                                },
+                                violation,
+                            });
+                        }
+                    }
+                }
+            }
+            errors
+        }
+
+        fn strip_invalid(&mut self) -> Vec<ValidationError> {
+            let errors = self.validate();
+            if !errors.is_empty() {
+                for (resource, resource_logs) in self.resource_logs.iter_mut().enumerate() {
+                    for (scope, scope_logs) in resource_logs.scope_logs.iter_mut().enumerate() {
+                        retain_valid(&mut scope_logs.log_records, &errors, resource, scope);
+                    }
+                }
+            }
+            errors
+        }
+    }
+}
+
+#[cfg(feature = "otel-metrics")]
+pub use self::metrics::validate_metric;
+
+#[cfg(feature = "otel-metrics")]
+mod metrics {
+    use super::{retain_valid, Location, Validate, ValidationError, Violation};
+    use crate::opentelemetry::proto::collector::metrics::v1::ExportMetricsServiceRequest;
+    use crate::opentelemetry::proto::metrics::v1::exponential_histogram_data_point::Buckets;
+    use crate::opentelemetry::proto::metrics::v1::{
+        metric, AggregationTemporality, DataPointFlags, HistogramDataPoint, Metric,
+    };
+    use crate::partial_success::data_point_count;
+
+    // Data points flagged without a recorded value carry no value or buckets
+    fn has_recorded_value(flags: u32) -> bool {
+        flags & DataPointFlags::NoRecordedValueMask as u32 == 0
+    }
+
+    fn check_temporality(temporality: i32) -> Result<(), Violation> {
+        match AggregationTemporality::from_i32(temporality) {
+            Some(AggregationTemporality::Delta) | Some(AggregationTemporality::Cumulative) => {
+                Ok(())
+            }
+            _ => Err(Violation::UnspecifiedTemporality),
+        }
+    }
+
+    fn check_histogram_point(index: usize, point: &HistogramDataPoint) -> Result<(), Violation> {
+        if !has_recorded_value(point.flags) || point.bucket_counts.is_empty() {
+            return Ok(());
+        }
+        if point.bucket_counts.len() != point.explicit_bounds.len() + 1 {
+            return Err(Violation::BucketCountMismatch {
+                point: index,
+                expected: point.explicit_bounds.len() + 1,
+                found: point.bucket_counts.len(),
+            });
+        }
+        if point
+            .explicit_bounds
+            .windows(2)
+            .any(|pair| pair[0] >= pair[1])
+        {
+            return Err(Violation::UnsortedBounds(index));
+        }
+        if checked_total(&point.bucket_counts) != Some(point.count) {
+            return Err(Violation::CountMismatch(index));
+        }
+        Ok(())
+    }
+
+    // Counts are client controlled, a total that overflows cannot match
+    fn checked_total(counts: &[u64]) -> Option<u64> {
+        counts
+            .iter()
+            .try_fold(0_u64, |total, count| total.checked_add(*count))
+    }
+
+    fn bucket_total(buckets: &Option<Buckets>) -> Option<u64> {
+        buckets
+            .as_ref()
+            .map_or(Some(0), |buckets| checked_total(&buckets.bucket_counts))
+    }
+
+    /// Checks a metric and its data points against the OTLP data model
+    pub fn validate_metric(metric: &Metric) -> Result<(), Violation> {
+        if metric.name.is_empty() {
+            return Err(Violation::MissingName);
+        }
+        match &metric.data {
+            None => Err(Violation::MissingData),
+            Some(metric::Data::Gauge(gauge)) => {
+                match gauge
+                    .data_points
+                    .iter()
+                    .position(|point| has_recorded_value(point.flags) && point.value.is_none())
+                {
+                    Some(point) => Err(Violation::MissingValue(point)),
+                    None => Ok(()),
+                }
+            }
+            Some(metric::Data::Sum(sum)) => {
+                check_temporality(sum.aggregation_temporality)?;
+                match sum
+                    .data_points
+                    .iter()
+                    .position(|point| has_recorded_value(point.flags) && point.value.is_none())
+                {
+                    Some(point) => Err(Violation::MissingValue(point)),
+                    None => Ok(()),
+                }
+            }
+            Some(metric::Data::Histogram(histogram)) => {
+                check_temporality(histogram.aggregation_temporality)?;
+                for (index, point) in histogram.data_points.iter().enumerate() {
+                    check_histogram_point(index, point)?;
+                }
+                Ok(())
+            }
+            Some(metric::Data::ExponentialHistogram(histogram)) => {
+                check_temporality(histogram.aggregation_temporality)?;
+                match histogram.data_points.iter().position(|point| {
+                    let total = bucket_total(&point.positive)
+                        .and_then(|total| total.checked_add(bucket_total(&point.negative)?))
+                        .and_then(|total| total.checked_add(point.zero_count));
+                    has_recorded_value(point.flags) && total != Some(point.count)
+                }) {
+                    Some(point) => Err(Violation::CountMismatch(point)),
+                    None => Ok(()),
+                }
+            }
+            Some(metric::Data::Summary(summary)) => {
+                match summary.data_points.iter().position(|point| {
+                    point
+                        .quantile_values
+                        .iter()
+                        .any(|value| !(0.0..=1.0).contains(&value.quantile))
+                }) {
+                    Some(point) => Err(Violation::InvalidQuantile(point)),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+
+    impl Validate for ExportMetricsServiceRequest {
+        const ITEMS: &'static str = "metrics";
+
+        fn validate(&self) -> Vec<ValidationError> {
+            let mut errors = Vec::new();
+            for (resource, resource_metrics) in self.resource_metrics.iter().enumerate() {
+                for (scope, scope_metrics) in resource_metrics.scope_metrics.iter().enumerate() {
+                    for (item, metric) in scope_metrics.metrics.iter().enumerate() {
+                        if let Err(violation) = validate_metric(metric) {
+                            errors.push(ValidationError {
+                                location: Location {
+                                    resource,
+                                    scope,
+                                    item,
+                                },
+                                violation,
+                            });
+                        }
+                    }
+                }
+            }
+            errors
+        }
+
+        fn strip_invalid(&mut self) -> Vec<ValidationError> {
+            let errors = self.validate();
+            if !errors.is_empty() {
+                for (resource, resource_metrics) in self.resource_metrics.iter_mut().enumerate() {
+                    for (scope, scope_metrics) in
+                        resource_metrics.scope_metrics.iter_mut().enumerate()
+                    {
+                        retain_valid(&mut scope_metrics.metrics, &errors, resource, scope);
+                    }
+                }
+            }
+            errors
+        }
+
+        /// The data points of the invalid metrics, metrics without data
+        /// points count as one
+        fn rejected_count(&self, errors: &[ValidationError]) -> usize {
+            errors
+                .iter()
+                .filter_map(|error| {
+                    self.resource_metrics
+                        .get(error.location.resource)?
+                        .scope_metrics
+                        .get(error.location.scope)?
+                        .metrics
+                        .get(error.location.item)
+                })
+                .map(|metric| data_point_count(metric).max(1))
+                .sum()
+        }
+    }
+}
+
+#[cfg(all(test, feature = "otel-all"))]
+mod tests {
+    use super::*;
+    use crate::builders::{
+        resource, scope, GaugeBuilder, HistogramBuilder, LogRecordBuilder, LogsRequestBuilder,
+        MetricsRequestBuilder, SpanBuilder, SumBuilder, TraceRequestBuilder,
+    };
+    use crate::opentelemetry::proto::collector::metrics::v1::ExportMetricsServiceResponse;
+    use crate::opentelemetry::proto::collector::trace::v1::{
+        ExportTraceServiceRequest, ExportTraceServiceResponse,
+    };
+    use crate::opentelemetry::proto::metrics::v1::exponential_histogram_data_point::Buckets;
+    use crate::opentelemetry::proto::metrics::v1::{
+        metric, AggregationTemporality, ExponentialHistogram, ExponentialHistogramDataPoint, Metric,
+    };
+    use crate::opentelemetry::proto::trace::v1::Span;
+
+    fn location(resource: usize, scope: usize, item: usize) -> Location {
+        Location {
+            resource,
+            scope,
+            item,
+        }
+    }
+
+    fn trace_request(spans: Vec<Vec<Span>>) -> ExportTraceServiceRequest {
+        let resource = resource(vec![("service.name", "test")]);
+        let mut builder = TraceRequestBuilder::new();
+        for (index, spans) in spans.into_iter().enumerate() {
+            let scope = scope(format!("scope-{}", index), "1.0", vec![]);
+            for span in spans {
+                builder.push(&resource, &scope, span);
+            }
+        }
+        builder.build()
+    }
+
+    fn invalid_span(name: &str) -> Span {
+        let mut span = SpanBuilder::new(name).build();
+        span.end_time_unix_nano = span.start_time_unix_nano - 1;
+        span
+    }
+
+    #[test]
+    fn span_rules() {
+        assert_eq!(validate_span(&SpanBuilder::new("ok").build()), Ok(()));
+        let mut span = SpanBuilder::new("ids").build();
+        span.trace_id = vec![0; 16];
+        assert_eq!(validate_span(&span), Err(Violation::InvalidTraceId));
+        span = SpanBuilder::new("ids").build();
+        span.span_id = vec![1; 4];
+        assert_eq!(validate_span(&span), Err(Violation::InvalidSpanId));
+        span = SpanBuilder::new("ids").build();
+        span.parent_span_id = vec![1; 16];
+        assert_eq!(validate_span(&span), Err(Violation::InvalidParentSpanId));
+        assert_eq!(
+            validate_span(&SpanBuilder::new("").build()),
+            Err(Violation::MissingName)
+        );
+        assert_eq!(
+            validate_span(&invalid_span("late")),
+            Err(Violation::EndBeforeStart)
+        );
+        let span = SpanBuilder::new("link")
+            .link([1; 16], [1; 8], vec![])
+            .link([1; 16], [0; 8], vec![])
+            .build();
+        assert_eq!(validate_span(&span), Err(Violation::InvalidLink(1)));
+    }
+
+    #[test]
+    fn strip_keeps_valid_spans_in_order() {
+        let ok = |name: &str| SpanBuilder::new(name).build();
+        let mut request = trace_request(vec![
+            vec![ok("a"), invalid_span("b"), ok("c"), invalid_span("d")],
+            vec![invalid_span("e"), ok("f")],
+        ]);
+        let errors = request.validate();
+        let locations: Vec<_> = errors.iter().map(|error| error.location).collect();
+        assert_eq!(
+            locations,
+            vec![location(0, 0, 1), location(0, 0, 3), location(0, 1, 0)]
+        );
+        assert_eq!(request.strip_invalid(), errors);
+        let names: Vec<Vec<&str>> = request.resource_spans[0]
+            .scope_spans
+            .iter()
+            .map(|scope| scope.spans.iter().map(|span| span.name.as_str()).collect())
+            .collect();
+        assert_eq!(names, vec![vec!["a", "c"], vec!["f"]]);
+        assert!(request.validate().is_empty());
+    }
+
+    #[test]
+    fn log_record_rules() {
+        let record = LogRecordBuilder::new().build();
+        assert_eq!(validate_log_record(&record), Ok(()));
+        let mut record = LogRecordBuilder::new().span([1; 16], [1; 8], 1).build();
+        assert_eq!(validate_log_record(&record), Ok(()));
+        record.span_id = vec![1; 3];
+        assert_eq!(validate_log_record(&record), Err(Violation::InvalidSpanId));
+        let mut record = LogRecordBuilder::new().build();
+        record.severity_number = 99;
+        assert_eq!(
+            validate_log_record(&record),
+            Err(Violation::InvalidSeverityNumber(99))
+        );
+        let request = LogsRequestBuilder::new()
+            .add(
+                &resource(Vec::<(&str, &str)>::new()),
+                &scope("s", "", vec![]),
+                record,
+            )
+            .build();
+        assert_eq!(request.validate().len(), 1);
+    }
+
+    fn histogram(update: impl FnOnce(&mut Metric)) -> Metric {
+        let mut metric = HistogramBuilder::new("h", vec![1.0, 2.0])
+            .record(&[0.5, 1.5, 2.5], vec![])
+            .build();
+        update(&mut metric);
+        metric
+    }
+
+    fn histogram_point(
+        metric: &mut Metric,
+    ) -> &mut crate::opentelemetry::proto::metrics::v1::HistogramDataPoint {
+        match &mut metric.data {
+            Some(metric::Data::Histogram(histogram)) => &mut histogram.data_points[0],
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn histogram_rules() {
+        assert_eq!(validate_metric(&histogram(|_| ())), Ok(()));
+        let metric = histogram(|metric| {
+            histogram_point(metric).bucket_counts.pop();
+        });
+        assert_eq!(
+            validate_metric(&metric),
+            Err(Violation::BucketCountMismatch {
+                point: 0,
+                expected: 3,
+                found: 2
+            })
+        );
+        let metric = histogram(|metric| histogram_point(metric).explicit_bounds = vec![2.0, 1.0]);
+        assert_eq!(validate_metric(&metric), Err(Violation::UnsortedBounds(0)));
+        let metric = histogram(|metric| histogram_point(metric).count = 4);
+        assert_eq!(validate_metric(&metric), Err(Violation::CountMismatch(0)));
+        let metric = histogram(|metric| {
+            let point = histogram_point(metric);
+            point.bucket_counts = vec![u64::MAX, 2, 0];
+            point.count = 1;
+        });
+        assert_eq!(validate_metric(&metric), Err(Violation::CountMismatch(0)));
+    }
+
+    #[test]
+    fn exponential_histogram_count_overflow() {
+        let metric = |zero_count: u64, positive: Vec<u64>, count: u64| Metric {
+            name: "e".to_string(),
+            data: Some(metric::Data::ExponentialHistogram(ExponentialHistogram {
+                aggregation_temporality: AggregationTemporality::Delta as i32,
+                data_points: vec![ExponentialHistogramDataPoint {
+                    count,
+                    zero_count,
+                    positive: Some(Buckets {
+                        offset: 0,
+                        bucket_counts: positive,
+                    }),
+                    ..ExponentialHistogramDataPoint::default()
+                }],
+            })),
+            ..Metric::default()
+        };
+        assert_eq!(validate_metric(&metric(1, vec![2, 3], 6)), Ok(()));
+        assert_eq!(
+            validate_metric(&metric(u64::MAX, vec![1], 0)),
+            Err(Violation::CountMismatch(0))
+        );
+        assert_eq!(
+            validate_metric(&metric(0, vec![u64::MAX, u64::MAX], 0)),
+            Err(Violation::CountMismatch(0))
+        );
+    }
+
+    #[test]
+    fn metrics_without_name_or_data() {
+        assert_eq!(
+            validate_metric(&Metric::default()),
+            Err(Violation::MissingName)
+        );
+        let metric = Metric {
+            name: "m".to_string(),
+            ..Metric::default()
+        };
+        assert_eq!(validate_metric(&metric), Err(Violation::MissingData));
+        let request = MetricsRequestBuilder::new()
+            .add(
+                &resource(Vec::<(&str, &str)>::new()),
+                &scope("s", "", vec![]),
+                metric,
+            )
+            .build();
+        assert_eq!(request.validate()[0].location, location(0, 0, 0));
+    }
+
+    #[test]
+    fn enforce_policies() {
+        let request =
+            || trace_request(vec![vec![SpanBuilder::new("a").build(), invalid_span("b")]]);
+        let mut unchecked = request();
+        let response: ExportTraceServiceResponse =
+            enforce(ValidationPolicy::Off, &mut unchecked).unwrap_or_default();
+        assert_eq!(response, ExportTraceServiceResponse::default());
+        assert_eq!(unchecked.item_count(), 2);
+
+        let status = enforce(ValidationPolicy::Reject, &mut request()).err();
+        assert_eq!(
+            status.map(|status| status.code()),
+            Some(tonic::Code::InvalidArgument)
+        );
+
+        let mut stripped = request();
+        let response = enforce(ValidationPolicy::Strip, &mut stripped).unwrap_or_default();
+        assert_eq!(response.rejected_count(), 1);
+        assert!(response
+            .error_message()
+            .map_or(false, |message| message.contains("item 1")));
+        assert_eq!(stripped.item_count(), 1);
+    }
+
+    #[test]
+    fn enforce_counts_the_data_points_of_stripped_metrics() {
+        let gauge = GaugeBuilder::new("g")
+            .point(1_i64, vec![])
+            .point(2_i64, vec![])
+            .build();
+        let unspecified = SumBuilder::new("s")
+            .temporality(AggregationTemporality::Unspecified)
+            .point(1_i64, vec![])
+            .point(2_i64, vec![])
+            .point(3_i64, vec![])
+            .build();
+        let without_data = Metric {
+            name: "m".to_string(),
+            ..Metric::default()
+        };
+        let resource = resource(vec![("service.name", "test")]);
+        let scope = scope("s", "", vec![]);
+        let request = || {
+            MetricsRequestBuilder::new()
+                .add(&resource, &scope, gauge.clone())
+                .add(&resource, &scope, unspecified.clone())
+                .add(&resource, &scope, without_data.clone())
+                .build()
+        };
+
+        let mut stripped = request();
+        assert_eq!(stripped.item_count(), 5);
+        let response: ExportMetricsServiceResponse =
+            enforce(ValidationPolicy::Strip, &mut stripped).unwrap_or_default();
+        // The sum rejects its three data points, the metric without data counts as one
+        assert_eq!(response.rejected_count(), 4);
+        assert!(response.error_message().map_or(false, |message| message
+            .starts_with("2 invalid metrics, the first at resource 0, scope 0, item 1")));
+        assert_eq!(stripped.item_count(), 2);
+        assert_eq!(
+            stripped.resource_metrics[0].scope_metrics[0].metrics,
+            [gauge.clone()]
+        );
+
+        let status = enforce(ValidationPolicy::Reject, &mut request()).err();
+        assert!(status.map_or(false, |status| status
+            .message()
+            .starts_with("2 invalid metrics")));
+    }
+}
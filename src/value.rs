@@ -0,0 +1,503 @@
+// Copyright 2020-2022, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Conversions between Rust values and `AnyValue`s, accessors for the value
+//! of an `AnyValue` and the `attributes!` macro building `KeyValue` lists.
+//!
+//! Strings, booleans, integers, floats and byte vectors convert to the
+//! matching scalar value, vectors to an `ArrayValue` and maps to a
+//! `KeyValueList`. `TryFrom` converts back and fails with a `TypeMismatch`
+//! when the value holds another type.
+//!
+//! ```ignore
+//! let attributes = attributes! {
+//!     "http.method" => "GET",
+//!     "http.status_code" => 200,
+//!     "http.route" => vec!["users", "{id}"],
+//! };
+//! let method = attributes[0].value.as_ref().and_then(AnyValue::as_str);
+//! let code = i64::try_from(attributes[1].value.clone().unwrap_or_default())?;
+//! ```
+
+use crate::opentelemetry::proto::common::v1::{
+    any_value, AnyValue, ArrayValue, KeyValue, KeyValueList,
+};
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+
+/// Builds a `Vec<KeyValue>` from `key => value` pairs, converting values
+/// with `Into<AnyValue>`
+#[macro_export]
+macro_rules! attributes {
+    () => {
+        ::std::vec::Vec::<$crate::opentelemetry::proto::common::v1::KeyValue>::new()
+    };
+    ($($key:expr => $value:expr),+ $(,)?) => {
+        ::std::vec![$($crate::value::key_value($key, $value)),+]
+    };
+}
+
+/// Creates an attribute from a key and a value
+pub fn key_value<K: Into<String>, V: Into<AnyValue>>(key: K, value: V) -> KeyValue {
+    KeyValue {
+        key: key.into(),
+        value: Some(value.into()),
+        ..KeyValue::default()
+    }
+}
+
+impl From<any_value::Value> for AnyValue {
+    fn from(value: any_value::Value) -> Self {
+        AnyValue { value: Some(value) }
+    }
+}
+
+impl From<String> for AnyValue {
+    fn from(value: String) -> Self {
+        any_value::Value::StringValue(value).into()
+    }
+}
+
+impl From<&str> for AnyValue {
+    fn from(value: &str) -> Self {
+        any_value::Value::StringValue(value.to_string()).into()
+    }
+}
+
+impl From<bool> for AnyValue {
+    fn from(value: bool) -> Self {
+        any_value::Value::BoolValue(value).into()
+    }
+}
+
+impl From<i64> for AnyValue {
+    fn from(value: i64) -> Self {
+        any_value::Value::IntValue(value).into()
+    }
+}
+
+impl From<f64> for AnyValue {
+    fn from(value: f64) -> Self {
+        any_value::Value::DoubleValue(value).into()
+    }
+}
+
+impl From<i32> for AnyValue {
+    fn from(value: i32) -> Self {
+        any_value::Value::IntValue(i64::from(value)).into()
+    }
+}
+
+impl From<u32> for AnyValue {
+    fn from(value: u32) -> Self {
+        any_value::Value::IntValue(i64::from(value)).into()
+    }
+}
+
+impl From<Vec<u8>> for AnyValue {
+    fn from(value: Vec<u8>) -> Self {
+        any_value::Value::BytesValue(value).into()
+    }
+}
+
+impl From<&[u8]> for AnyValue {
+    fn from(value: &[u8]) -> Self {
+        any_value::Value::BytesValue(value.to_vec()).into()
+    }
+}
+
+impl From<ArrayValue> for AnyValue {
+    fn from(value: ArrayValue) -> Self {
+        any_value::Value::ArrayValue(value).into()
+    }
+}
+
+impl From<KeyValueList> for AnyValue {
+    fn from(value: KeyValueList) -> Self {
+        any_value::Value::KvlistValue(value).into()
+    }
+}
+
+impl<T: Into<AnyValue>> From<Vec<T>> for AnyValue {
+    fn from(values: Vec<T>) -> Self {
+        ArrayValue {
+            values: values.into_iter().map(Into::into).collect(),
+        }
+        .into()
+    }
+}
+
+impl<K: Into<String>, V: Into<AnyValue>, S: BuildHasher> From<HashMap<K, V, S>> for AnyValue {
+    fn from(values: HashMap<K, V, S>) -> Self {
+        KeyValueList {
+            values: values
+                .into_iter()
+                .map(|(key, value)| key_value(key, value))
+                .collect(),
+        }
+        .into()
+    }
+}
+
+impl<K: Into<String>, V: Into<AnyValue>> From<BTreeMap<K, V>> for AnyValue {
+    fn from(values: BTreeMap<K, V>) -> Self {
+        KeyValueList {
+            values: values
+                .into_iter()
+                .map(|(key, value)| key_value(key, value))
+                .collect(),
+        }
+        .into()
+    }
+}
+
+/// The error of converting an `AnyValue` that holds another type
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TypeMismatch {
+    /// The type the value was converted to
+    pub expected: &'static str,
+    /// The type the value holds
+    pub found: &'static str,
+}
+
+impl fmt::Display for TypeMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Expected a {} value, found {}",
+            self.expected, self.found
+        )
+    }
+}
+
+impl std::error::Error for TypeMismatch {}
+
+impl AnyValue {
+    /// The name of the type the value holds, `empty` if it holds none
+    pub fn type_name(&self) -> &'static str {
+        match &self.value {
+            None => "empty",
+            Some(any_value::Value::StringValue(_)) => "string",
+            Some(any_value::Value::BoolValue(_)) => "bool",
+            Some(any_value::Value::IntValue(_)) => "int",
+            Some(any_value::Value::DoubleValue(_)) => "double",
+            Some(any_value::Value::ArrayValue(_)) => "array",
+            Some(any_value::Value::KvlistValue(_)) => "kvlist",
+            Some(any_value::Value::BytesValue(_)) => "bytes",
+            Some(any_value::Value::StringValueStrindex(_)) => "string index",
+        }
+    }
+
+    /// Checks if the value holds no value
+    pub fn is_empty(&self) -> bool {
+        self.value.is_none()
+    }
+
+    /// The string held by the value
+    pub fn as_str(&self) -> Option<&str> {
+        match &self.value {
+            Some(any_value::Value::StringValue(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// The boolean held by the value
+    pub fn as_bool(&self) -> Option<bool> {
+        match &self.value {
+            Some(any_value::Value::BoolValue(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The integer held by the value
+    pub fn as_i64(&self) -> Option<i64> {
+        match &self.value {
+            Some(any_value::Value::IntValue(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The double held by the value
+    pub fn as_f64(&self) -> Option<f64> {
+        match &self.value {
+            Some(any_value::Value::DoubleValue(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The bytes held by the value
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match &self.value {
+            Some(any_value::Value::BytesValue(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// The elements of the array held by the value
+    pub fn as_array(&self) -> Option<&[AnyValue]> {
+        match &self.value {
+            Some(any_value::Value::ArrayValue(array)) => Some(&array.values),
+            _ => None,
+        }
+    }
+
+    /// The entries of the key value list held by the value
+    pub fn as_kvlist(&self) -> Option<&[KeyValue]> {
+        match &self.value {
+            Some(any_value::Value::KvlistValue(list)) => Some(&list.values),
+            _ => None,
+        }
+    }
+
+    /// The value of the first entry with the key in the key value list held by the value
+    pub fn get(&self, key: &str) -> Option<&AnyValue> {
+        self.as_kvlist()?
+            .iter()
+            .find(|entry| entry.key == key)
+            .and_then(|entry| entry.value.as_ref())
+    }
+
+    fn mismatch(&self, expected: &'static str) -> TypeMismatch {
+        TypeMismatch {
+            expected,
+            found: self.type_name(),
+        }
+    }
+}
+
+impl TryFrom<AnyValue> for String {
+    type Error = TypeMismatch;
+
+    fn try_from(value: AnyValue) -> Result<Self, Self::Error> {
+        match value.value {
+            Some(any_value::Value::StringValue(value)) => Ok(value),
+            _ => Err(value.mismatch("string")),
+        }
+    }
+}
+
+impl TryFrom<AnyValue> for bool {
+    type Error = TypeMismatch;
+
+    fn try_from(value: AnyValue) -> Result<Self, Self::Error> {
+        value.as_bool().ok_or_else(|| value.mismatch("bool"))
+    }
+}
+
+impl TryFrom<AnyValue> for i64 {
+    type Error = TypeMismatch;
+
+    fn try_from(value: AnyValue) -> Result<Self, Self::Error> {
+        value.as_i64().ok_or_else(|| value.mismatch("int"))
+    }
+}
+
+impl TryFrom<AnyValue> for f64 {
+    type Error = TypeMismatch;
+
+    fn try_from(value: AnyValue) -> Result<Self, Self::Error> {
+        value.as_f64().ok_or_else(|| value.mismatch("double"))
+    }
+}
+
+impl TryFrom<AnyValue> for Vec<u8> {
+    type Error = TypeMismatch;
+
+    fn try_from(value: AnyValue) -> Result<Self, Self::Error> {
+        match value.value {
+            Some(any_value::Value::BytesValue(value)) => Ok(value),
+            _ => Err(value.mismatch("bytes")),
+        }
+    }
+}
+
+impl TryFrom<AnyValue> for Vec<AnyValue> {
+    type Error = TypeMismatch;
+
+    fn try_from(value: AnyValue) -> Result<Self, Self::Error> {
+        match value.value {
+            Some(any_value::Value::ArrayValue(array)) => Ok(array.values),
+            _ => Err(value.mismatch("array")),
+        }
+    }
+}
+
+impl TryFrom<AnyValue> for HashMap<String, AnyValue> {
+    type Error = TypeMismatch;
+
+    fn try_from(value: AnyValue) -> Result<Self, Self::Error> {
+        kvlist_entries(value).map(|entries| entries.collect())
+    }
+}
+
+impl TryFrom<AnyValue> for BTreeMap<String, AnyValue> {
+    type Error = TypeMismatch;
+
+    fn try_from(value: AnyValue) -> Result<Self, Self::Error> {
+        kvlist_entries(value).map(|entries| entries.collect())
+    }
+}
+
+// Later entries of a key value list win over earlier ones with the same key
+fn kvlist_entries(
+    value: AnyValue,
+) -> Result<impl Iterator<Item = (String, AnyValue)>, TypeMismatch> {
+    match value.value {
+        Some(any_value::Value::KvlistValue(list)) => Ok(list
+            .values
+            .into_iter()
+            .map(|entry| (entry.key, entry.value.unwrap_or_default()))),
+        _ => Err(value.mismatch("kvlist")),
+    }
+}
+
+/// Converts the elements of an array value
+pub fn try_into_vec<T: TryFrom<AnyValue, Error = TypeMismatch>>(
+    value: AnyValue,
+) -> Result<Vec<T>, TypeMismatch> {
+    Vec::<AnyValue>::try_from(value)?
+        .into_iter()
+        .map(T::try_from)
+        .collect()
+}
+
+/// Converts the values of a key value list into a map
+pub fn try_into_map<K: Eq + Hash + From<String>, T: TryFrom<AnyValue, Error = TypeMismatch>>(
+    value: AnyValue,
+) -> Result<HashMap<K, T>, TypeMismatch> {
+    kvlist_entries(value)?
+        .map(|(key, value)| T::try_from(value).map(|value| (K::from(key), value)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(value: any_value::Value) -> AnyValue {
+        AnyValue::from(value)
+    }
+
+    #[test]
+    fn scalars_round_trip() -> Result<(), TypeMismatch> {
+        assert_eq!(String::try_from(AnyValue::from("text"))?, "text");
+        assert_eq!(
+            String::try_from(AnyValue::from("text".to_string()))?,
+            "text"
+        );
+        assert!(bool::try_from(AnyValue::from(true))?);
+        assert_eq!(i64::try_from(AnyValue::from(-7_i64))?, -7);
+        assert_eq!(i64::try_from(AnyValue::from(-7_i32))?, -7);
+        assert_eq!(
+            i64::try_from(AnyValue::from(u32::MAX))?,
+            i64::from(u32::MAX)
+        );
+        assert_eq!(
+            f64::try_from(AnyValue::from(0.5))?.to_bits(),
+            0.5_f64.to_bits()
+        );
+        assert_eq!(Vec::<u8>::try_from(AnyValue::from(vec![1_u8, 2]))?, [1, 2]);
+        assert_eq!(Vec::<u8>::try_from(AnyValue::from(&[3_u8][..]))?, [3]);
+        Ok(())
+    }
+
+    #[test]
+    fn collections_convert_to_arrays_and_kvlists() -> Result<(), TypeMismatch> {
+        let array = AnyValue::from(vec!["a", "b"]);
+        assert_eq!(array.type_name(), "array");
+        assert_eq!(try_into_vec::<String>(array)?, ["a", "b"]);
+
+        let mut map = HashMap::new();
+        map.insert("one", 1_i64);
+        map.insert("two", 2_i64);
+        let kvlist = AnyValue::from(map);
+        assert_eq!(kvlist.type_name(), "kvlist");
+        assert_eq!(kvlist.get("two").and_then(AnyValue::as_i64), Some(2));
+        assert_eq!(kvlist.get("three"), None);
+        let map: HashMap<String, i64> = try_into_map(kvlist)?;
+        assert_eq!(map.get("one"), Some(&1));
+
+        let sorted: BTreeMap<_, _> = vec![("b", "2"), ("a", "1")].into_iter().collect();
+        let kvlist = AnyValue::from(sorted);
+        let keys: Vec<_> = kvlist
+            .as_kvlist()
+            .unwrap_or_default()
+            .iter()
+            .map(|entry| entry.key.as_str())
+            .collect();
+        assert_eq!(keys, ["a", "b"]);
+
+        // Later entries win over earlier ones with the same key
+        let duplicates = AnyValue::from(KeyValueList {
+            values: vec![key_value("k", 1_i64), key_value("k", 2_i64)],
+        });
+        let map = BTreeMap::<String, AnyValue>::try_from(duplicates)?;
+        assert_eq!(map.get("k").and_then(AnyValue::as_i64), Some(2));
+        Ok(())
+    }
+
+    #[test]
+    fn reports_type_mismatches() {
+        let int = AnyValue::from(1_i64);
+        let mismatch = TypeMismatch {
+            expected: "string",
+            found: "int",
+        };
+        assert_eq!(String::try_from(int.clone()), Err(mismatch.clone()));
+        assert_eq!(mismatch.to_string(), "Expected a string value, found int");
+        assert_eq!(int.as_str(), None);
+        assert_eq!(
+            bool::try_from(AnyValue::default()).map_err(|e| e.found),
+            Err("empty")
+        );
+        assert_eq!(
+            f64::try_from(int.clone()).map_err(|e| e.expected),
+            Err("double")
+        );
+        assert_eq!(
+            Vec::<u8>::try_from(AnyValue::from("x")).map_err(|e| e.found),
+            Err("string")
+        );
+        assert_eq!(
+            try_into_vec::<i64>(int.clone()).map_err(|e| e.expected),
+            Err("array")
+        );
+        assert_eq!(
+            try_into_vec::<i64>(AnyValue::from(vec![value(any_value::Value::BoolValue(
+                true
+            ))]))
+            .map_err(|e| e.expected),
+            Err("int")
+        );
+        assert_eq!(
+            try_into_map::<String, i64>(int).map_err(|e| e.expected),
+            Err("kvlist")
+        );
+    }
+
+    #[test]
+    fn builds_attributes() {
+        let attributes = attributes! { "a" => 1, "b" => "x", };
+        assert_eq!(
+            attributes,
+            vec![
+                key_value("a", value(any_value::Value::IntValue(1))),
+                key_value("b", value(any_value::Value::StringValue("x".to_string()))),
+            ]
+        );
+        assert_eq!(attributes! {}, Vec::<KeyValue>::new());
+    }
+}